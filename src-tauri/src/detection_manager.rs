@@ -136,7 +136,7 @@ pub fn detect_port_scan(manager: &mut SSHManagerRussh) -> Result<PortScanResult,
     let output_result = manager.execute_command(cmd)
         .map_err(|e| format!("执行端口扫描命令失败: {}", e))?;
 
-    let output = output_result.output;
+    let output = output_result.stdout;
     let mut open_ports = Vec::new();
 
     // 解析输出
@@ -173,7 +173,7 @@ pub fn detect_user_audit(manager: &mut SSHManagerRussh) -> Result<UserAuditResul
     let passwd_result = manager.execute_command(cmd)
         .map_err(|e| format!("读取 /etc/passwd 失败: {}", e))?;
 
-    let passwd_output = passwd_result.output;
+    let passwd_output = passwd_result.stdout;
     let mut root_users = Vec::new();
     let mut recent_users = Vec::new();
 
@@ -209,7 +209,7 @@ pub fn detect_user_audit(manager: &mut SSHManagerRussh) -> Result<UserAuditResul
     // 检查空密码用户
     let shadow_cmd = "sudo cat /etc/shadow 2>/dev/null | grep -E '^[^:]+::' | cut -d: -f1";
     let empty_password_output = manager.execute_command(shadow_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let empty_password_users: Vec<String> = empty_password_output
@@ -233,7 +233,7 @@ pub fn detect_backdoor(manager: &mut SSHManagerRussh) -> Result<BackdoorScanResu
         grep -v '^#' | grep -v '^$' | grep -E '(curl|wget|nc|bash|sh|python)'
     "#;
     let cron_output = manager.execute_command(cron_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let suspicious_cron: Vec<String> = cron_output
@@ -248,7 +248,7 @@ pub fn detect_backdoor(manager: &mut SSHManagerRussh) -> Result<BackdoorScanResu
         head -20
     "#;
     let autostart_output = manager.execute_command(autostart_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let suspicious_autostart: Vec<String> = autostart_output
@@ -264,7 +264,7 @@ pub fn detect_backdoor(manager: &mut SSHManagerRussh) -> Result<BackdoorScanResu
         xargs cat 2>/dev/null | grep -v '^#' | grep -v '^$' | wc -l
     "#;
     let keys_count_output = manager.execute_command(ssh_keys_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let mut suspicious_ssh_keys = Vec::new();
@@ -275,7 +275,7 @@ pub fn detect_backdoor(manager: &mut SSHManagerRussh) -> Result<BackdoorScanResu
                 xargs cat 2>/dev/null | grep -v '^#' | grep -v '^$' | head -5
             "#;
             let keys_output = manager.execute_command(keys_cmd)
-                .map(|r| r.output)
+                .map(|r| r.stdout)
                 .unwrap_or_default();
             suspicious_ssh_keys = keys_output
                 .lines()
@@ -299,7 +299,7 @@ pub fn detect_process_analysis(manager: &mut SSHManagerRussh) -> Result<ProcessA
     let output_result = manager.execute_command(cmd)
         .map_err(|e| format!("获取进程列表失败: {}", e))?;
 
-    let output = output_result.output;
+    let output = output_result.stdout;
     let mut high_resource_processes = Vec::new();
     let suspicious_processes = Vec::new();
 
@@ -342,7 +342,7 @@ pub fn detect_file_permission(manager: &mut SSHManagerRussh) -> Result<FilePermi
     // 查找 SUID 文件
     let suid_cmd = "find / -perm -4000 -type f 2>/dev/null | head -20";
     let suid_output = manager.execute_command(suid_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let suid_files: Vec<String> = suid_output
@@ -356,7 +356,7 @@ pub fn detect_file_permission(manager: &mut SSHManagerRussh) -> Result<FilePermi
         ls -l /etc/passwd /etc/shadow /etc/sudoers 2>/dev/null
     "#;
     let sensitive_output = manager.execute_command(sensitive_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let mut sensitive_file_issues = Vec::new();
@@ -381,7 +381,7 @@ pub fn detect_ssh_audit(manager: &mut SSHManagerRussh) -> Result<SSHAuditResult,
     // 读取 SSH 配置
     let cmd = "cat /etc/ssh/sshd_config 2>/dev/null | grep -v '^#' | grep -v '^$'";
     let output = manager.execute_command(cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let mut permit_root_login = false;
@@ -415,14 +415,9 @@ pub fn detect_log_analysis(manager: &mut SSHManagerRussh) -> Result<LogAnalysisR
         grep -i 'failed password' /var/log/auth.log /var/log/secure 2>/dev/null | wc -l
     "#;
     let brute_force_count_result = manager.execute_command(brute_force_cmd)
-        .unwrap_or_else(|_| crate::ssh_manager_russh::TerminalOutput {
-            command: brute_force_cmd.to_string(),
-            output: "0".to_string(),
-            exit_code: Some(0),
-            timestamp: chrono::Utc::now(),
-        });
+        .unwrap_or_else(|_| crate::ssh_manager_russh::TerminalOutput::new(brute_force_cmd, "0", Some(0)));
 
-    let brute_force_count = brute_force_count_result.output;
+    let brute_force_count = brute_force_count_result.stdout;
     let attempts = brute_force_count.trim().parse::<u32>().unwrap_or(0);
 
     // 获取详情
@@ -430,7 +425,7 @@ pub fn detect_log_analysis(manager: &mut SSHManagerRussh) -> Result<LogAnalysisR
         grep -i 'failed password' /var/log/auth.log /var/log/secure 2>/dev/null | tail -5
     "#;
     let details_output = manager.execute_command(details_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let brute_force_details: Vec<String> = details_output
@@ -444,7 +439,7 @@ pub fn detect_log_analysis(manager: &mut SSHManagerRussh) -> Result<LogAnalysisR
         last -10 2>/dev/null | grep -v 'wtmp begins'
     "#;
     let abnormal_output = manager.execute_command(abnormal_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let abnormal_logins: Vec<String> = abnormal_output
@@ -468,7 +463,7 @@ pub fn detect_firewall_check(manager: &mut SSHManagerRussh) -> Result<FirewallCh
         systemctl is-active iptables firewalld ufw 2>/dev/null | grep -q 'active' && echo 'active' || echo 'inactive'
     "#;
     let status_output = manager.execute_command(status_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_else(|_| "inactive".to_string());
 
     let firewall_active = status_output.trim() == "active";
@@ -478,7 +473,7 @@ pub fn detect_firewall_check(manager: &mut SSHManagerRussh) -> Result<FirewallCh
         iptables -L -n 2>/dev/null | head -20 || firewall-cmd --list-all 2>/dev/null || ufw status 2>/dev/null
     "#;
     let rules_output = manager.execute_command(rules_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_default();
 
     let mut risky_rules = Vec::new();
@@ -506,7 +501,7 @@ pub fn detect_cpu_test(manager: &mut SSHManagerRussh) -> Result<CpuTestResult, S
     let output_result = manager.execute_command(cmd)
         .map_err(|e| format!("获取 CPU 信息失败: {}", e))?;
 
-    let output = output_result.output;
+    let output = output_result.stdout;
     let lines: Vec<&str> = output.lines().collect();
 
     let cores = if let Some(line) = lines.get(0) {
@@ -540,7 +535,7 @@ pub fn detect_memory_test(manager: &mut SSHManagerRussh) -> Result<MemoryTestRes
     let output_result = manager.execute_command(cmd)
         .map_err(|e| format!("获取内存信息失败: {}", e))?;
 
-    let output = output_result.output;
+    let output = output_result.stdout;
     let parts: Vec<&str> = output.split_whitespace().collect();
 
     let total = parts.get(1)
@@ -572,7 +567,7 @@ pub fn detect_disk_test(manager: &mut SSHManagerRussh) -> Result<DiskTestResult,
         rm -f /tmp/test_disk_speed
     "#;
     let output = manager.execute_command(cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_else(|_| "50".to_string());
 
     let speed = output.trim().parse::<f64>().unwrap_or(50.0);
@@ -588,7 +583,7 @@ pub fn detect_network_test(manager: &mut SSHManagerRussh) -> Result<NetworkTestR
     // 测试延迟
     let ping_cmd = "ping -c 3 8.8.8.8 2>/dev/null | grep 'avg' | awk -F'/' '{print $5}'";
     let ping_output = manager.execute_command(ping_cmd)
-        .map(|r| r.output)
+        .map(|r| r.stdout)
         .unwrap_or_else(|_| "10".to_string());
 
     let latency = ping_output.trim().parse::<f64>().unwrap_or(10.0);
@@ -659,7 +654,7 @@ pub fn detect_password_policy(manager: &mut SSHManagerRussh) -> Result<GenericDe
     // 检查 /etc/login.defs 中的密码策略
     let cmd = r#"grep -E "^PASS_MAX_DAYS|^PASS_MIN_DAYS|^PASS_MIN_LEN|^PASS_WARN_AGE" /etc/login.defs 2>/dev/null || echo "NOT_FOUND""#;
     let output_result = manager.execute_command(cmd)?;
-    let output = output_result.output.trim();
+    let output = output_result.stdout.trim();
 
     if output.contains("NOT_FOUND") || output.is_empty() {
         issues.push(SecurityIssue {
@@ -692,7 +687,7 @@ pub fn detect_sudo_config(manager: &mut SSHManagerRussh) -> Result<GenericDetect
     // 检查 sudoers 文件中的 NOPASSWD 配置
     let cmd = r#"sudo grep -r "NOPASSWD" /etc/sudoers /etc/sudoers.d/ 2>/dev/null || echo "NO_NOPASSWD""#;
     let output_result = manager.execute_command(cmd)?;
-    let output = output_result.output.trim();
+    let output = output_result.stdout.trim();
 
     if !output.contains("NO_NOPASSWD") && output.contains("NOPASSWD") {
         issues.push(SecurityIssue {
@@ -714,7 +709,7 @@ pub fn detect_pam_config(manager: &mut SSHManagerRussh) -> Result<GenericDetecti
     // 检查 PAM 密码复杂度模块
     let cmd = r#"grep -r "pam_pwquality\|pam_cracklib" /etc/pam.d/ 2>/dev/null || echo "NOT_CONFIGURED""#;
     let output_result = manager.execute_command(cmd)?;
-    let output = output_result.output.trim();
+    let output = output_result.stdout.trim();
 
     if output.contains("NOT_CONFIGURED") {
         issues.push(SecurityIssue {
@@ -736,7 +731,7 @@ pub fn detect_account_lockout(manager: &mut SSHManagerRussh) -> Result<GenericDe
     // 检查 PAM 账号锁定模块
     let cmd = r#"grep "pam_faillock\|pam_tally" /etc/pam.d/system-auth /etc/pam.d/password-auth /etc/pam.d/common-auth 2>/dev/null || echo "NOT_CONFIGURED""#;
     let output_result = manager.execute_command(cmd)?;
-    let output = output_result.output.trim();
+    let output = output_result.stdout.trim();
 
     if output.contains("NOT_CONFIGURED") {
         issues.push(SecurityIssue {
@@ -758,12 +753,12 @@ pub fn detect_selinux_status(manager: &mut SSHManagerRussh) -> Result<GenericDet
     // 检查 SELinux 状态
     let selinux_cmd = "getenforce 2>/dev/null || echo 'NOT_INSTALLED'";
     let selinux_result = manager.execute_command(selinux_cmd)?;
-    let selinux_status = selinux_result.output.trim();
+    let selinux_status = selinux_result.stdout.trim();
 
     // 检查 AppArmor 状态
     let apparmor_cmd = "aa-status 2>/dev/null || echo 'NOT_INSTALLED'";
     let apparmor_result = manager.execute_command(apparmor_cmd)?;
-    let apparmor_status = apparmor_result.output.trim();
+    let apparmor_status = apparmor_result.stdout.trim();
 
     if selinux_status.contains("NOT_INSTALLED") && apparmor_status.contains("NOT_INSTALLED") {
         issues.push(SecurityIssue {
@@ -801,7 +796,7 @@ pub fn detect_kernel_params(manager: &mut SSHManagerRussh) -> Result<GenericDete
     for (param, expected, desc) in params_to_check {
         let cmd = format!("sysctl {} 2>/dev/null || echo 'NOT_SET'", param);
         let result = manager.execute_command(&cmd)?;
-        let output = result.output.trim();
+        let output = result.stdout.trim();
 
         if output.contains("NOT_SET") || !output.contains(&format!("= {}", expected)) {
             issues.push(SecurityIssue {
@@ -833,7 +828,7 @@ pub fn detect_system_updates(manager: &mut SSHManagerRussh) -> Result<GenericDet
     "#;
 
     let result = manager.execute_command(check_cmd)?;
-    let count: usize = result.output.trim().parse().unwrap_or(0);
+    let count: usize = result.stdout.trim().parse().unwrap_or(0);
 
     if count > 0 {
         let severity = if count > 50 { "high" } else if count > 20 { "medium" } else { "low" };
@@ -858,7 +853,7 @@ pub fn detect_unnecessary_services(manager: &mut SSHManagerRussh) -> Result<Gene
 
     let cmd = "systemctl list-units --type=service --state=running --no-pager 2>/dev/null | awk '{print $1}' || service --status-all 2>/dev/null";
     let result = manager.execute_command(cmd)?;
-    let running_services = result.output.to_lowercase();
+    let running_services = result.stdout.to_lowercase();
 
     for service in unnecessary_services {
         if running_services.contains(service) {
@@ -882,7 +877,7 @@ pub fn detect_auto_start_services(manager: &mut SSHManagerRussh) -> Result<Gener
     // 获取所有启用的服务
     let cmd = "systemctl list-unit-files --type=service --state=enabled --no-pager 2>/dev/null | wc -l || echo '0'";
     let result = manager.execute_command(cmd)?;
-    let count: usize = result.output.trim().parse().unwrap_or(0);
+    let count: usize = result.stdout.trim().parse().unwrap_or(0);
 
     if count > 30 {
         issues.push(SecurityIssue {
@@ -904,7 +899,7 @@ pub fn detect_audit_config(manager: &mut SSHManagerRussh) -> Result<GenericDetec
     // 检查 auditd 服务状态
     let cmd = "systemctl is-active auditd 2>/dev/null || service auditd status 2>/dev/null || echo 'NOT_RUNNING'";
     let result = manager.execute_command(cmd)?;
-    let status = result.output.trim();
+    let status = result.stdout.trim();
 
     if status.contains("NOT_RUNNING") || status.contains("inactive") {
         issues.push(SecurityIssue {
@@ -927,7 +922,7 @@ pub fn detect_history_audit(manager: &mut SSHManagerRussh) -> Result<GenericDete
     let suspicious_patterns = vec!["wget http", "curl http", "nc -", "bash -i", "/dev/tcp", "base64 -d"];
     let cmd = "cat ~/.bash_history 2>/dev/null | tail -100";
     let result = manager.execute_command(cmd)?;
-    let history = result.output.to_lowercase();
+    let history = result.stdout.to_lowercase();
 
     for pattern in suspicious_patterns {
         if history.contains(&pattern.to_lowercase()) {
@@ -952,7 +947,7 @@ pub fn detect_ntp_config(manager: &mut SSHManagerRussh) -> Result<GenericDetecti
     // 检查时间同步服务
     let cmd = "systemctl is-active chronyd ntpd systemd-timesyncd 2>/dev/null || echo 'NONE_ACTIVE'";
     let result = manager.execute_command(cmd)?;
-    let output = result.output;
+    let output = result.stdout;
 
     let has_active_ntp = output.lines().any(|line| line.trim() == "active");
 
@@ -976,7 +971,7 @@ pub fn detect_dns_config(manager: &mut SSHManagerRussh) -> Result<GenericDetecti
     // 检查 DNS 配置
     let cmd = "cat /etc/resolv.conf 2>/dev/null | grep -v '^#' | grep nameserver || echo 'NO_DNS'";
     let result = manager.execute_command(cmd)?;
-    let output = result.output.trim();
+    let output = result.stdout.trim();
 
     if output.contains("NO_DNS") || output.is_empty() {
         issues.push(SecurityIssue {
@@ -19,7 +19,7 @@ pub async fn sftp_file_analysis_independent(
 ) -> Result<serde_json::Value, String> {
     // 执行分析（使用共享 session）
     let result = {
-        let mut ssh_manager = state.ssh_manager.lock().unwrap();
+        let mut ssh_manager = state.ssh_manager.lock().unwrap().clone();
         execute_analysis_action(&mut ssh_manager, &action, &file_path)?
     };
 
@@ -42,7 +42,7 @@ pub async fn sftp_file_analysis(
     // 在 async 函数中，我们需要避免持有 MutexGuard 跨越 await 点
     // 所以我们在这里同步执行所有操作
     let result = {
-        let mut ssh_manager = state.ssh_manager.lock().unwrap();
+        let mut ssh_manager = state.ssh_manager.lock().unwrap().clone();
 
         match action.as_str() {
             // 基础信息模块
@@ -97,7 +97,7 @@ pub async fn sftp_file_analysis(
 fn execute_ssh_command(ssh_manager: &mut SSHManagerRussh, command: &str) -> Result<String, String> {
     ssh_manager
         .execute_command(command)
-        .map(|output| output.output.trim().to_string())
+        .map(|output| output.stdout.trim().to_string())
         .map_err(|e| format!("执行命令失败: {}", e))
 }
 
@@ -0,0 +1,478 @@
+// SSH 主机密钥管理
+// 以 OpenSSH known_hosts 格式持久化服务器公钥，实现首次信任 (TOFU) 与密钥变更检测
+
+use crate::types::{AppDataPaths, LovelyResError, LovelyResResult};
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use russh::keys::{HashAlg, PublicKey, PublicKeyBase64};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// 串行化对 known_hosts 的读改写，避免并发连接的首次信任互相覆盖
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// known_hosts 中的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownHostEntry {
+    pub host: String, // 哈希主机名 (HashKnownHosts) 无法还原，保留 "|1|…" 原文
+    pub port: u16,
+    pub key_type: String,
+    pub fingerprint: String, // SHA256:xxxx
+    pub public_key: String,  // "<算法> <base64>" 形式，可直接用于固定 (pin)
+    pub line: usize,
+    #[serde(default)]
+    pub revoked: bool, // @revoked 标记的密钥，连接时拒绝
+}
+
+/// 服务器主机密钥信息（用于首次连接前在界面上展示指纹）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostKeyInfo {
+    pub host: String,
+    pub port: u16,
+    pub key_type: String,
+    pub fingerprint: String,
+    pub public_key: String,
+    pub status: String, // "trusted", "unknown", "mismatch", "revoked"
+    pub known_fingerprints: Vec<String>,
+}
+
+/// 主机密钥校验结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostKeyVerification {
+    /// 与已记录的密钥一致
+    Trusted,
+    /// 主机尚未记录
+    Unknown,
+    /// 主机已记录但密钥不同
+    Mismatch { known_fingerprints: Vec<String> },
+    /// 密钥被 @revoked 标记吊销，无论主机是否记录过都拒绝
+    Revoked,
+}
+
+/// 计算公钥的 SHA256 指纹
+pub fn fingerprint(key: &PublicKey) -> String {
+    key.fingerprint(HashAlg::Sha256).to_string()
+}
+
+/// 将公钥编码为 "<算法> <base64>" 形式
+pub fn encode_public_key(key: &PublicKey) -> String {
+    format!("{} {}", key.algorithm().as_str(), key.public_key_base64())
+}
+
+/// 解析 "<算法> <base64> [注释]" 形式的公钥
+pub fn parse_public_key(public_key: &str) -> LovelyResResult<PublicKey> {
+    let mut parts = public_key.split_whitespace();
+    let base64 = match (parts.next(), parts.next()) {
+        (Some(_), Some(b64)) => b64,
+        (Some(b64), None) => b64,
+        _ => return Err(LovelyResError::InvalidInput("公钥为空".to_string())),
+    };
+    russh::keys::parse_public_key_base64(base64)
+        .map_err(|e| LovelyResError::InvalidInput(format!("无法解析公钥: {}", e)))
+}
+
+/// known_hosts 主机字段，非 22 端口使用 "[host]:port" 形式
+fn host_pattern(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+/// 将主机字段拆解为 (host, port)
+fn split_host_pattern(pattern: &str) -> (String, u16) {
+    if let Some(rest) = pattern.strip_prefix('[') {
+        if let Some((host, port)) = rest.split_once("]:") {
+            if let Ok(port) = port.parse() {
+                return (host.to_string(), port);
+            }
+        }
+    }
+    (pattern.to_string(), 22)
+}
+
+/// 主机字段是否指向该主机；"|1|salt|hash" 形式按 OpenSSH 的做法比较
+/// HMAC-SHA1(salt, "host" 或 "[host]:port")，也接受 list 返回的哈希原文
+fn host_matches(entry: &str, pattern: &str) -> bool {
+    if entry == pattern {
+        return true;
+    }
+    let Some(hashed) = entry.strip_prefix("|1|") else {
+        return false;
+    };
+    let Some((salt, hash)) = hashed.split_once('|') else {
+        return false;
+    };
+    let (Ok(salt), Ok(hash)) = (general_purpose::STANDARD.decode(salt), general_purpose::STANDARD.decode(hash)) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<sha1::Sha1>::new_from_slice(&salt) else {
+        return false;
+    };
+    mac.update(pattern.as_bytes());
+    mac.verify_slice(&hash).is_ok()
+}
+
+/// 解析后的单行记录
+struct ParsedLine {
+    hosts: Vec<String>,
+    key: PublicKey,
+    revoked: bool,
+}
+
+impl ParsedLine {
+    /// 主机字段是否包含该主机；吊销记录常用 "*" 适用于所有主机
+    fn matches(&self, pattern: &str) -> bool {
+        self.hosts.iter().any(|h| host_matches(h, pattern) || (self.revoked && h == "*"))
+    }
+}
+
+/// 解析一行 known_hosts，注释、空行以及 @revoked 以外的标记行返回 None
+fn parse_line(line: &str) -> Option<ParsedLine> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (line, revoked) = match line.strip_prefix("@revoked") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => (rest.trim_start(), true),
+        _ if line.starts_with('@') => return None, // @cert-authority 暂不支持
+        _ => (line, false),
+    };
+
+    let mut parts = line.split_whitespace();
+    let hosts = parts.next()?;
+    let _key_type = parts.next()?;
+    let key = russh::keys::parse_public_key_base64(parts.next()?).ok()?;
+
+    let hosts = hosts.split(',').map(|h| h.to_string()).collect();
+    Some(ParsedLine { hosts, key, revoked })
+}
+
+/// known_hosts 存储
+pub struct KnownHostsStore {
+    path: PathBuf,
+}
+
+impl KnownHostsStore {
+    /// 使用应用数据目录下的 known_hosts 文件
+    pub fn new() -> LovelyResResult<Self> {
+        let data_paths =
+            AppDataPaths::new().map_err(|e| LovelyResError::ConfigError(e.to_string()))?;
+        Ok(Self::with_path(data_paths.known_hosts_file))
+    }
+
+    /// 使用指定路径的 known_hosts 文件
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn read_lines(&self) -> LovelyResResult<Vec<String>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .map_err(|e| LovelyResError::FileError(format!("读取 known_hosts 失败: {}", e)))?;
+        Ok(content.lines().map(|l| l.to_string()).collect())
+    }
+
+    fn write_lines(&self, lines: &[String]) -> LovelyResResult<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| LovelyResError::FileError(format!("创建目录失败: {}", e)))?;
+        }
+        let mut content = lines.join("\n");
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        // 先写临时文件再重命名，写到一半中断也不会留下截断的 known_hosts
+        let temp = self.path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temp);
+                LovelyResError::FileError(format!("写入 known_hosts 失败: {}", e))
+            })
+    }
+
+    /// 列出所有记录
+    pub fn list(&self) -> LovelyResResult<Vec<KnownHostEntry>> {
+        let mut entries = Vec::new();
+        for (index, line) in self.read_lines()?.iter().enumerate() {
+            if let Some(parsed) = parse_line(line) {
+                for pattern in &parsed.hosts {
+                    let (host, port) = split_host_pattern(pattern);
+                    entries.push(KnownHostEntry {
+                        host,
+                        port,
+                        key_type: parsed.key.algorithm().as_str().to_string(),
+                        fingerprint: fingerprint(&parsed.key),
+                        public_key: encode_public_key(&parsed.key),
+                        line: index + 1,
+                        revoked: parsed.revoked,
+                    });
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// 获取主机已记录（未吊销）的全部公钥
+    pub fn host_keys(&self, host: &str, port: u16) -> LovelyResResult<Vec<PublicKey>> {
+        self.matching_keys(host, port, false)
+    }
+
+    fn matching_keys(&self, host: &str, port: u16, revoked: bool) -> LovelyResResult<Vec<PublicKey>> {
+        let pattern = host_pattern(host, port);
+        Ok(self
+            .read_lines()?
+            .iter()
+            .filter_map(|line| parse_line(line))
+            .filter(|parsed| parsed.revoked == revoked && parsed.matches(&pattern))
+            .map(|parsed| parsed.key)
+            .collect())
+    }
+
+    /// 校验服务器公钥
+    pub fn verify(&self, host: &str, port: u16, key: &PublicKey) -> LovelyResResult<HostKeyVerification> {
+        let revoked = self.matching_keys(host, port, true)?;
+        if revoked.iter().any(|k| k.key_data() == key.key_data()) {
+            return Ok(HostKeyVerification::Revoked);
+        }
+        let known = self.host_keys(host, port)?;
+        if known.is_empty() {
+            return Ok(HostKeyVerification::Unknown);
+        }
+        if known.iter().any(|k| k.key_data() == key.key_data()) {
+            return Ok(HostKeyVerification::Trusted);
+        }
+        Ok(HostKeyVerification::Mismatch {
+            known_fingerprints: known.iter().map(fingerprint).collect(),
+        })
+    }
+
+    /// 追加一条记录
+    pub fn add(&self, host: &str, port: u16, key: &PublicKey) -> LovelyResResult<()> {
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        self.add_locked(host, port, key)
+    }
+
+    fn add_locked(&self, host: &str, port: u16, key: &PublicKey) -> LovelyResResult<()> {
+        let mut lines = self.read_lines()?;
+        lines.push(format!("{} {}", host_pattern(host, port), encode_public_key(key)));
+        self.write_lines(&lines)
+    }
+
+    /// 删除主机的全部记录（吊销记录保留），返回删除的条目数
+    pub fn remove(&self, host: &str, port: u16) -> LovelyResResult<usize> {
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        self.remove_locked(host, port)
+    }
+
+    fn remove_locked(&self, host: &str, port: u16) -> LovelyResResult<usize> {
+        let pattern = host_pattern(host, port);
+        let mut removed = 0;
+        let mut lines = Vec::new();
+
+        for line in self.read_lines()? {
+            let Some(parsed) = parse_line(&line) else {
+                lines.push(line);
+                continue;
+            };
+            if parsed.revoked || !parsed.matches(&pattern) {
+                lines.push(line);
+                continue;
+            }
+
+            removed += 1;
+            // 同一行记录了多个主机时只移除当前主机
+            let others: Vec<&String> = parsed.hosts.iter().filter(|h| !host_matches(h, &pattern)).collect();
+            if !others.is_empty() {
+                let hosts = others.iter().map(|h| h.as_str()).collect::<Vec<_>>().join(",");
+                lines.push(format!("{} {}", hosts, encode_public_key(&parsed.key)));
+            }
+        }
+
+        self.write_lines(&lines)?;
+        Ok(removed)
+    }
+
+    /// 固定主机公钥：替换该主机已有的全部记录
+    pub fn pin(&self, host: &str, port: u16, key: &PublicKey) -> LovelyResResult<()> {
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        self.remove_locked(host, port)?;
+        self.add_locked(host, port, key)
+    }
+
+    /// 生成主机密钥信息
+    pub fn describe(&self, host: &str, port: u16, key: &PublicKey) -> LovelyResResult<HostKeyInfo> {
+        let (status, known_fingerprints) = match self.verify(host, port, key)? {
+            HostKeyVerification::Trusted => ("trusted", vec![fingerprint(key)]),
+            HostKeyVerification::Unknown => ("unknown", Vec::new()),
+            HostKeyVerification::Mismatch { known_fingerprints } => ("mismatch", known_fingerprints),
+            HostKeyVerification::Revoked => ("revoked", Vec::new()),
+        };
+
+        Ok(HostKeyInfo {
+            host: host.to_string(),
+            port,
+            key_type: key.algorithm().as_str().to_string(),
+            fingerprint: fingerprint(key),
+            public_key: encode_public_key(key),
+            status: status.to_string(),
+            known_fingerprints,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIJcgKp5RzZ7drY5XXxvE+L6G+4oDiXFKHI/K8gtfT+JW";
+    const KEY_B: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIJoRsbq76KcUqhyrBCOR9/LbTdVHYZpZnC1YD/is/r9q";
+
+    fn temp_store() -> KnownHostsStore {
+        let path = std::env::temp_dir().join(format!("lovelyres_known_hosts_{}", uuid::Uuid::new_v4()));
+        KnownHostsStore::with_path(path)
+    }
+
+    #[test]
+    fn test_trust_on_first_use_and_mismatch() {
+        let store = temp_store();
+        let key_a = parse_public_key(KEY_A).unwrap();
+        let key_b = parse_public_key(KEY_B).unwrap();
+
+        assert_eq!(store.verify("10.0.0.1", 2222, &key_a).unwrap(), HostKeyVerification::Unknown);
+        store.add("10.0.0.1", 2222, &key_a).unwrap();
+        assert_eq!(store.verify("10.0.0.1", 2222, &key_a).unwrap(), HostKeyVerification::Trusted);
+        assert_eq!(store.verify("10.0.0.1", 22, &key_a).unwrap(), HostKeyVerification::Unknown);
+
+        match store.verify("10.0.0.1", 2222, &key_b).unwrap() {
+            HostKeyVerification::Mismatch { known_fingerprints } => {
+                assert_eq!(known_fingerprints, vec![fingerprint(&key_a)]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let content = fs::read_to_string(&store.path).unwrap();
+        assert_eq!(content, format!("[10.0.0.1]:2222 {}\n", KEY_A));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn test_pin_and_remove_keep_other_hosts() {
+        let store = temp_store();
+        let key_a = parse_public_key(KEY_A).unwrap();
+        let key_b = parse_public_key(KEY_B).unwrap();
+        store
+            .write_lines(&[
+                "# comment".to_string(),
+                format!("web1,web2 {}", KEY_A),
+            ])
+            .unwrap();
+
+        store.pin("web1", 22, &key_b).unwrap();
+        assert_eq!(store.verify("web1", 22, &key_b).unwrap(), HostKeyVerification::Trusted);
+        assert_eq!(store.verify("web2", 22, &key_a).unwrap(), HostKeyVerification::Trusted);
+
+        let entries = store.list().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.port == 22 && e.key_type == "ssh-ed25519"));
+
+        assert_eq!(store.remove("web2", 22).unwrap(), 1);
+        assert_eq!(store.verify("web2", 22, &key_a).unwrap(), HostKeyVerification::Unknown);
+        assert!(fs::read_to_string(&store.path).unwrap().starts_with("# comment\n"));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn test_revoked_keys_are_refused() {
+        let store = temp_store();
+        let key_a = parse_public_key(KEY_A).unwrap();
+        let key_b = parse_public_key(KEY_B).unwrap();
+        store
+            .write_lines(&[
+                format!("web1 {}", KEY_A),
+                format!("@revoked web1 {}", KEY_A),
+                format!("@revoked * {}", KEY_B),
+                format!("@cert-authority *.example.com {}", KEY_B),
+            ])
+            .unwrap();
+
+        assert_eq!(store.verify("web1", 22, &key_a).unwrap(), HostKeyVerification::Revoked);
+        assert_eq!(store.verify("web2", 22, &key_a).unwrap(), HostKeyVerification::Unknown);
+        assert_eq!(store.verify("db", 2222, &key_b).unwrap(), HostKeyVerification::Revoked);
+        assert_eq!(store.describe("web1", 22, &key_a).unwrap().status, "revoked");
+
+        // 重新固定不会清除吊销记录
+        store.pin("web1", 22, &key_a).unwrap();
+        assert_eq!(store.verify("web1", 22, &key_a).unwrap(), HostKeyVerification::Revoked);
+        assert_eq!(store.list().unwrap().iter().filter(|e| e.revoked).count(), 2);
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn test_hashed_hosts_are_matched() {
+        let store = temp_store();
+        let key_a = parse_public_key(KEY_A).unwrap();
+        let key_b = parse_public_key(KEY_B).unwrap();
+        // ssh-keygen -H 的输出：salt 为 0x01..0x14，分别对应 "web1" 与 "[db.example.com]:2222"
+        store
+            .write_lines(&[
+                format!("|1|AQIDBAUGBwgJCgsMDQ4PEBESExQ=|AbVSdRCtB9UValAgM3m3OJ4Cz9E= {}", KEY_A),
+                format!("|1|AQIDBAUGBwgJCgsMDQ4PEBESExQ=|iYa4+uOFt89dlRDRVGHvdqFn7NM=,web2 {}", KEY_B),
+            ])
+            .unwrap();
+
+        assert_eq!(store.verify("web1", 22, &key_a).unwrap(), HostKeyVerification::Trusted);
+        assert!(matches!(store.verify("web1", 22, &key_b).unwrap(), HostKeyVerification::Mismatch { .. }));
+        assert_eq!(store.verify("web1", 2222, &key_a).unwrap(), HostKeyVerification::Unknown);
+        assert_eq!(store.verify("db.example.com", 2222, &key_b).unwrap(), HostKeyVerification::Trusted);
+        assert_eq!(store.list().unwrap().len(), 3);
+
+        // 删除只去掉哈希后与该主机一致的字段
+        assert_eq!(store.remove("db.example.com", 2222).unwrap(), 1);
+        assert_eq!(store.verify("db.example.com", 2222, &key_b).unwrap(), HostKeyVerification::Unknown);
+        assert_eq!(store.verify("web2", 22, &key_b).unwrap(), HostKeyVerification::Trusted);
+        let hashed = store.list().unwrap().into_iter().find(|e| e.host.starts_with("|1|")).unwrap();
+        assert_eq!(store.remove(&hashed.host, hashed.port).unwrap(), 1);
+        assert_eq!(store.verify("web1", 22, &key_a).unwrap(), HostKeyVerification::Unknown);
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn test_concurrent_adds_keep_every_entry() {
+        let store = std::sync::Arc::new(temp_store());
+        let key_a = parse_public_key(KEY_A).unwrap();
+        let workers: Vec<_> = (0..8)
+            .map(|i| {
+                let store = store.clone();
+                let key = key_a.clone();
+                std::thread::spawn(move || store.add(&format!("host{}", i), 22, &key).unwrap())
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(store.list().unwrap().len(), 8);
+        let dir = store.path.parent().unwrap();
+        let name = store.path.file_name().unwrap().to_string_lossy().into_owned();
+        let leftovers = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with(&format!("{}.tmp-", name)))
+            .count();
+        assert_eq!(leftovers, 0);
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn test_split_host_pattern() {
+        assert_eq!(split_host_pattern("[example.com]:2222"), ("example.com".to_string(), 2222));
+        assert_eq!(split_host_pattern("example.com"), ("example.com".to_string(), 22));
+    }
+}
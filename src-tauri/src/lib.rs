@@ -7,10 +7,24 @@ pub mod detection_manager;
 pub mod device_info;
 pub mod docker_manager;
 pub mod file_analysis;
+pub mod known_hosts;
+pub mod local_terminal;
 pub mod log_analysis;
 pub mod settings;
 pub mod ssh_connection_manager;
 pub mod ssh_manager_russh;  // 使用 russh 实现的 SSH 管理器
+pub mod ssh_port_forward;
+pub mod ssh_interactive_auth;
+pub mod ssh_file_transfer;
+pub mod ssh_file_sync;
+pub mod ssh_file_range;
+pub mod ssh_file_watch;
+pub mod ssh_archive;
+pub mod ssh_zmodem;
+pub mod ssh_terminal_recording;
+pub mod ssh_terminal_encoding;
+pub mod ssh_terminal_group;
+pub mod ssh_terminal_scrollback;
 pub mod theme_manager;
 pub mod types;
 pub mod window_manager;
@@ -23,7 +37,7 @@ pub mod ssh_flow_control;
 pub mod ssh_manager;
 pub mod ssh_thread_manager;
 
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tauri::{Manager, State};
 
 use tauri::Emitter;
@@ -36,6 +50,7 @@ pub struct AppState {
     pub ssh_client: Mutex<ssh_client::SSHClient>,
     pub ssh_manager: Mutex<ssh_manager_russh::SSHManagerRussh>,  // 使用新的 russh 管理器
     pub ssh_terminal_creation_lock: Mutex<()>,
+    pub ssh_auth_broker: Arc<ssh_interactive_auth::InteractiveAuthBroker>, // 键盘交互认证（OTP/2FA）待回答的提示
 }
 
 // 窗口控制命令
@@ -123,6 +138,13 @@ async fn save_app_settings(
     let mut settings = state.settings.lock().unwrap();
     *settings = new_settings.clone();
 
+    // 断线重连次数立即生效
+    state
+        .ssh_manager
+        .lock()
+        .unwrap()
+        .set_max_retries(new_settings.ssh.max_retries);
+
     // 保存到文件
     settings::save_settings(&new_settings)?;
 
@@ -631,6 +653,7 @@ async fn ssh_connect_with_auth(
         is_connected: false,
         last_connected: None,
         tags: None,
+        jump_hosts: Vec::new(),
     };
 
     let mut client = state.ssh_client.lock().unwrap();
@@ -685,6 +708,7 @@ async fn ssh_test_connection(
         is_connected: false,
         last_connected: None,
         tags: None,
+        jump_hosts: Vec::new(),
     };
 
     match ssh_client::SSHClient::test_connection(&connection, password.as_deref()) {
@@ -717,13 +741,21 @@ async fn ssh_disconnect(state: State<'_, AppState>) -> Result<(), String> {
 
 // 新的SSH/SFTP命令
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn ssh_connect_direct(
     host: String,
     port: u16,
     username: String,
     password: String,
+    connection_id: Option<String>,
+    key_path: Option<String>,
+    key_passphrase: Option<String>,
+    certificate_path: Option<String>,
+    use_agent: Option<bool>,
+    isolated: Option<bool>,
+    window: tauri::Window,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<String, String> {
     println!("=== [Tauri] ssh_connect_direct 被调用 ===");
     println!("  Host: {}", host);
     println!("  Port: {}", port);
@@ -731,35 +763,201 @@ async fn ssh_connect_direct(
     println!("  Password Length: {}", password.len());
     println!("  Password (masked): {}***", if password.len() > 3 { &password[..3] } else { "" });
     
-    let manager = state.ssh_manager.lock().unwrap();
-    let result = manager.connect(&host, port, &username, Some(&password), None);
+    // 已保存的连接可能配置了跳板机链
+    let jump_hosts = match &connection_id {
+        Some(id) => {
+            let connection_manager = state.ssh_connection_manager.lock().unwrap();
+            connection_manager.resolve_jump_hosts(id)?
+        }
+        None => Vec::new(),
+    };
+    if !jump_hosts.is_empty() {
+        println!("  Jump Hosts: {}", jump_hosts.len());
+    }
+
+    // 未传入密码短语时使用已保存连接中该账号的密码短语
+    let key_passphrase = match (key_passphrase.filter(|p| !p.is_empty()), &connection_id) {
+        (Some(passphrase), _) => Some(passphrase),
+        (None, Some(id)) => {
+            let connection_manager = state.ssh_connection_manager.lock().unwrap();
+            connection_manager.stored_key_passphrase(id, &username)?
+        }
+        (None, None) => None,
+    };
+
+    let credentials = ssh_manager_russh::SSHCredentials {
+        password: if password.is_empty() { None } else { Some(password) },
+        private_key: key_path.filter(|p| !p.is_empty()),
+        key_passphrase,
+        certificate: certificate_path.filter(|p| !p.is_empty()),
+        use_agent: use_agent.unwrap_or(false),
+        prompter: Some(ssh_interactive_auth::InteractivePrompter::new(
+            window,
+            state.ssh_auth_broker.clone(),
+        )),
+    };
+    println!("  Auth: agent={}, key={}", credentials.use_agent, credentials.private_key.is_some());
+
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    // isolated: 打开独立会话（例如专用于耗时检测），不切换当前会话；否则切换为当前会话，原来的当前会话若已没有终端、端口转发和文件监视则断开
+    let result = if isolated.unwrap_or(false) {
+        manager.open_session(&host, port, &username, credentials, jump_hosts)
+    } else {
+        manager.connect_via(&host, port, &username, credentials, jump_hosts)
+    };
     
     match &result {
-        Ok(_) => println!("✅ [Tauri] SSH 连接成功"),
+        Ok(session_id) => println!("✅ [Tauri] SSH 连接成功, 会话ID: {}", session_id),
         Err(e) => println!("❌ [Tauri] SSH 连接失败: {}", e),
     }
     
-    result.map(|_| ()).map_err(|e| e.to_string())
+    result
 }
 
 #[tauri::command]
 async fn ssh_disconnect_direct(state: State<'_, AppState>) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager.disconnect().map_err(|e| e.to_string())
 }
 
+// SSH 多会话管理命令
+
+#[tauri::command]
+async fn ssh_list_sessions(state: State<'_, AppState>) -> Result<Vec<ssh_manager_russh::ConnectionInfo>, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    Ok(manager.list_session_infos())
+}
+
+#[tauri::command]
+async fn ssh_switch_session(session_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    println!("🔀 [Tauri] 切换当前会话: {}", session_id);
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.set_current_session_id(&session_id)
+}
+
+#[tauri::command]
+async fn ssh_close_session(session_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    println!("🔌 [Tauri] 关闭会话: {}", session_id);
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.disconnect_session(&session_id)
+}
+
+/// 回答键盘交互认证提示（answers 为空表示取消认证）
+#[tauri::command]
+async fn ssh_keyboard_interactive_respond(
+    request_id: String,
+    answers: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    println!("🔐 [Tauri] 收到键盘交互认证回答: {}", request_id);
+    state.ssh_auth_broker.respond(&request_id, answers)
+}
+
+// SSH 主机密钥管理命令
+
+/// 获取服务器主机密钥指纹（不进行认证），用于首次连接前确认
+#[tauri::command]
+async fn ssh_scan_host_key(
+    host: String,
+    port: u16,
+    state: State<'_, AppState>,
+) -> Result<known_hosts::HostKeyInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.scan_host_key(&host, port)
+}
+
+/// 列出已信任的主机密钥
+#[tauri::command]
+async fn known_hosts_list() -> Result<Vec<known_hosts::KnownHostEntry>, String> {
+    let store = known_hosts::KnownHostsStore::new()?;
+    store.list().map_err(|e| e.to_string())
+}
+
+/// 删除主机的已信任密钥
+#[tauri::command]
+async fn known_hosts_remove(host: String, port: u16) -> Result<usize, String> {
+    let store = known_hosts::KnownHostsStore::new()?;
+    let removed = store.remove(&host, port)?;
+    println!("🔑 已删除 {}:{} 的 {} 条主机密钥记录", host, port, removed);
+    Ok(removed)
+}
+
+/// 固定主机密钥，替换该主机已有的记录
+#[tauri::command]
+async fn known_hosts_pin(host: String, port: u16, public_key: String) -> Result<(), String> {
+    let store = known_hosts::KnownHostsStore::new()?;
+    let key = known_hosts::parse_public_key(&public_key)?;
+    store.pin(&host, port, &key)?;
+    println!("📌 已固定 {}:{} 的主机密钥: {}", host, port, known_hosts::fingerprint(&key));
+    Ok(())
+}
+
+// SSH 端口转发命令
+
+/// 创建端口转发（local / remote / dynamic），未指定会话时使用当前会话
+#[tauri::command]
+async fn ssh_create_port_forward(
+    kind: ssh_port_forward::PortForwardKind,
+    bind_address: Option<String>,
+    bind_port: u16,
+    target_host: Option<String>,
+    target_port: Option<u16>,
+    session_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ssh_port_forward::PortForwardInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let session_id = match session_id {
+        Some(id) => id,
+        None => manager.get_current_session_id().ok_or("没有活动的 SSH 连接")?,
+    };
+
+    let spec = ssh_port_forward::PortForwardSpec {
+        kind,
+        bind_address: bind_address.unwrap_or_else(|| "127.0.0.1".to_string()),
+        bind_port,
+        target_host,
+        target_port,
+    };
+    let info = manager.create_port_forward(&session_id, spec)?;
+    println!("🔀 已创建端口转发 {:?} {}:{}", info.kind, info.bind_address, info.bind_port);
+    Ok(info)
+}
+
+/// 列出端口转发及流量统计
+#[tauri::command]
+async fn ssh_list_port_forwards(
+    session_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ssh_port_forward::PortForwardInfo>, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    Ok(manager.list_port_forwards(session_id.as_deref()))
+}
+
+/// 关闭端口转发
+#[tauri::command]
+async fn ssh_close_port_forward(
+    forward_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.close_port_forward(&forward_id)
+}
+
 #[tauri::command]
 async fn ssh_execute_command_direct(
     command: String,
     username: Option<String>,
+    options: Option<ssh_manager_russh::ExecOptions>,
     state: State<'_, AppState>,
 ) -> Result<ssh_manager_russh::TerminalOutput, String> {
     let start_time = std::time::Instant::now();
     //println!("[PERF] 右键菜单命令执行开始: \"{}\" 时间: {:?}", command, start_time);
 
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     // 使用仪表盘专用 session 快速执行（右键菜单命令都是快速查询）
-    let result = manager.execute_dashboard_command_as_user(&command, username.as_deref()).map_err(|e| e.to_string());
+    let result = manager
+        .execute_command_as_user_with_options(&command, username.as_deref(), options.unwrap_or_else(ssh_manager_russh::ExecOptions::unlimited))
+        .map_err(|e| e.to_string());
 
     //println!("[PERF] 右键菜单命令执行完成: \"{}\" 总耗时: {:?}", command, start_time.elapsed());
     result
@@ -773,7 +971,7 @@ async fn ssh_execute_dashboard_command_direct(
     let start_time = std::time::Instant::now();
     //println!("[PERF] 仪表盘命令执行开始: \"{}\" 时间: {:?}", command, start_time);
 
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     let result = manager.execute_dashboard_command(&command).map_err(|e| e.to_string());
 
     //println!("[PERF] 仪表盘命令执行完成: \"{}\" 总耗时: {:?}", command, start_time.elapsed());
@@ -784,32 +982,66 @@ async fn ssh_execute_dashboard_command_direct(
 async fn ssh_execute_emergency_command_direct(
     command: String,
     username: Option<String>,
+    options: Option<ssh_manager_russh::ExecOptions>,
     state: State<'_, AppState>,
 ) -> Result<ssh_manager_russh::TerminalOutput, String> {
     let _start_time = std::time::Instant::now();
     //println!("[PERF] 应急响应命令执行开始: \"{}\" 账号: {:?} 时间: {:?}", command, username, _start_time);
 
-    let mut manager = state.ssh_manager.lock().unwrap();
-    let result = if username.is_some() {
-        manager.execute_dashboard_command_as_user(&command, username.as_deref()).map_err(|e| e.to_string())
-    } else {
-        manager.execute_dashboard_command(&command).map_err(|e| e.to_string())
-    };
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
+    let result = manager
+        .execute_command_as_user_with_options(&command, username.as_deref(), options.unwrap_or_else(ssh_manager_russh::ExecOptions::unlimited))
+        .map_err(|e| e.to_string());
 
     //println!("[PERF] 应急响应命令执行完成: \"{}\" 总耗时: {:?}", command, _start_time.elapsed());
     result
 }
 
+/// 流式执行命令，输出通过 ssh_command_output / ssh_command_finished 事件推送，返回任务ID
+#[tauri::command]
+async fn ssh_execute_command_stream(
+    command: String,
+    options: Option<ssh_manager_russh::ExecOptions>,
+    session_id: Option<String>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let options = options.unwrap_or_else(ssh_manager_russh::ExecOptions::unlimited);
+    let job_id = match session_id {
+        Some(session_id) => manager.execute_command_streaming_on_session(&session_id, &command, options, window)?,
+        None => manager.execute_command_streaming(&command, options, window)?,
+    };
+    println!("📡 [Tauri] 流式命令已启动: {} ({})", command, job_id);
+    Ok(job_id)
+}
+
+/// 取消正在执行的命令（cancel_id 与执行时 options.cancel_id 一致）
+#[tauri::command]
+async fn ssh_cancel_command(cancel_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    println!("🛑 [Tauri] 取消命令: {}", cancel_id);
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.cancel_command(&cancel_id)
+}
+
 /// 执行检测报告中AI生成的命令
 #[tauri::command]
 async fn execute_detection_command(
     command: String,
+    options: Option<ssh_manager_russh::ExecOptions>,
+    session_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<ssh_manager_russh::TerminalOutput, String> {
     println!("🤖 [AI命令执行] 开始执行: {}", command);
     
-    let mut manager = state.ssh_manager.lock().unwrap();
-    let result = manager.execute_dashboard_command(&command).map_err(|e| {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let options = options.unwrap_or_else(ssh_manager_russh::ExecOptions::unlimited);
+    // 可指定独立会话运行，避免耗时检测占用交互会话
+    let result = match &session_id {
+        Some(session_id) => manager.execute_command_on_session(session_id, &command, options),
+        None => manager.execute_command_with_options(&command, options),
+    }
+    .map_err(|e| {
         println!("❌ [AI命令执行] 执行失败: {}", e);
         e.to_string()
     });
@@ -835,7 +1067,7 @@ async fn execute_detection_command(
 async fn test_ssh_performance(
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
 
     let test_commands = vec![
         ("echo test", "基础响应测试"),
@@ -876,7 +1108,7 @@ async fn test_ssh_performance(
 async fn diagnose_shell_performance(
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
 
     let mut results = Vec::new();
     results.push("=== Shell性能诊断 ===".to_string());
@@ -939,7 +1171,7 @@ async fn detect_system_type(state: State<'_, AppState>) -> Result<serde_json::Va
 
     println!("🔍 [后端] 开始系统类型检测...");
 
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
 
     if !manager.is_connected() {
         println!("❌ [后端] 没有活动的 SSH 连接");
@@ -956,7 +1188,7 @@ async fn detect_system_type(state: State<'_, AppState>) -> Result<serde_json::Va
         })?;
 
     println!("✅ [后端] os-release 读取成功");
-    let os_release_content = os_release_output.output;
+    let os_release_content = os_release_output.stdout;
 
     // 2. 检测包管理器
     println!("📦 [后端] 检测包管理器...");
@@ -968,7 +1200,7 @@ async fn detect_system_type(state: State<'_, AppState>) -> Result<serde_json::Va
         })?;
 
     println!("✅ [后端] 包管理器检测成功");
-    let package_manager = pkg_mgr_output.output.lines().last().unwrap_or("unknown").trim();
+    let package_manager = pkg_mgr_output.stdout.lines().last().unwrap_or("unknown").trim();
 
     // 3. 检测 init 系统
     println!("⚙️ [后端] 检测 init 系统...");
@@ -981,7 +1213,7 @@ async fn detect_system_type(state: State<'_, AppState>) -> Result<serde_json::Va
 
     println!("✅ [后端] init 系统检测成功");
 
-    let init_output_str = init_output.output.trim().to_lowercase();
+    let init_output_str = init_output.stdout.trim().to_lowercase();
     let init_system = if init_output_str.contains("systemd") {
         "systemd"
     } else if init_output_str.contains("init") {
@@ -1087,84 +1319,84 @@ async fn detect_system_type(state: State<'_, AppState>) -> Result<serde_json::Va
 /// 端口安全扫描
 #[tauri::command]
 async fn detect_port_scan(state: State<'_, AppState>) -> Result<detection_manager::PortScanResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_port_scan(&mut manager)
 }
 
 /// 用户权限审计
 #[tauri::command]
 async fn detect_user_audit(state: State<'_, AppState>) -> Result<detection_manager::UserAuditResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_user_audit(&mut manager)
 }
 
 /// 后门检测
 #[tauri::command]
 async fn detect_backdoor(state: State<'_, AppState>) -> Result<detection_manager::BackdoorScanResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_backdoor(&mut manager)
 }
 
 /// 进程分析
 #[tauri::command]
 async fn detect_process_analysis(state: State<'_, AppState>) -> Result<detection_manager::ProcessAnalysisResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_process_analysis(&mut manager)
 }
 
 /// 文件权限检测
 #[tauri::command]
 async fn detect_file_permission(state: State<'_, AppState>) -> Result<detection_manager::FilePermissionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_file_permission(&mut manager)
 }
 
 /// SSH 安全审计
 #[tauri::command]
 async fn detect_ssh_audit(state: State<'_, AppState>) -> Result<detection_manager::SSHAuditResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_ssh_audit(&mut manager)
 }
 
 /// 日志分析
 #[tauri::command]
 async fn detect_log_analysis(state: State<'_, AppState>) -> Result<detection_manager::LogAnalysisResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_log_analysis(&mut manager)
 }
 
 /// 防火墙检查
 #[tauri::command]
 async fn detect_firewall_check(state: State<'_, AppState>) -> Result<detection_manager::FirewallCheckResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_firewall_check(&mut manager)
 }
 
 /// CPU 测试
 #[tauri::command]
 async fn detect_cpu_test(state: State<'_, AppState>) -> Result<detection_manager::CpuTestResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_cpu_test(&mut manager)
 }
 
 /// 内存测试
 #[tauri::command]
 async fn detect_memory_test(state: State<'_, AppState>) -> Result<detection_manager::MemoryTestResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_memory_test(&mut manager)
 }
 
 /// 磁盘测试
 #[tauri::command]
 async fn detect_disk_test(state: State<'_, AppState>) -> Result<detection_manager::DiskTestResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_disk_test(&mut manager)
 }
 
 /// 网络测试
 #[tauri::command]
 async fn detect_network_test(state: State<'_, AppState>) -> Result<detection_manager::NetworkTestResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_network_test(&mut manager)
 }
 
@@ -1173,115 +1405,120 @@ async fn detect_network_test(state: State<'_, AppState>) -> Result<detection_man
 /// 密码策略检查
 #[tauri::command]
 async fn detect_password_policy(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_password_policy(&mut manager)
 }
 
 /// Sudo 配置审计
 #[tauri::command]
 async fn detect_sudo_config(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_sudo_config(&mut manager)
 }
 
 /// PAM 配置检查
 #[tauri::command]
 async fn detect_pam_config(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_pam_config(&mut manager)
 }
 
 /// 账号锁定策略检查
 #[tauri::command]
 async fn detect_account_lockout(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_account_lockout(&mut manager)
 }
 
 /// SELinux/AppArmor 状态检查
 #[tauri::command]
 async fn detect_selinux_status(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_selinux_status(&mut manager)
 }
 
 /// 内核参数检查
 #[tauri::command]
 async fn detect_kernel_params(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_kernel_params(&mut manager)
 }
 
 /// 系统补丁状态检查
 #[tauri::command]
 async fn detect_system_updates(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_system_updates(&mut manager)
 }
 
 /// 不必要服务检查
 #[tauri::command]
 async fn detect_unnecessary_services(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_unnecessary_services(&mut manager)
 }
 
 /// 自启动服务审计
 #[tauri::command]
 async fn detect_auto_start_services(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_auto_start_services(&mut manager)
 }
 
 /// 审计配置检查
 #[tauri::command]
 async fn detect_audit_config(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_audit_config(&mut manager)
 }
 
 /// 历史命令审计
 #[tauri::command]
 async fn detect_history_audit(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_history_audit(&mut manager)
 }
 
 /// NTP 配置检查
 #[tauri::command]
 async fn detect_ntp_config(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_ntp_config(&mut manager)
 }
 
 /// DNS 配置检查
 #[tauri::command]
 async fn detect_dns_config(state: State<'_, AppState>) -> Result<detection_manager::GenericDetectionResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     detection_manager::detect_dns_config(&mut manager)
 }
 
 // SSH 终端管理命令
 
-/// 创建 SSH 终端会话
+/// 创建 SSH 终端会话；传入 local 时在本机伪终端中启动 shell，不需要 SSH 连接
 #[tauri::command]
 async fn ssh_create_terminal_session(
     window: tauri::Window,
     terminal_id: String,
     cols: u16,
     rows: u16,
+    output: Option<ssh_terminal_encoding::TerminalOutputOptions>,
+    local: Option<local_terminal::LocalShellOptions>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     // 获取终端创建锁，确保原子性
     let _creation_lock = state.ssh_terminal_creation_lock.lock().unwrap();
 
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let output = output.unwrap_or_default();
 
-    if !manager.is_connected() {
-        return Err("没有活动的 SSH 连接".to_string());
-    }
+    let result = match local {
+        Some(options) => manager.create_local_terminal_session(window, &terminal_id, cols as u32, rows as u32, output, options),
+        None if !manager.is_connected() => return Err("没有活动的 SSH 连接".to_string()),
+        None => manager.create_terminal_session(window, &terminal_id, cols as u32, rows as u32, output),
+    };
 
-    match manager.create_terminal_session(window, &terminal_id, cols as u32, rows as u32) {
+    match result {
         Ok(_) => {
             println!("✅ 创建终端会话成功: {}", terminal_id);
             Ok(terminal_id)
@@ -1299,7 +1536,7 @@ async fn ssh_close_terminal_session(
     terminal_id: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
 
     match manager.close_terminal_session(&terminal_id) {
         Ok(_) => {
@@ -1313,12 +1550,12 @@ async fn ssh_close_terminal_session(
     }
 }
 
-/// 关闭所有 SSH 终端会话
+/// 关闭所有终端会话（SSH 终端与本机终端）
 #[tauri::command]
 async fn ssh_close_all_terminal_sessions(
     state: State<'_, AppState>,
 ) -> Result<usize, String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
 
     match manager.close_all_terminal_sessions() {
         Ok(_) => {
@@ -1339,7 +1576,7 @@ async fn ssh_send_input(
     data: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
 
     match manager.send_terminal_input(&terminal_id, data.as_bytes().to_vec()) {
         Ok(_) => Ok(()),
@@ -1350,6 +1587,226 @@ async fn ssh_send_input(
     }
 }
 
+/// 调整终端窗口大小（SSH 与本机终端）
+#[tauri::command]
+async fn ssh_resize_terminal(
+    terminal_id: String,
+    cols: u16,
+    rows: u16,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.resize_terminal(&terminal_id, cols as u32, rows as u32)
+}
+
+// 终端组命令
+
+/// 创建终端组，成员默认全部启用
+#[tauri::command]
+async fn ssh_terminal_group_create(
+    name: String,
+    terminal_ids: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_group::TerminalGroupInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let group = manager.create_terminal_group(&name, terminal_ids);
+    println!("👥 [Tauri] 创建终端组: {} ({} 个终端)", group.name, group.members.len());
+    Ok(group)
+}
+
+#[tauri::command]
+async fn ssh_terminal_group_delete(
+    group_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.delete_terminal_group(&group_id)
+}
+
+#[tauri::command]
+async fn ssh_terminal_group_list(
+    state: State<'_, AppState>,
+) -> Result<Vec<ssh_terminal_group::TerminalGroupInfo>, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    Ok(manager.list_terminal_groups())
+}
+
+#[tauri::command]
+async fn ssh_terminal_group_add_member(
+    group_id: String,
+    terminal_id: String,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_group::TerminalGroupInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.add_terminal_group_member(&group_id, &terminal_id)
+}
+
+#[tauri::command]
+async fn ssh_terminal_group_remove_member(
+    group_id: String,
+    terminal_id: String,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_group::TerminalGroupInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.remove_terminal_group_member(&group_id, &terminal_id)
+}
+
+/// 启用/停用组内某个终端的广播
+#[tauri::command]
+async fn ssh_terminal_group_set_enabled(
+    group_id: String,
+    terminal_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_group::TerminalGroupInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.set_terminal_group_member_enabled(&group_id, &terminal_id, enabled)
+}
+
+/// 把输入广播给组内所有启用的终端；成员断开时发送 ssh_terminal_group_member_lost 事件
+#[tauri::command]
+async fn ssh_terminal_group_send_input(
+    group_id: String,
+    data: String,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_group::BroadcastResult, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let result = manager.broadcast_terminal_input(&group_id, data.as_bytes())?;
+    for failure in &result.failed {
+        println!("❌ 广播到终端失败: {} {}", failure.terminal_id, failure.error);
+    }
+    Ok(result)
+}
+
+/// 设置终端的远程编码（utf8 / gbk / gb18030 / big5），raw 为 true 时输出以 base64 发送
+#[tauri::command]
+async fn ssh_set_terminal_output(
+    terminal_id: String,
+    output: ssh_terminal_encoding::TerminalOutputOptions,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    println!("🔤 [Tauri] 终端输出设置: {} {:?}", terminal_id, output);
+    manager.set_terminal_output(&terminal_id, output)
+}
+
+// 终端录制命令
+
+/// 开始录制终端（asciinema v2 .cast，保存在应用数据目录的 recordings/ 下）
+#[tauri::command]
+async fn ssh_recording_start(
+    terminal_id: String,
+    options: Option<ssh_terminal_recording::RecordingOptions>,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_recording::RecordingInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let info = manager.start_terminal_recording(&terminal_id, options.unwrap_or_default())?;
+    println!("🎬 [Tauri] 开始录制终端: {} -> {}", terminal_id, info.path);
+    Ok(info)
+}
+
+#[tauri::command]
+async fn ssh_recording_stop(
+    terminal_id: String,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_recording::RecordingInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let info = manager.stop_terminal_recording(&terminal_id)?;
+    println!("⏹️ [Tauri] 停止录制终端: {} ({:.1}s)", terminal_id, info.duration);
+    Ok(info)
+}
+
+#[tauri::command]
+async fn ssh_recording_list(
+    state: State<'_, AppState>,
+) -> Result<Vec<ssh_terminal_recording::RecordingInfo>, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.list_terminal_recordings()
+}
+
+/// 回放录制：内容通过 ssh_recording_data 事件分批发送，返回 stream_id
+#[tauri::command]
+async fn ssh_recording_stream(
+    recording_id: String,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.stream_terminal_recording(&recording_id, window)
+}
+
+// 终端回滚缓冲区命令
+
+#[tauri::command]
+async fn ssh_scrollback_info(
+    terminal_id: String,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_scrollback::ScrollbackInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.scrollback_info(&terminal_id)
+}
+
+/// 按正则搜索终端历史输出（已去除 ANSI 控制序列）
+#[tauri::command]
+async fn ssh_scrollback_search(
+    terminal_id: String,
+    pattern: String,
+    options: Option<ssh_terminal_scrollback::ScrollbackSearchOptions>,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_scrollback::ScrollbackSearchResult, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.search_scrollback(&terminal_id, &pattern, options.unwrap_or_default())
+}
+
+/// 取终端历史输出中 start 起的 count 行；窗口重新加载后用于重新绘制
+#[tauri::command]
+async fn ssh_scrollback_range(
+    terminal_id: String,
+    start: u64,
+    count: usize,
+    strip_ansi: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<ssh_terminal_scrollback::ScrollbackRange, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.scrollback_range(&terminal_id, start, count, strip_ansi.unwrap_or(false))
+}
+
+/// 把终端历史输出导出为纯文本文件，返回行数
+#[tauri::command]
+async fn ssh_scrollback_export(
+    terminal_id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let lines = manager.export_scrollback(&terminal_id, std::path::Path::new(&path))?;
+    println!("📄 [Tauri] 导出终端记录: {} -> {} ({} 行)", terminal_id, path, lines);
+    Ok(lines)
+}
+
+/// 应答终端内的 ZMODEM 请求（ssh_zmodem_request 事件）
+/// answer: {"action": "receive", "directory"} / {"action": "send", "paths"} / {"action": "cancel"}
+#[tauri::command]
+async fn ssh_zmodem_respond(
+    terminal_id: String,
+    answer: ssh_zmodem::ZmodemAnswer,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    println!("📦 [Tauri] ZMODEM 应答: {} {:?}", terminal_id, answer);
+    manager.zmodem_respond(&terminal_id, answer)
+}
+
+/// 取消终端内进行中的 ZMODEM 传输
+#[tauri::command]
+async fn ssh_zmodem_cancel(
+    terminal_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    Ok(manager.zmodem_cancel(&terminal_id))
+}
+
 /// 获取 SSH 终端自动补全建议
 #[tauri::command]
 async fn ssh_get_completion(
@@ -1357,7 +1814,7 @@ async fn ssh_get_completion(
     #[allow(unused_variables)] cursor_position: usize,
     state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
 
     // 基本的命令补全逻辑
     let words: Vec<&str> = input.split_whitespace().collect();
@@ -1401,7 +1858,7 @@ async fn ssh_get_completion(
         // 执行 ls 命令获取文件列表
         match manager.execute_command(&format!("ls -1a {}", dir_path)) {
             Ok(output) => {
-                let files: Vec<&str> = output.output.lines().collect();
+                let files: Vec<&str> = output.stdout.lines().collect();
                 let filename_prefix = if last_word.contains('/') {
                     last_word.split('/').last().unwrap_or("")
                 } else {
@@ -1436,7 +1893,7 @@ async fn sftp_list_files(
     path: String,
     state: State<'_, AppState>,
 ) -> Result<Vec<ssh_manager_russh::SftpFileInfo>, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     manager.list_sftp_files(&path).map_err(|e| e.to_string())
 }
 #[tauri::command]
@@ -1445,7 +1902,7 @@ async fn sftp_read_file(
     max_bytes: Option<usize>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     let content = manager
         .read_sftp_file(&path)
         .map_err(|e| e.to_string())?;
@@ -1465,26 +1922,111 @@ async fn sftp_read_file(
         .map_err(|e| format!("Failed to decode file as UTF-8: {}", e))
 }
 
+/// 分段读取远程文件（offset/length），length 上限 16MB
+#[tauri::command]
+async fn sftp_read_range(
+    path: String,
+    offset: u64,
+    length: u64,
+    state: State<'_, AppState>,
+) -> Result<ssh_file_range::FileRange, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.read_sftp_range(&path, offset, length)
+}
+
+/// 读取远程文件末尾的 bytes 字节
+#[tauri::command]
+async fn sftp_tail_file(
+    path: String,
+    bytes: u64,
+    state: State<'_, AppState>,
+) -> Result<ssh_file_range::FileRange, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.tail_sftp_file(&path, bytes)
+}
+
+/// 建立行索引：每 interval 行记录一次字节偏移
+#[tauri::command]
+async fn sftp_build_line_index(
+    path: String,
+    interval: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<ssh_file_range::LineIndex, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    println!("📑 [Tauri] 建立行索引: {}", path);
+    manager.build_line_index(
+        &path,
+        interval.unwrap_or(ssh_file_range::DEFAULT_LINE_INDEX_INTERVAL),
+    )
+}
+
+#[tauri::command]
+async fn sftp_chmod(
+    path: String,
+    mode: u32,
+    recursive: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager
+        .chmod_sftp(&path, mode, recursive.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// 修改属主/属组（数字 uid/gid）
 #[tauri::command]
-async fn sftp_chmod(path: String, mode: u32, state: State<'_, AppState>) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
-    manager.chmod_sftp(&path, mode).map_err(|e| e.to_string())
+async fn sftp_chown(
+    path: String,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    recursive: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.chown_sftp(&path, uid, gid, recursive.unwrap_or(false))
+}
+
+/// 设置访问/修改时间（Unix 秒）
+#[tauri::command]
+async fn sftp_set_times(
+    path: String,
+    atime: Option<u32>,
+    mtime: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.set_sftp_times(&path, atime, mtime)
+}
+
+/// 创建符号链接 link_path -> target
+#[tauri::command]
+async fn sftp_create_symlink(
+    target: String,
+    link_path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    println!("🔗 [Tauri] 创建符号链接: {} -> {}", link_path, target);
+    manager.create_sftp_symlink(&target, &link_path)
 }
 
 #[tauri::command]
 async fn sftp_get_file_details(
     path: String,
+    follow_links: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<ssh_manager_russh::SftpFileDetails, String> {
-    let manager = state.ssh_manager.lock().unwrap();
-    manager.get_file_details(&path).map_err(|e| e.to_string())
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager
+        .get_file_details(&path, follow_links.unwrap_or(false))
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn get_bash_environment_info(
     state: State<'_, AppState>,
 ) -> Result<types::BashEnvironmentInfo, String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager
         .get_bash_environment_info()
         .map_err(|e| e.to_string())
@@ -1495,22 +2037,40 @@ async fn get_command_completion(
     input: String,
     state: State<'_, AppState>,
 ) -> Result<types::CommandCompletion, String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager
         .get_command_completion(&input)
         .map_err(|e| e.to_string())
 }
+/// 写入远程文件；传入读取时的 expected_version 时，文件已被他人修改则返回冲突错误，
+/// backup 为 true 时先把原文件复制为 .bak。返回写入后的新版本
 #[tauri::command]
 async fn sftp_write_file(
     path: String,
     content: String,
+    expected_version: Option<String>,
+    backup: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+) -> Result<ssh_manager_russh::FileVersion, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let options = ssh_manager_russh::WriteOptions {
+        expected_version,
+        backup: backup.unwrap_or(false),
+    };
     manager
-        .write_sftp_file(&path, content.as_bytes())
+        .write_sftp_file_with_options(&path, content.as_bytes(), options)
         .map_err(|e| e.to_string())
 }
+
+/// 读取远程文件并返回版本标识，供 sftp_write_file 的 expected_version 使用
+#[tauri::command]
+async fn sftp_read_file_versioned(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<ssh_manager_russh::VersionedTextFile, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.read_sftp_file_versioned(&path)?.into_text()
+}
 #[tauri::command]
 async fn sftp_compress(
     source_path: String,
@@ -1518,12 +2078,44 @@ async fn sftp_compress(
     format: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager
         .compress_file(&source_path, &target_path, &format)
         .map_err(|e| e.to_string())
 }
 
+/// 把远程文件/目录直接打包到本地归档（不依赖远程的 tar/zip），format 省略时按扩展名判断
+#[tauri::command]
+async fn sftp_archive_to_local(
+    remote_paths: Vec<String>,
+    local_path: String,
+    format: Option<ssh_archive::ArchiveFormat>,
+    state: State<'_, AppState>,
+) -> Result<ssh_archive::ArchiveSummary, String> {
+    let format = format
+        .or_else(|| ssh_archive::ArchiveFormat::from_path(&local_path))
+        .ok_or("无法识别的归档格式，请使用 .tar、.tar.gz 或 .zip")?;
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    println!("📦 [Tauri] 打包到本地: {:?} -> {}", remote_paths, local_path);
+    manager.archive_to_local(&remote_paths, &local_path, format)
+}
+
+/// 把本地归档解包到远程目录
+#[tauri::command]
+async fn sftp_extract_local_archive(
+    archive_path: String,
+    remote_dir: String,
+    format: Option<ssh_archive::ArchiveFormat>,
+    state: State<'_, AppState>,
+) -> Result<ssh_archive::ArchiveSummary, String> {
+    let format = format
+        .or_else(|| ssh_archive::ArchiveFormat::from_path(&archive_path))
+        .ok_or("无法识别的归档格式，请使用 .tar、.tar.gz 或 .zip")?;
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    println!("📦 [Tauri] 解包到远程: {} -> {}", archive_path, remote_dir);
+    manager.extract_local_archive(&archive_path, &remote_dir, format)
+}
+
 #[tauri::command]
 async fn sftp_extract(
     archive_path: String,
@@ -1531,7 +2123,7 @@ async fn sftp_extract(
     _overwrite: bool,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager
         .extract_file(&archive_path, &target_dir)
         .map_err(|e| e.to_string())
@@ -1542,7 +2134,7 @@ async fn sftp_upload(
     remote_path: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager
         .upload_file(&local_path, &remote_path)
         .map_err(|e| e.to_string())
@@ -1554,18 +2146,170 @@ async fn sftp_download(
     local_path: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager
         .download_file(&remote_path, &local_path)
         .map_err(|e| e.to_string())
 }
 
+// 远程文件监视命令
+
+/// 开始监视远程文件（mode: "tail" / "changes"），事件为 ssh_watch_lines / ssh_watch_change / ssh_watch_stopped
+#[tauri::command]
+async fn ssh_watch_start(
+    spec: ssh_file_watch::WatchSpec,
+    owner: Option<String>,
+    session_id: Option<String>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<ssh_file_watch::WatchInfo, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let session_id = match session_id {
+        Some(id) => id,
+        None => manager.get_current_session_id().ok_or("没有活动的 SSH 连接")?,
+    };
+    println!("👀 [Tauri] 开始监视: {:?} {}", spec.mode, spec.path);
+    manager.start_watch(&session_id, spec, owner, window)
+}
+
+/// 停止监视；传 owner 时停止该标签页的所有监视
+#[tauri::command]
+async fn ssh_watch_stop(
+    watch_id: Option<String>,
+    owner: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    match (watch_id, owner) {
+        (Some(watch_id), _) => manager.stop_watch(&watch_id).map(|_| 1),
+        (None, Some(owner)) => Ok(manager.stop_owner_watches(&owner)),
+        (None, None) => Err("需要 watch_id 或 owner".to_string()),
+    }
+}
+
+#[tauri::command]
+async fn ssh_watch_list(
+    session_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ssh_file_watch::WatchInfo>, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    Ok(manager.list_watches(session_id.as_deref()))
+}
+
+// SFTP 传输队列命令
+
+/// 加入传输队列（transfer_type: "upload" / "download"），进度通过 sftp_transfer_progress 事件推送
+#[tauri::command]
+async fn sftp_transfer_enqueue(
+    transfer_type: ssh_file_transfer::TransferDirection,
+    source_path: String,
+    destination_path: String,
+    session_id: Option<String>,
+    options: Option<ssh_file_transfer::TransferOptions>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<types::FileTransferTask, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let session_id = match session_id {
+        Some(id) => id,
+        None => manager.get_current_session_id().ok_or("没有活动的 SSH 连接")?,
+    };
+    println!("📦 [Tauri] 传输入队: {:?} {} -> {}", transfer_type, source_path, destination_path);
+    manager.enqueue_transfer(
+        &session_id,
+        transfer_type,
+        &source_path,
+        &destination_path,
+        options.unwrap_or_default(),
+        Some(window),
+    )
+}
+
+#[tauri::command]
+async fn sftp_transfer_list(
+    session_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<types::FileTransferTask>, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    Ok(manager.list_transfers(session_id.as_deref()))
+}
+
+#[tauri::command]
+async fn sftp_transfer_pause(transfer_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.pause_transfer(&transfer_id)
+}
+
+#[tauri::command]
+async fn sftp_transfer_resume(transfer_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.resume_transfer(&transfer_id)
+}
+
+#[tauri::command]
+async fn sftp_transfer_cancel(transfer_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    manager.cancel_transfer(&transfer_id)
+}
+
+/// 递归上传/下载目录；options.mirror 为 true 时只传输变化的文件
+#[tauri::command]
+async fn sftp_transfer_directory(
+    transfer_type: ssh_file_transfer::TransferDirection,
+    source_path: String,
+    destination_path: String,
+    session_id: Option<String>,
+    options: Option<ssh_file_sync::SyncOptions>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<ssh_file_sync::DirectorySyncSummary, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    let session_id = match session_id {
+        Some(id) => id,
+        None => manager.get_current_session_id().ok_or("没有活动的 SSH 连接")?,
+    };
+    println!("📁 [Tauri] 目录传输: {:?} {} -> {}", transfer_type, source_path, destination_path);
+    let summary = manager.transfer_directory(
+        &session_id,
+        transfer_type,
+        &source_path,
+        &destination_path,
+        options.unwrap_or_default(),
+        Some(window),
+    )?;
+    println!(
+        "✅ [Tauri] 目录传输已入队: {} 个文件, 跳过 {} 个, 删除 {} 个",
+        summary.transfers.len(),
+        summary.skipped.len(),
+        summary.deleted.len()
+    );
+    Ok(summary)
+}
+
+/// 重试失败或已取消的传输，从目标已有的位置续传
+#[tauri::command]
+async fn sftp_transfer_retry(
+    transfer_id: String,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<types::FileTransferTask, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    println!("🔁 [Tauri] 重试传输: {}", transfer_id);
+    manager.retry_transfer(&transfer_id, Some(window))
+}
+
+#[tauri::command]
+async fn sftp_transfer_clear_finished(state: State<'_, AppState>) -> Result<usize, String> {
+    let manager = state.ssh_manager.lock().unwrap().clone();
+    Ok(manager.clear_finished_transfers())
+}
+
 #[tauri::command]
 async fn sftp_create_directory(
     remote_path: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     manager
         .create_directory(&remote_path)
         .map_err(|e| e.to_string())
@@ -1593,7 +2337,7 @@ async fn save_temp_file(file_name: String, data: Vec<u8>) -> Result<String, Stri
 async fn ssh_get_connection_status(
     state: State<'_, AppState>,
 ) -> Result<Option<ssh_manager_russh::SSHConnectionStatus>, String> {
-    let manager = state.ssh_manager.lock().unwrap();
+    let manager = state.ssh_manager.lock().unwrap().clone();
     let status = manager.get_connection_status();
     //println!("🔍 前端请求SSH连接状态: {:?}", status);
     Ok(status)
@@ -1603,9 +2347,9 @@ async fn ssh_get_connection_status(
 async fn docker_list_containers(
     state: State<'_, AppState>,
 ) -> Result<Vec<types::DockerContainerSummary>, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
-    manager.list_containers(&mut *ssh).map_err(Into::into)
+    manager.list_containers(&mut ssh).map_err(Into::into)
 }
 
 #[tauri::command]
@@ -1614,10 +2358,10 @@ async fn docker_container_action(
     action: String,
     state: State<'_, AppState>,
 ) -> Result<types::DockerActionResult, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
     manager
-        .perform_action(&mut *ssh, &container_id, &action)
+        .perform_action(&mut ssh, &container_id, &action)
         .map_err(Into::into)
 }
 
@@ -1627,10 +2371,10 @@ async fn docker_container_logs(
     options: Option<types::DockerLogsOptions>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
     manager
-        .get_logs(&mut *ssh, &container_id, options)
+        .get_logs(&mut ssh, &container_id, options)
         .map_err(Into::into)
 }
 
@@ -1639,10 +2383,10 @@ async fn docker_inspect_container(
     container_id: String,
     state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
     manager
-        .inspect(&mut *ssh, &container_id)
+        .inspect(&mut ssh, &container_id)
         .map_err(Into::into)
 }
 
@@ -1652,10 +2396,10 @@ async fn docker_read_container_file(
     path: String,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
     manager
-        .read_file(&mut *ssh, &container_id, &path)
+        .read_file(&mut ssh, &container_id, &path)
         .map_err(Into::into)
 }
 
@@ -1666,11 +2410,11 @@ async fn docker_exec_command(
     shell: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<ssh_manager_russh::TerminalOutput, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
     let shell = shell.unwrap_or_else(|| "sh".to_string());
     manager
-        .exec_command(&mut *ssh, &container_id, &command, &shell)
+        .exec_command(&mut ssh, &container_id, &command, &shell)
         .map_err(Into::into)
 }
 
@@ -1717,10 +2461,10 @@ async fn docker_write_container_file(
     content: String,
     state: State<'_, AppState>,
 ) -> Result<types::DockerActionResult, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
     manager
-        .write_file(&mut *ssh, &container_id, &path, &content)
+        .write_file(&mut ssh, &container_id, &path, &content)
         .map_err(Into::into)
 }
 
@@ -1730,10 +2474,10 @@ async fn docker_copy(
     request: types::DockerCopyRequest,
     state: State<'_, AppState>,
 ) -> Result<types::DockerActionResult, String> {
-    let mut ssh = state.ssh_manager.lock().unwrap();
+    let mut ssh = state.ssh_manager.lock().unwrap().clone();
     let manager = docker_manager::DockerManager::new();
     manager
-        .copy(&mut *ssh, &container_id, &request)
+        .copy(&mut ssh, &container_id, &request)
         .map_err(Into::into)
 }
 
@@ -1749,7 +2493,7 @@ async fn read_system_log(
     date_filter: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<log_analysis::LogAnalysisResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     
     if !manager.is_connected() {
         return Err("没有活动的 SSH 连接".to_string());
@@ -1772,7 +2516,7 @@ async fn read_system_log(
         .map_err(|e| format!("读取日志失败: {}", e))?;
     
     // 解析日志内容
-    let entries: Vec<log_analysis::LogEntry> = output.output
+    let entries: Vec<log_analysis::LogEntry> = output.stdout
         .lines()
         .filter(|line| !line.trim().is_empty() && !line.contains("Log file not found") && !line.contains("No matching entries"))
         .map(|line| log_analysis::parse_log_line(line, log_analysis::HIGHLIGHT_KEYWORDS))
@@ -1799,7 +2543,7 @@ async fn read_journalctl_log(
     until: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<log_analysis::LogAnalysisResult, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     
     if !manager.is_connected() {
         return Err("没有活动的 SSH 连接".to_string());
@@ -1823,7 +2567,7 @@ async fn read_journalctl_log(
         .map_err(|e| format!("读取 journalctl 日志失败: {}", e))?;
     
     // 解析日志内容
-    let entries: Vec<log_analysis::LogEntry> = output.output
+    let entries: Vec<log_analysis::LogEntry> = output.stdout
         .lines()
         .filter(|line| !line.trim().is_empty() && !line.contains("journalctl not available"))
         .map(|line| log_analysis::parse_log_line(line, log_analysis::HIGHLIGHT_KEYWORDS))
@@ -1844,7 +2588,7 @@ async fn read_journalctl_log(
 async fn list_log_files(
     state: State<'_, AppState>,
 ) -> Result<Vec<log_analysis::LogFileInfo>, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     
     if !manager.is_connected() {
         return Err("没有活动的 SSH 连接".to_string());
@@ -1858,7 +2602,7 @@ async fn list_log_files(
         .map_err(|e| format!("列出日志文件失败: {}", e))?;
     
     // 解析输出
-    let mut log_files: Vec<log_analysis::LogFileInfo> = output.output
+    let mut log_files: Vec<log_analysis::LogFileInfo> = output.stdout
         .lines()
         .filter_map(|line| {
             let parts: Vec<&str> = line.split('|').collect();
@@ -1903,7 +2647,7 @@ async fn get_log_file_info(
     log_path: String,
     state: State<'_, AppState>,
 ) -> Result<log_analysis::LogFileInfo, String> {
-    let mut manager = state.ssh_manager.lock().unwrap();
+    let mut manager = state.ssh_manager.lock().unwrap().clone();
     
     if !manager.is_connected() {
         return Err("没有活动的 SSH 连接".to_string());
@@ -1919,7 +2663,7 @@ async fn get_log_file_info(
     let name = log_path.split('/').last().unwrap_or(&log_path).to_string();
     
     // 解析输出
-    if output.output.contains("readable:no") {
+    if output.stdout.contains("readable:no") {
         return Ok(log_analysis::LogFileInfo {
             path: log_path,
             name,
@@ -1933,7 +2677,7 @@ async fn get_log_file_info(
     let mut size = 0u64;
     let mut modified = String::new();
     
-    for part in output.output.split('|') {
+    for part in output.stdout.split('|') {
         if part.starts_with("size:") {
             size = part[5..].parse().unwrap_or(0);
         } else if part.starts_with("modified:") {
@@ -1958,6 +2702,7 @@ pub fn run() {
         ssh_connection_manager::SSHConnectionManager::new().expect("初始化SSH连接管理器失败");
     let ssh_client = ssh_client::SSHClient::new();
     let ssh_manager = ssh_manager_russh::SSHManagerRussh::new();
+    ssh_manager.set_max_retries(app_settings.ssh.max_retries);
 
     let app_state = AppState {
         settings: Mutex::new(app_settings),
@@ -1965,6 +2710,7 @@ pub fn run() {
         ssh_client: Mutex::new(ssh_client),
         ssh_manager: Mutex::new(ssh_manager),
         ssh_terminal_creation_lock: Mutex::new(()),
+        ssh_auth_broker: Arc::new(ssh_interactive_auth::InteractiveAuthBroker::new()),
     };
 
     tauri::Builder::default()
@@ -1996,21 +2742,54 @@ pub fn run() {
             // 新的SSH/SFTP命令
             ssh_connect_direct,
             ssh_disconnect_direct,
+            ssh_list_sessions,
+            ssh_switch_session,
+            ssh_close_session,
+            ssh_keyboard_interactive_respond,
+            ssh_scan_host_key,
+            known_hosts_list,
+            known_hosts_remove,
+            known_hosts_pin,
+            ssh_create_port_forward,
+            ssh_list_port_forwards,
+            ssh_close_port_forward,
             ssh_execute_command_direct,
             ssh_execute_dashboard_command_direct,
             ssh_execute_emergency_command_direct,
+            ssh_execute_command_stream,
+            ssh_cancel_command,
             execute_detection_command,
             sftp_list_files,
             sftp_read_file,
             sftp_write_file,
             sftp_upload,
             sftp_download,
+            ssh_watch_start,
+            ssh_watch_stop,
+            ssh_watch_list,
+            sftp_transfer_enqueue,
+            sftp_transfer_list,
+            sftp_transfer_pause,
+            sftp_transfer_resume,
+            sftp_transfer_cancel,
+            sftp_transfer_retry,
+            sftp_transfer_directory,
+            sftp_transfer_clear_finished,
             sftp_create_directory,
             save_temp_file,
             sftp_compress,
             sftp_extract,
             sftp_chmod,
             sftp_get_file_details,
+            sftp_chown,
+            sftp_read_range,
+            sftp_read_file_versioned,
+            sftp_archive_to_local,
+            sftp_extract_local_archive,
+            sftp_tail_file,
+            sftp_build_line_index,
+            sftp_set_times,
+            sftp_create_symlink,
             file_analysis::sftp_file_analysis,
             file_analysis::sftp_file_analysis_independent,
             get_bash_environment_info,
@@ -2051,6 +2830,25 @@ pub fn run() {
             ssh_close_terminal_session,
             ssh_close_all_terminal_sessions,
             ssh_send_input,
+            ssh_resize_terminal,
+            ssh_zmodem_respond,
+            ssh_zmodem_cancel,
+            ssh_set_terminal_output,
+            ssh_terminal_group_create,
+            ssh_terminal_group_delete,
+            ssh_terminal_group_list,
+            ssh_terminal_group_add_member,
+            ssh_terminal_group_remove_member,
+            ssh_terminal_group_set_enabled,
+            ssh_terminal_group_send_input,
+            ssh_recording_start,
+            ssh_recording_stop,
+            ssh_recording_list,
+            ssh_recording_stream,
+            ssh_scrollback_info,
+            ssh_scrollback_search,
+            ssh_scrollback_range,
+            ssh_scrollback_export,
             ssh_get_completion,
             // Docker
             docker_list_containers,
@@ -0,0 +1,301 @@
+// 本机终端
+// 在分析人员自己的机器上用伪终端启动 shell（计算证据哈希、运行 YARA 等），
+// 与 SSH 终端使用相同的 ssh_terminal_data / ssh_terminal_closed 事件，终端命令由 SSHManagerRussh 按 terminal_id 分派
+
+use crate::ssh_terminal_encoding::{TerminalDecoder, TerminalOutputOptions};
+use crate::ssh_terminal_group::{MemberLoss, TerminalGroups};
+use crate::ssh_terminal_recording::{RecordingInfo, RecordingOptions, RecordingRegistry};
+use crate::ssh_terminal_scrollback::ScrollbackRegistry;
+use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+/// 本机 shell 选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalShellOptions {
+    /// 为空时使用用户的默认 shell（$SHELL，Windows 为 %ComSpec%）
+    pub shell: Option<String>,
+    pub args: Vec<String>,
+    /// 为空时为用户主目录
+    pub cwd: Option<String>,
+}
+
+type PtyReader = Box<dyn Read + Send>;
+
+struct LocalTerminal {
+    master: Box<dyn MasterPty + Send>,
+    // 写入可能阻塞（shell 不读取输入时 PTY 缓冲区会满），单独加锁，不占用全部终端的表
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+    decoder: Arc<Mutex<TerminalDecoder>>,
+    cols: u32,
+    rows: u32,
+}
+
+/// 本机终端表；表锁只在查找时持有，输入写到各终端自己的写端，卡住的 shell 不影响其他终端
+#[derive(Clone)]
+pub struct LocalTerminals {
+    terminals: Arc<Mutex<HashMap<String, LocalTerminal>>>,
+    recordings: RecordingRegistry,
+    groups: TerminalGroups,
+    scrollback: ScrollbackRegistry,
+}
+
+impl LocalTerminals {
+    pub fn new(recordings: RecordingRegistry, groups: TerminalGroups, scrollback: ScrollbackRegistry) -> Self {
+        Self {
+            terminals: Arc::new(Mutex::new(HashMap::new())),
+            recordings,
+            groups,
+            scrollback,
+        }
+    }
+
+    pub fn contains(&self, terminal_id: &str) -> bool {
+        self.terminals.lock().unwrap().contains_key(terminal_id)
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.terminals.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// 打开伪终端并启动 shell，输出由后台线程发送到窗口
+    pub fn create(
+        &self,
+        window: tauri::Window,
+        terminal_id: &str,
+        cols: u32,
+        rows: u32,
+        output: TerminalOutputOptions,
+        options: LocalShellOptions,
+    ) -> Result<(), String> {
+        if self.contains(terminal_id) {
+            return Err(format!("Terminal already exists: {}", terminal_id));
+        }
+
+        let (terminal, reader, mut child) = spawn_shell(cols, rows, output, options)?;
+        let decoder = terminal.decoder.clone();
+        self.scrollback.register(terminal_id);
+        self.terminals.lock().unwrap().insert(terminal_id.to_string(), terminal);
+
+        let terminals = self.clone();
+        let terminal_id = terminal_id.to_string();
+        std::thread::spawn(move || {
+            terminals.read_output(reader, &terminal_id, &decoder, &window);
+            let _ = child.wait();
+            terminals.finish(&terminal_id, &window);
+        });
+        Ok(())
+    }
+
+    fn read_output(
+        &self,
+        mut reader: PtyReader,
+        terminal_id: &str,
+        decoder: &Mutex<TerminalDecoder>,
+        window: &tauri::Window,
+    ) {
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            // shell 退出后读取返回 0 或 EIO
+            let n = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => n,
+            };
+            let data = &buf[..n];
+            let mut decoder = decoder.lock().unwrap();
+            let text = decoder.decode(data, false);
+            if !text.is_empty() {
+                self.recordings.output(terminal_id, &text);
+                self.scrollback.output(terminal_id, &text);
+            } else if !decoder.options().raw {
+                continue;
+            }
+            let _ = window.emit("ssh_terminal_data", decoder.payload(terminal_id, data, &text));
+        }
+    }
+
+    /// shell 退出：仍在登记中的终端（不是前端主动关闭的）发送 ssh_terminal_closed
+    fn finish(&self, terminal_id: &str, window: &tauri::Window) {
+        let removed = self.terminals.lock().unwrap().remove(terminal_id);
+        let _ = self.recordings.stop(terminal_id);
+        self.groups.member_lost(terminal_id, MemberLoss::Closed, window);
+        if removed.is_some() {
+            let _ = window.emit("ssh_terminal_closed", serde_json::json!({"terminalId": terminal_id}));
+        }
+    }
+
+    pub fn send_input(&self, terminal_id: &str, data: &[u8]) -> Result<(), String> {
+        let (writer, data) = {
+            let terminals = self.terminals.lock().unwrap();
+            let term = terminals
+                .get(terminal_id)
+                .ok_or_else(|| format!("Terminal session not found: {}", terminal_id))?;
+            self.recordings.input(terminal_id, data);
+            let data = term.decoder.lock().unwrap().encode_input(data).into_owned();
+            (term.writer.clone(), data)
+        };
+        let mut writer = writer.lock().unwrap();
+        writer
+            .write_all(&data)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to send data: {}", e))
+    }
+
+    pub fn resize(&self, terminal_id: &str, cols: u32, rows: u32) -> Result<(), String> {
+        let mut terminals = self.terminals.lock().unwrap();
+        let term = terminals
+            .get_mut(terminal_id)
+            .ok_or_else(|| format!("Terminal session not found: {}", terminal_id))?;
+        term.cols = cols;
+        term.rows = rows;
+        self.recordings.resize(terminal_id, cols, rows);
+        term.master
+            .resize(pty_size(cols, rows))
+            .map_err(|e| format!("Failed to resize terminal: {}", e))
+    }
+
+    pub fn set_output(&self, terminal_id: &str, output: TerminalOutputOptions) -> Result<(), String> {
+        let terminals = self.terminals.lock().unwrap();
+        let term = terminals
+            .get(terminal_id)
+            .ok_or_else(|| format!("Terminal session not found: {}", terminal_id))?;
+        *term.decoder.lock().unwrap() = TerminalDecoder::new(output);
+        Ok(())
+    }
+
+    pub fn start_recording(&self, terminal_id: &str, options: RecordingOptions) -> Result<RecordingInfo, String> {
+        let terminals = self.terminals.lock().unwrap();
+        let term = terminals
+            .get(terminal_id)
+            .ok_or_else(|| format!("Terminal session not found: {}", terminal_id))?;
+        self.recordings.start(terminal_id, term.cols, term.rows, options)
+    }
+
+    /// 结束 shell；读取线程随后退出
+    pub fn close(&self, terminal_id: &str) -> Result<(), String> {
+        if let Some(mut term) = self.terminals.lock().unwrap().remove(terminal_id) {
+            let _ = term.killer.kill();
+        }
+        Ok(())
+    }
+}
+
+/// 打开伪终端并启动 shell，返回登记用的终端、输出读取端与子进程
+fn spawn_shell(
+    cols: u32,
+    rows: u32,
+    output: TerminalOutputOptions,
+    options: LocalShellOptions,
+) -> Result<(LocalTerminal, PtyReader, Box<dyn Child + Send + Sync>), String> {
+    let pair = native_pty_system()
+        .openpty(pty_size(cols, rows))
+        .map_err(|e| format!("Failed to open PTY: {}", e))?;
+
+    let mut command = match &options.shell {
+        Some(shell) => CommandBuilder::new(shell),
+        None => CommandBuilder::new_default_prog(),
+    };
+    command.args(&options.args);
+    command.env("TERM", "xterm-256color");
+    if let Some(dir) = options.cwd.map(std::path::PathBuf::from).or_else(dirs::home_dir) {
+        command.cwd(dir);
+    }
+
+    let child = pair
+        .slave
+        .spawn_command(command)
+        .map_err(|e| format!("Failed to start shell: {}", e))?;
+    // 只有主进程一侧关闭从端后，shell 退出时读取才会结束
+    drop(pair.slave);
+
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to read PTY: {}", e))?;
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| format!("Failed to write PTY: {}", e))?;
+
+    let terminal = LocalTerminal {
+        master: pair.master,
+        writer: Arc::new(Mutex::new(writer)),
+        killer: child.clone_killer(),
+        decoder: Arc::new(Mutex::new(TerminalDecoder::new(output))),
+        cols,
+        rows,
+    };
+    Ok((terminal, reader, child))
+}
+
+fn pty_size(cols: u32, rows: u32) -> PtySize {
+    PtySize {
+        rows: rows.min(u16::MAX as u32) as u16,
+        cols: cols.min(u16::MAX as u32) as u16,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn terminals() -> LocalTerminals {
+        LocalTerminals::new(RecordingRegistry::new(), TerminalGroups::new(), ScrollbackRegistry::new())
+    }
+
+    /// 在后台读取 PTY 输出，直到出现 expected
+    fn wait_for_output(mut reader: PtyReader, expected: &'static str) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut seen = Vec::new();
+            let mut buf = [0u8; 1024];
+            while let Ok(n) = reader.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                seen.extend_from_slice(&buf[..n]);
+                if String::from_utf8_lossy(&seen).contains(expected) {
+                    let _ = tx.send(String::from_utf8_lossy(&seen).into_owned());
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn input_reaches_the_shell_and_close_stops_it() {
+        let terminals = terminals();
+        let options = LocalShellOptions { shell: Some("cat".to_string()), args: Vec::new(), cwd: None };
+        let (terminal, reader, mut child) = spawn_shell(80, 24, TerminalOutputOptions::default(), options).unwrap();
+        terminals.terminals.lock().unwrap().insert("local-1".to_string(), terminal);
+        let output = wait_for_output(reader, "hash check");
+
+        // 写入时不持有终端表的锁，其他终端的操作不受影响
+        terminals.send_input("local-1", b"hash check\n").unwrap();
+        assert!(output.recv_timeout(Duration::from_secs(5)).is_ok());
+        terminals.resize("local-1", 120, 40).unwrap();
+        assert!(terminals.send_input("missing", b"x").is_err());
+
+        assert_eq!(terminals.ids(), vec!["local-1".to_string()]);
+        terminals.close("local-1").unwrap();
+        assert!(!terminals.contains("local-1"));
+        assert!(child.wait().is_ok());
+    }
+
+    #[test]
+    fn pty_size_is_clamped() {
+        let size = pty_size(100_000, 50);
+        assert_eq!((size.cols, size.rows), (u16::MAX, 50));
+    }
+}
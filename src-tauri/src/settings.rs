@@ -295,7 +295,8 @@ pub fn validate_settings(settings: &AppSettings) -> Result<(), String> {
         return Err("无效的SSH连接超时设置".to_string());
     }
 
-    if settings.ssh.max_retries == 0 || settings.ssh.max_retries > 10 {
+    // 0 表示断线后不自动重连
+    if settings.ssh.max_retries > 10 {
         return Err("无效的SSH最大重试次数设置".to_string());
     }
 
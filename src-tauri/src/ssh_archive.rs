@@ -0,0 +1,865 @@
+// 本地归档的创建与解包
+// 通过 SFTP 把远程文件直接流式写入本地 tar / tar.gz / zip，或把本地归档解包到远程目录，
+// 全程不依赖远程主机上的 tar、zip 等工具，也不在远程磁盘上产生临时文件。
+// 归档格式的读写交给 tar / zip crate，在阻塞线程上进行；本模块只负责与 SFTP 之间的数据搬运
+
+use crate::ssh_file_transfer;
+use chrono::{Datelike, Timelike};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use russh::client::{Handle, Handler};
+use russh_sftp::client::SftpSession;
+use russh_sftp::protocol::FileAttributes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{mpsc, RwLock};
+use zip::write::SimpleFileOptions;
+
+/// 每次读写的块大小
+const CHUNK_SIZE: usize = 256 * 1024;
+/// SFTP 与归档线程之间最多缓冲的块数
+const QUEUE_CHUNKS: usize = 8;
+/// 达到该大小的 zip 项需要 zip64 扩展
+const ZIP64_THRESHOLD: u64 = u32::MAX as u64;
+
+/// 归档格式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// 根据扩展名推断格式
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if lower.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if lower.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// 归档中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntryInfo {
+    /// 归档内的相对路径
+    pub name: String,
+    /// "file" / "directory" / "symlink"
+    pub kind: String,
+    pub size: u64,
+    /// 文件内容的 SHA-256，便于取证时核对
+    pub sha256: Option<String>,
+}
+
+/// 创建或解包的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveSummary {
+    pub archive_path: String,
+    pub format: ArchiveFormat,
+    pub entries: Vec<ArchiveEntryInfo>,
+    pub total_bytes: u64,
+    /// 未处理的项及原因
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+impl EntryKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EntryKind::File => "file",
+            EntryKind::Directory => "directory",
+            EntryKind::Symlink => "symlink",
+        }
+    }
+}
+
+/// 归档项的元数据
+#[derive(Debug, Clone, PartialEq)]
+struct EntryHeader {
+    name: String,
+    kind: EntryKind,
+    size: u64,
+    mode: u32,
+    mtime: u64,
+    link_target: Option<String>,
+}
+
+/// 在 SFTP 一侧与归档线程之间传递的消息：一项的头部，文件项随后是内容块和 End
+#[derive(Debug)]
+enum Chunk {
+    Entry(EntryHeader),
+    Data(Vec<u8>),
+    End,
+    /// 无法解包的项及原因
+    Skipped(String),
+}
+
+/// 把消息流中一个文件项的内容当作 Read；内容不足头部大小时补零（读取期间文件变短），超出部分丢弃
+struct EntryReader<'a> {
+    rx: &'a mut mpsc::Receiver<io::Result<Chunk>>,
+    remaining: u64,
+    pending: Vec<u8>,
+    offset: usize,
+    ended: bool,
+}
+
+impl<'a> EntryReader<'a> {
+    fn new(rx: &'a mut mpsc::Receiver<io::Result<Chunk>>, size: u64) -> Self {
+        Self { rx, remaining: size, pending: Vec::new(), offset: 0, ended: false }
+    }
+
+    /// 跳过该项剩余的内容块
+    fn drain(mut self) -> io::Result<()> {
+        while !self.ended {
+            self.next_chunk()?;
+        }
+        Ok(())
+    }
+
+    fn next_chunk(&mut self) -> io::Result<()> {
+        match self.rx.blocking_recv() {
+            Some(Ok(Chunk::Data(data))) => {
+                self.pending = data;
+                self.offset = 0;
+            }
+            Some(Err(e)) => return Err(e),
+            // 发送方出错时通道关闭，归档随后会被删除
+            Some(Ok(_)) | None => self.ended = true,
+        }
+        Ok(())
+    }
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        while self.offset == self.pending.len() && !self.ended {
+            self.next_chunk()?;
+        }
+        let want = buf.len().min(self.remaining.min(usize::MAX as u64) as usize);
+        let n = if self.offset < self.pending.len() {
+            let n = want.min(self.pending.len() - self.offset);
+            buf[..n].copy_from_slice(&self.pending[self.offset..self.offset + n]);
+            self.offset += n;
+            n
+        } else {
+            buf[..want].fill(0);
+            want
+        };
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+// ================== 写入归档（阻塞线程） ==================
+
+fn tar_header(header: &EntryHeader, entry_type: tar::EntryType) -> tar::Header {
+    let mut tar_header = tar::Header::new_gnu();
+    tar_header.set_entry_type(entry_type);
+    tar_header.set_size(if header.kind == EntryKind::File { header.size } else { 0 });
+    tar_header.set_mode(header.mode);
+    tar_header.set_mtime(header.mtime);
+    tar_header
+}
+
+fn write_tar<W: Write>(out: W, rx: &mut mpsc::Receiver<io::Result<Chunk>>) -> io::Result<W> {
+    let mut builder = tar::Builder::new(out);
+    while let Some(chunk) = rx.blocking_recv() {
+        let Chunk::Entry(header) = chunk? else { continue };
+        match header.kind {
+            EntryKind::Directory => {
+                let mut th = tar_header(&header, tar::EntryType::Directory);
+                builder.append_data(&mut th, &header.name, io::empty())?;
+            }
+            EntryKind::Symlink => {
+                let mut th = tar_header(&header, tar::EntryType::Symlink);
+                builder.append_link(&mut th, &header.name, header.link_target.as_deref().unwrap_or_default())?;
+            }
+            EntryKind::File => {
+                let mut th = tar_header(&header, tar::EntryType::Regular);
+                let mut data = EntryReader::new(rx, header.size);
+                builder.append_data(&mut th, &header.name, &mut data)?;
+                data.drain()?;
+            }
+        }
+    }
+    builder.into_inner()
+}
+
+/// zip 的修改时间是不带时区的 DOS 时间，按 UTC 保存
+fn zip_time(mtime: u64) -> zip::DateTime {
+    chrono::DateTime::from_timestamp(mtime as i64, 0)
+        .and_then(|t| {
+            zip::DateTime::from_date_and_time(
+                t.year() as u16,
+                t.month() as u8,
+                t.day() as u8,
+                t.hour() as u8,
+                t.minute() as u8,
+                t.second() as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
+fn unix_from_zip_time(time: zip::DateTime) -> u64 {
+    chrono::NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)
+        .and_then(|d| d.and_hms_opt(time.hour() as u32, time.minute() as u32, time.second() as u32))
+        .map(|t| t.and_utc().timestamp().max(0) as u64)
+        .unwrap_or(0)
+}
+
+fn write_zip<W: Write + Seek>(out: W, rx: &mut mpsc::Receiver<io::Result<Chunk>>) -> io::Result<W> {
+    let mut zip = zip::ZipWriter::new(out);
+    while let Some(chunk) = rx.blocking_recv() {
+        let Chunk::Entry(header) = chunk? else { continue };
+        let options = SimpleFileOptions::default()
+            .unix_permissions(header.mode)
+            .last_modified_time(zip_time(header.mtime));
+        match header.kind {
+            EntryKind::Directory => zip.add_directory(header.name.as_str(), options)?,
+            EntryKind::Symlink => {
+                zip.add_symlink(header.name.as_str(), header.link_target.as_deref().unwrap_or_default(), options)?
+            }
+            EntryKind::File => {
+                zip.start_file(header.name.as_str(), options.large_file(header.size >= ZIP64_THRESHOLD))?;
+                let mut data = EntryReader::new(rx, header.size);
+                io::copy(&mut data, &mut zip)?;
+                data.drain()?;
+            }
+        }
+    }
+    Ok(zip.finish()?)
+}
+
+/// 把消息流写成归档文件
+fn write_archive(path: &str, format: ArchiveFormat, mut rx: mpsc::Receiver<io::Result<Chunk>>) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    match format {
+        ArchiveFormat::Tar => write_tar(file, &mut rx)?.flush(),
+        ArchiveFormat::TarGz => write_tar(GzEncoder::new(file, Compression::default()), &mut rx)?.finish()?.flush(),
+        ArchiveFormat::Zip => write_zip(file, &mut rx)?.flush(),
+    }
+}
+
+// ================== 读取归档（阻塞线程） ==================
+
+fn send(tx: &mpsc::Sender<io::Result<Chunk>>, chunk: Chunk) -> io::Result<()> {
+    // SFTP 一侧出错后不再接收
+    tx.blocking_send(Ok(chunk)).map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "extraction stopped"))
+}
+
+fn send_data(tx: &mpsc::Sender<io::Result<Chunk>>, data: &mut impl Read) -> io::Result<()> {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = data.read(&mut buf)?;
+        if n == 0 {
+            return send(tx, Chunk::End);
+        }
+        send(tx, Chunk::Data(buf[..n].to_vec()))?;
+    }
+}
+
+fn read_tar<R: Read>(reader: R, tx: &mpsc::Sender<io::Result<Chunk>>) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+            tar::EntryType::Directory => EntryKind::Directory,
+            tar::EntryType::Symlink => EntryKind::Symlink,
+            // pax 扩展头由 tar crate 合并到后续项中
+            tar::EntryType::XGlobalHeader | tar::EntryType::XHeader => continue,
+            other => {
+                let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+                send(tx, Chunk::Skipped(format!("{}: unsupported entry type {:?}", name, other)))?;
+                continue;
+            }
+        };
+        let header = EntryHeader {
+            name: String::from_utf8_lossy(&entry.path_bytes()).into_owned(),
+            kind,
+            size: entry.size(),
+            mode: entry.header().mode().unwrap_or(0o644) & 0o7777,
+            mtime: entry.header().mtime().unwrap_or(0),
+            link_target: entry.link_name_bytes().map(|l| String::from_utf8_lossy(&l).into_owned()),
+        };
+        send(tx, Chunk::Entry(header))?;
+        if kind == EntryKind::File {
+            send_data(tx, &mut entry)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_zip<R: Read + Seek>(reader: R, tx: &mpsc::Sender<io::Result<Chunk>>) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let kind = if file.is_dir() {
+            EntryKind::Directory
+        } else if file.is_symlink() {
+            EntryKind::Symlink
+        } else {
+            EntryKind::File
+        };
+        let default_mode = if kind == EntryKind::Directory { 0o755 } else { 0o644 };
+        let mut header = EntryHeader {
+            name: file.name().to_string(),
+            kind,
+            size: file.size(),
+            mode: file.unix_mode().map(|m| m & 0o7777).unwrap_or(default_mode),
+            mtime: file.last_modified().map(unix_from_zip_time).unwrap_or(0),
+            link_target: None,
+        };
+        if kind == EntryKind::Symlink {
+            // 链接目标作为内容保存
+            let mut target = String::new();
+            file.read_to_string(&mut target)?;
+            header.link_target = Some(target);
+        }
+        send(tx, Chunk::Entry(header))?;
+        if kind == EntryKind::File {
+            // zip crate 在读到末尾时校验 CRC
+            send_data(tx, &mut file)?;
+        }
+    }
+    Ok(())
+}
+
+/// 读取归档并把各项送往 SFTP 一侧
+fn read_archive(path: &str, format: ArchiveFormat, tx: mpsc::Sender<io::Result<Chunk>>) {
+    let result = File::open(path).and_then(|file| {
+        let file = BufReader::new(file);
+        match format {
+            ArchiveFormat::Tar => read_tar(file, &tx),
+            ArchiveFormat::TarGz => read_tar(GzDecoder::new(file), &tx),
+            ArchiveFormat::Zip => read_zip(file, &tx),
+        }
+    });
+    if let Err(e) = result {
+        let _ = tx.blocking_send(Err(e));
+    }
+}
+
+// ================== 创建 ==================
+
+fn io_error(context: &str) -> impl Fn(io::Error) -> String + '_ {
+    move |e| format!("{}: {}", context, e)
+}
+
+fn remote_name(path: &str) -> String {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|n| !n.is_empty())
+        .unwrap_or("root")
+        .to_string()
+}
+
+/// 把远程文件/目录流式写入本地归档；目录递归打包，符号链接不跟随
+pub async fn create_local_archive<H: Handler>(
+    handle: &RwLock<Handle<H>>,
+    remote_paths: &[String],
+    archive_path: &str,
+    format: ArchiveFormat,
+) -> Result<ArchiveSummary, String> {
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    let (tx, rx) = mpsc::channel(QUEUE_CHUNKS);
+    let writer = {
+        let archive_path = archive_path.to_string();
+        tokio::task::spawn_blocking(move || write_archive(&archive_path, format, rx))
+    };
+    // 归档线程出错时通道关闭，具体错误由 writer 返回
+    let closed = || "Archive writer stopped".to_string();
+
+    let mut summary = ArchiveSummary {
+        archive_path: archive_path.to_string(),
+        format,
+        entries: Vec::new(),
+        total_bytes: 0,
+        skipped: Vec::new(),
+    };
+
+    let result = async {
+        // (远程路径, 归档内名称)
+        let mut pending: VecDeque<(String, String)> = remote_paths
+            .iter()
+            .map(|p| (p.trim_end_matches('/').to_string(), remote_name(p)))
+            .collect();
+
+        while let Some((path, name)) = pending.pop_front() {
+            let path = if path.is_empty() { "/".to_string() } else { path };
+            let attrs = match sftp.symlink_metadata(path.as_str()).await {
+                Ok(attrs) => attrs,
+                Err(e) => {
+                    summary.skipped.push(format!("{}: {}", path, e));
+                    continue;
+                }
+            };
+            let mode = attrs.permissions.unwrap_or(0o644) & 0o7777;
+            let mtime = attrs.mtime.unwrap_or(0) as u64;
+
+            match attrs.permissions.map(|p| p & 0o170000) {
+                Some(0o040000) => {
+                    let header = EntryHeader { name: name.clone(), kind: EntryKind::Directory, size: 0, mode, mtime, link_target: None };
+                    tx.send(Ok(Chunk::Entry(header))).await.map_err(|_| closed())?;
+                    summary.entries.push(ArchiveEntryInfo { name: name.clone(), kind: "directory".to_string(), size: 0, sha256: None });
+
+                    match sftp.read_dir(path.as_str()).await {
+                        Ok(entries) => {
+                            let mut children: Vec<String> = entries.map(|e| e.file_name()).collect();
+                            children.sort();
+                            for child in children {
+                                pending.push_back((format!("{}/{}", path.trim_end_matches('/'), child), format!("{}/{}", name, child)));
+                            }
+                        }
+                        Err(e) => summary.skipped.push(format!("{}: {}", path, e)),
+                    }
+                }
+                Some(0o120000) => {
+                    let target = sftp.read_link(path.as_str()).await.map_err(|e| format!("Failed to read link {}: {}", path, e))?;
+                    let header = EntryHeader { name: name.clone(), kind: EntryKind::Symlink, size: 0, mode, mtime, link_target: Some(target) };
+                    tx.send(Ok(Chunk::Entry(header))).await.map_err(|_| closed())?;
+                    summary.entries.push(ArchiveEntryInfo { name, kind: "symlink".to_string(), size: 0, sha256: None });
+                }
+                Some(0o100000) => {
+                    let size = attrs.size.unwrap_or(0);
+                    let mut file = match sftp.open(path.as_str()).await {
+                        Ok(file) => file,
+                        Err(e) => {
+                            summary.skipped.push(format!("{}: {}", path, e));
+                            continue;
+                        }
+                    };
+                    let header = EntryHeader { name: name.clone(), kind: EntryKind::File, size, mode, mtime, link_target: None };
+                    tx.send(Ok(Chunk::Entry(header))).await.map_err(|_| closed())?;
+
+                    // 只读取 stat 时的大小，持续增长的日志也能得到与头部一致的内容
+                    let mut hasher = Sha256::new();
+                    let mut read = 0u64;
+                    while read < size {
+                        let mut buf = vec![0u8; ((size - read) as usize).min(CHUNK_SIZE)];
+                        let n = file.read(&mut buf).await.map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                        if n == 0 {
+                            break;
+                        }
+                        buf.truncate(n);
+                        hasher.update(&buf);
+                        tx.send(Ok(Chunk::Data(buf))).await.map_err(|_| closed())?;
+                        read += n as u64;
+                    }
+                    tx.send(Ok(Chunk::End)).await.map_err(|_| closed())?;
+                    if read < size {
+                        summary.skipped.push(format!("{}: file shrank while reading ({} of {} bytes)", path, read, size));
+                    }
+
+                    summary.total_bytes += read;
+                    summary.entries.push(ArchiveEntryInfo {
+                        name,
+                        kind: "file".to_string(),
+                        size: read,
+                        sha256: Some(hex::encode(hasher.finalize())),
+                    });
+                }
+                _ => summary.skipped.push(format!("{}: not a regular file, directory or symlink", path)),
+            }
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+    let _ = sftp.close().await;
+    drop(tx);
+
+    let written = match writer.await {
+        Ok(written) => written.map_err(io_error("Failed to write archive")),
+        Err(e) => Err(format!("Failed to write archive: {}", e)),
+    };
+    match written.and(result) {
+        Ok(()) => Ok(summary),
+        Err(e) => {
+            let _ = std::fs::remove_file(archive_path);
+            Err(e)
+        }
+    }
+}
+
+// ================== 解包 ==================
+
+/// 归档内路径规范化；绝对路径或包含 ".." 的项会写到目标目录之外，拒绝处理
+fn sanitize_entry_name(name: &str) -> Option<String> {
+    let name = name.replace('\\', "/");
+    if name.starts_with('/') || name.as_bytes().get(1) == Some(&b':') {
+        return None;
+    }
+    let parts: Vec<&str> = name.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+    if parts.is_empty() || parts.contains(&"..") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// 符号链接目标只允许指向归档内部，否则后续项可能经由链接写到目标目录之外
+fn is_contained_link(name: &str, target: &str) -> bool {
+    if target.starts_with('/') {
+        return false;
+    }
+    let mut depth = name.matches('/').count() as i64;
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => depth -= 1,
+            _ => depth += 1,
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    true
+}
+
+async fn ensure_remote_dir(sftp: &SftpSession, created: &mut HashSet<String>, path: &str) -> Result<(), String> {
+    let mut current = String::new();
+    for part in path.split('/') {
+        if part.is_empty() {
+            current.push('/');
+            continue;
+        }
+        if !current.is_empty() && !current.ends_with('/') {
+            current.push('/');
+        }
+        current.push_str(part);
+        if created.contains(&current) {
+            continue;
+        }
+        if !sftp.try_exists(current.as_str()).await.unwrap_or(false) {
+            sftp.create_dir(current.as_str())
+                .await
+                .map_err(|e| format!("Failed to create remote directory {}: {}", current, e))?;
+        }
+        created.insert(current.clone());
+    }
+    Ok(())
+}
+
+/// 经由本次解包创建的符号链接的项：返回途经的链接。
+/// 链接本身的目标在归档内，但链接之上再建链接（a/l -> ..、a/l/m -> ..）就能逐级跳出目标目录
+fn through_archive_link<'a>(name: &str, links: &'a HashSet<String>) -> Option<&'a String> {
+    name.match_indices('/').find_map(|(pos, _)| links.get(&name[..pos]))
+}
+
+/// 在目标目录下逐级创建 rel 的各级目录；已存在的各级用 lstat 检查，
+/// 不是真正的目录（例如目标目录中原有的符号链接）时返回拒绝原因
+async fn ensure_extract_dir(
+    sftp: &SftpSession,
+    created: &mut HashSet<String>,
+    root: &str,
+    rel: &str,
+) -> Result<Option<String>, String> {
+    let mut current = root.to_string();
+    for part in rel.split('/').filter(|p| !p.is_empty()) {
+        current.push('/');
+        current.push_str(part);
+        if created.contains(&current) {
+            continue;
+        }
+        match sftp.symlink_metadata(current.as_str()).await {
+            Ok(attrs) if attrs.permissions.map(|p| p & 0o170000) == Some(0o040000) => {}
+            Ok(_) => return Ok(Some(format!("{}: not a directory, refusing to extract through it", current))),
+            Err(_) => sftp
+                .create_dir(current.as_str())
+                .await
+                .map_err(|e| format!("Failed to create remote directory {}: {}", current, e))?,
+        }
+        created.insert(current.clone());
+    }
+    Ok(None)
+}
+
+/// 一个文件项的下一块内容，None 表示该项结束
+async fn next_data(rx: &mut mpsc::Receiver<io::Result<Chunk>>) -> Result<Option<Vec<u8>>, String> {
+    match rx.recv().await {
+        Some(Ok(Chunk::Data(data))) => Ok(Some(data)),
+        Some(Ok(_)) | None => Ok(None),
+        Some(Err(e)) => Err(format!("Failed to read archive: {}", e)),
+    }
+}
+
+/// 把本地归档解包到远程目录
+pub async fn extract_local_archive<H: Handler>(
+    handle: &RwLock<Handle<H>>,
+    archive_path: &str,
+    remote_dir: &str,
+    format: ArchiveFormat,
+) -> Result<ArchiveSummary, String> {
+    std::fs::metadata(archive_path).map_err(io_error("Failed to open archive"))?;
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    let root = remote_dir.trim_end_matches('/');
+    let mut created = HashSet::new();
+    ensure_remote_dir(&sftp, &mut created, if root.is_empty() { "/" } else { root }).await?;
+    // 本次解包创建的符号链接（归档内路径）
+    let mut links = HashSet::new();
+
+    let (tx, mut rx) = mpsc::channel(QUEUE_CHUNKS);
+    let reader = {
+        let archive_path = archive_path.to_string();
+        tokio::task::spawn_blocking(move || read_archive(&archive_path, format, tx))
+    };
+
+    let mut summary = ArchiveSummary {
+        archive_path: archive_path.to_string(),
+        format,
+        entries: Vec::new(),
+        total_bytes: 0,
+        skipped: Vec::new(),
+    };
+
+    let result = async {
+        while let Some(chunk) = rx.recv().await {
+            let header = match chunk.map_err(io_error("Failed to read archive"))? {
+                Chunk::Entry(header) => header,
+                Chunk::Skipped(reason) => {
+                    summary.skipped.push(reason);
+                    continue;
+                }
+                Chunk::Data(_) | Chunk::End => continue,
+            };
+            let Some(name) = sanitize_entry_name(&header.name) else {
+                summary.skipped.push(format!("{}: path escapes the target directory", header.name));
+                continue;
+            };
+            if let Some(link) = through_archive_link(&name, &links) {
+                summary.skipped.push(format!("{}: path goes through symlink {}", name, link));
+                continue;
+            }
+            let target = format!("{}/{}", root, name);
+            let parent = name.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+            let dir = if header.kind == EntryKind::Directory { name.as_str() } else { parent };
+            if let Some(reason) = ensure_extract_dir(&sftp, &mut created, root, dir).await? {
+                summary.skipped.push(reason);
+                continue;
+            }
+
+            match header.kind {
+                EntryKind::Directory => {
+                    summary.entries.push(ArchiveEntryInfo { name, kind: "directory".to_string(), size: 0, sha256: None });
+                }
+                EntryKind::Symlink => {
+                    let link = header.link_target.clone().unwrap_or_default();
+                    if !is_contained_link(&name, &link) {
+                        summary.skipped.push(format!("{}: symlink points outside the archive ({})", name, link));
+                        continue;
+                    }
+                    // OpenSSH 的 sftp-server 按（目标，链接）顺序解析 SSH_FXP_SYMLINK
+                    sftp.symlink(link.as_str(), target.as_str())
+                        .await
+                        .map_err(|e| format!("Failed to create symlink {}: {}", target, e))?;
+                    links.insert(name.clone());
+                    summary.entries.push(ArchiveEntryInfo { name, kind: "symlink".to_string(), size: 0, sha256: None });
+                }
+                EntryKind::File => {
+                    // 已存在的符号链接会被 create 跟随
+                    if let Ok(attrs) = sftp.symlink_metadata(target.as_str()).await {
+                        if attrs.permissions.map(|p| p & 0o170000) != Some(0o100000) {
+                            summary.skipped.push(format!("{}: existing path is not a regular file", target));
+                            continue;
+                        }
+                    }
+                    let mut file = sftp
+                        .create(target.as_str())
+                        .await
+                        .map_err(|e| format!("Failed to create remote file {}: {}", target, e))?;
+
+                    let mut hasher = Sha256::new();
+                    let mut written = 0u64;
+                    while let Some(data) = next_data(&mut rx).await? {
+                        hasher.update(&data);
+                        file.write_all(&data).await.map_err(|e| format!("Failed to write {}: {}", target, e))?;
+                        written += data.len() as u64;
+                    }
+                    file.shutdown().await.map_err(|e| format!("Failed to write {}: {}", target, e))?;
+
+                    // 去掉 setuid/setgid 位，避免解包出提权文件
+                    let mut attrs = FileAttributes::empty();
+                    attrs.permissions = Some(header.mode & 0o1777);
+                    if header.mtime > 0 {
+                        attrs.mtime = Some(header.mtime as u32);
+                        attrs.atime = attrs.mtime;
+                    }
+                    let _ = sftp.set_metadata(target.as_str(), attrs).await;
+
+                    summary.total_bytes += written;
+                    summary.entries.push(ArchiveEntryInfo {
+                        name,
+                        kind: EntryKind::File.as_str().to_string(),
+                        size: written,
+                        sha256: Some(hex::encode(hasher.finalize())),
+                    });
+                }
+            }
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+    let _ = sftp.close().await;
+    // 出错时关闭通道，读取线程随即退出
+    drop(rx);
+    let _ = reader.await;
+    result.map(|_| summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_header(name: &str, size: u64) -> EntryHeader {
+        EntryHeader { name: name.to_string(), kind: EntryKind::File, size, mode: 0o640, mtime: 1_700_000_000, link_target: None }
+    }
+
+    /// 经由归档线程的读写函数写出再读回
+    fn round_trip(format: ArchiveFormat, chunks: Vec<Chunk>) -> Vec<Chunk> {
+        let path = std::env::temp_dir().join(format!("lovelyres-archive-{}", uuid::Uuid::new_v4()));
+        let path = path.to_string_lossy().into_owned();
+
+        let (tx, rx) = mpsc::channel(chunks.len() + 1);
+        for chunk in chunks {
+            tx.try_send(Ok(chunk)).unwrap();
+        }
+        drop(tx);
+        write_archive(&path, format, rx).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(64);
+        let reader = {
+            let path = path.clone();
+            std::thread::spawn(move || read_archive(&path, format, tx))
+        };
+        let mut read = Vec::new();
+        while let Some(chunk) = rx.blocking_recv() {
+            read.push(chunk.unwrap());
+        }
+        reader.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+        read
+    }
+
+    fn entry(chunk: &Chunk) -> &EntryHeader {
+        match chunk {
+            Chunk::Entry(header) => header,
+            other => panic!("expected an entry, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tar_round_trip_with_long_names() {
+        let long_name = format!("var/log/{}/access.log", "nested".repeat(20));
+        let read = round_trip(
+            ArchiveFormat::TarGz,
+            vec![
+                Chunk::Entry(EntryHeader { kind: EntryKind::Directory, size: 0, mode: 0o755, ..file_header("var", 0) }),
+                Chunk::Entry(file_header(&long_name, 5)),
+                Chunk::Data(b"hel".to_vec()),
+                Chunk::End, // 文件变短时补零
+                Chunk::Entry(EntryHeader { kind: EntryKind::Symlink, link_target: Some("../x".to_string()), ..file_header("var/link", 0) }),
+            ],
+        );
+
+        let dir = entry(&read[0]);
+        assert_eq!((dir.name.trim_end_matches('/'), dir.kind, dir.mode), ("var", EntryKind::Directory, 0o755));
+        let file = entry(&read[1]);
+        assert_eq!((file.name.as_str(), file.size, file.mtime), (long_name.as_str(), 5, 1_700_000_000));
+        assert!(matches!(&read[2], Chunk::Data(data) if data == b"hel\0\0"));
+        assert!(matches!(read[3], Chunk::End));
+        assert_eq!(entry(&read[4]).link_target.as_deref(), Some("../x"));
+        assert_eq!(read.len(), 5);
+    }
+
+    #[test]
+    fn zip_round_trip() {
+        let read = round_trip(
+            ArchiveFormat::Zip,
+            vec![
+                Chunk::Entry(EntryHeader { kind: EntryKind::Directory, mode: 0o755, ..file_header("etc", 0) }),
+                Chunk::Entry(file_header("etc/passwd", 67)),
+                Chunk::Data(b"root:x:0:0:root:/root:/bin/bash\n".to_vec()),
+                Chunk::Data(b"daemon:x:1:1::/:/usr/sbin/nologin\n".to_vec()),
+                Chunk::End,
+                Chunk::Entry(EntryHeader { kind: EntryKind::Symlink, link_target: Some("passwd".to_string()), ..file_header("etc/users", 0) }),
+            ],
+        );
+
+        assert_eq!((entry(&read[0]).name.trim_end_matches('/'), entry(&read[0]).kind), ("etc", EntryKind::Directory));
+        let file = entry(&read[1]);
+        assert_eq!((file.name.as_str(), file.mode, file.size, file.mtime), ("etc/passwd", 0o640, 67, 1_700_000_000));
+        let content: Vec<u8> = read[2..]
+            .iter()
+            .map_while(|c| match c {
+                Chunk::Data(data) => Some(data.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert!(content.starts_with(b"root:x:0:0") && content.len() == 67);
+        let link = entry(read.last().unwrap());
+        assert_eq!((link.kind, link.link_target.as_deref()), (EntryKind::Symlink, Some("passwd")));
+    }
+
+    #[test]
+    fn rejects_entries_escaping_the_target() {
+        assert_eq!(sanitize_entry_name("./html/index.php").as_deref(), Some("html/index.php"));
+        assert_eq!(sanitize_entry_name("../../etc/cron.d/x"), None);
+        assert_eq!(sanitize_entry_name("/etc/passwd"), None);
+        assert_eq!(sanitize_entry_name("C:\\Windows\\x"), None);
+
+        assert!(is_contained_link("html/current", "releases/v2"));
+        assert!(is_contained_link("html/a/b", "../c"));
+        assert!(!is_contained_link("html/current", "../../etc"));
+        assert!(!is_contained_link("html/current", "/etc/shadow"));
+    }
+
+    #[test]
+    fn refuses_entries_through_chained_symlinks() {
+        // 每个链接单独看都在归档内，叠加后 a/l/m 指向目标目录的上一级
+        let mut links = HashSet::new();
+        assert!(is_contained_link("a/l", ".."));
+        links.insert("a/l".to_string());
+        assert!(is_contained_link("a/l/m", ".."));
+        assert_eq!(through_archive_link("a/l/m", &links).map(String::as_str), Some("a/l"));
+        assert_eq!(through_archive_link("a/l/m/etc/cron.d/x", &links).map(String::as_str), Some("a/l"));
+
+        assert_eq!(through_archive_link("a/l", &links), None);
+        assert_eq!(through_archive_link("a/lib/x", &links), None);
+        assert_eq!(through_archive_link("b/l/x", &links), None);
+    }
+
+    #[test]
+    fn detects_format_from_extension() {
+        assert_eq!(ArchiveFormat::from_path("/tmp/evidence.TAR.GZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_path("logs.tgz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_path("site.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::from_path("notes.txt"), None);
+    }
+}
@@ -1,6 +1,7 @@
 // SSH连接管理器
 // 负责SSH连接的持久化存储和加密功能
 
+use crate::ssh_manager_russh::{JumpHostConfig, SSHCredentials};
 use crate::types::{AppDataPaths, LovelyResError, LovelyResResult, SSHConnection};
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
@@ -12,6 +13,46 @@ use serde_json;
 use std::fs;
 use std::path::Path;
 
+/// 展开连接的跳板机链：跳板机自身配置的跳板机排在它前面，按连接顺序返回。
+/// 已在链中的跳板机不再重复经过（如 target -> [bastion, inner]、inner -> [bastion]，
+/// 即 ssh -J bastion,inner）；沿当前引用路径回到自身才是循环
+fn jump_chain<'a>(connections: &'a [SSHConnection], connection_id: &str) -> LovelyResResult<Vec<&'a SSHConnection>> {
+    fn expand<'a>(
+        connections: &'a [SSHConnection],
+        connection_id: &str,
+        path: &mut Vec<String>,
+        chain: &mut Vec<&'a SSHConnection>,
+    ) -> LovelyResResult<&'a SSHConnection> {
+        let connection = connections
+            .iter()
+            .find(|c| c.id == connection_id)
+            .ok_or_else(|| LovelyResError::NotFound(format!("跳板机连接不存在: {}", connection_id)))?;
+        path.push(connection_id.to_string());
+
+        for hop_id in &connection.jump_hosts {
+            if path.contains(hop_id) {
+                return Err(LovelyResError::ConfigError(format!(
+                    "跳板机配置存在循环引用: {} -> {}",
+                    path.join(" -> "),
+                    hop_id
+                )));
+            }
+            if chain.iter().any(|c| &c.id == hop_id) {
+                continue;
+            }
+            let hop = expand(connections, hop_id, path, chain)?;
+            chain.push(hop);
+        }
+
+        path.pop();
+        Ok(connection)
+    }
+
+    let mut chain = Vec::new();
+    expand(connections, connection_id, &mut Vec::new(), &mut chain)?;
+    Ok(chain)
+}
+
 /// SSH连接管理器
 pub struct SSHConnectionManager {
     data_paths: AppDataPaths,
@@ -138,6 +179,74 @@ impl SSHConnectionManager {
             .map_err(|e| LovelyResError::AuthError(format!("解密结果不是有效UTF-8: {}", e)))
     }
 
+    /// 解析连接的跳板机链，返回按顺序排列的跳板机凭证
+    pub fn resolve_jump_hosts(&self, connection_id: &str) -> LovelyResResult<Vec<JumpHostConfig>> {
+        let connections = self.load_connections()?;
+        if !connections.iter().any(|c| c.id == connection_id) {
+            return Err(LovelyResError::NotFound(format!("SSH连接不存在: {}", connection_id)));
+        }
+
+        let mut hops = Vec::new();
+        for hop in jump_chain(&connections, connection_id)? {
+            let account = hop.get_active_account().ok_or_else(|| {
+                LovelyResError::ConfigError(format!("跳板机 {} 没有可用账号", hop.name))
+            })?;
+
+            let password = match &account.encrypted_password {
+                Some(encrypted) if !encrypted.is_empty() => Some(self.decrypt_password(encrypted)?),
+                _ => None,
+            };
+            let (private_key, key_passphrase) = if account.auth_type == "password" {
+                (None, None)
+            } else {
+                (account.key_path.clone(), self.decrypt_key_passphrase(account.key_passphrase.as_deref()))
+            };
+
+            hops.push(JumpHostConfig {
+                host: hop.host.clone(),
+                port: hop.port,
+                username: account.username.clone(),
+                credentials: SSHCredentials {
+                    password,
+                    private_key,
+                    key_passphrase,
+                    certificate: if account.auth_type == "certificate" {
+                        account.certificate_path.clone()
+                    } else {
+                        None
+                    },
+                    use_agent: account.auth_type == "agent",
+                    prompter: None,
+                },
+            });
+        }
+
+        Ok(hops)
+    }
+
+    /// 已保存连接中该用户名账号的私钥密码短语（已解密），前端未传入密码短语时使用
+    pub fn stored_key_passphrase(&self, connection_id: &str, username: &str) -> LovelyResResult<Option<String>> {
+        let connections = self.load_connections()?;
+        let Some(connection) = connections.iter().find(|c| c.id == connection_id) else {
+            return Ok(None);
+        };
+        let stored = match connection.accounts.iter().find(|a| a.username == username) {
+            Some(account) => account.key_passphrase.as_deref(),
+            None if connection.username == username => connection.key_passphrase.as_deref(),
+            None => None,
+        };
+        Ok(self.decrypt_key_passphrase(stored))
+    }
+
+    /// 解密私钥密码短语，旧数据中以明文保存的密码短语原样返回
+    pub fn decrypt_key_passphrase(&self, key_passphrase: Option<&str>) -> Option<String> {
+        let passphrase = key_passphrase.filter(|p| !p.is_empty())?;
+        Some(
+            self.decrypt_password(passphrase)
+                .unwrap_or_else(|_| passphrase.to_string()),
+        )
+    }
+
     /// 获取或创建加密密钥
     fn get_or_create_encryption_key(data_paths: &AppDataPaths) -> LovelyResResult<[u8; 32]> {
         let key_file = data_paths.app_data_dir.join("encryption.key");
@@ -279,3 +388,59 @@ impl SSHConnectionManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(id: &str, jump_hosts: &[&str]) -> SSHConnection {
+        SSHConnection {
+            id: id.to_string(),
+            host: format!("{}.example.com", id),
+            jump_hosts: jump_hosts.iter().map(|h| h.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn jump_hosts_are_followed_recursively() {
+        let connections = vec![
+            connection("target", &["inner"]),
+            connection("inner", &["bastion"]),
+            connection("bastion", &[]),
+        ];
+        let chain: Vec<&str> = jump_chain(&connections, "target").unwrap().iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(chain, vec!["bastion", "inner"]);
+        assert!(jump_chain(&connections, "bastion").unwrap().is_empty());
+    }
+
+    #[test]
+    fn shared_jump_hosts_are_used_once() {
+        // ssh -J bastion,inner：inner 自己也经由 bastion
+        let connections = vec![
+            connection("target", &["bastion", "inner"]),
+            connection("inner", &["bastion"]),
+            connection("bastion", &[]),
+            connection("db", &["inner", "bastion"]),
+        ];
+        let ids = |id| -> Vec<String> { jump_chain(&connections, id).unwrap().iter().map(|c| c.id.clone()).collect() };
+        assert_eq!(ids("target"), vec!["bastion", "inner"]);
+        assert_eq!(ids("db"), vec!["bastion", "inner"]);
+    }
+
+    #[test]
+    fn jump_host_cycles_are_rejected() {
+        let connections = vec![
+            connection("target", &["a"]),
+            connection("a", &["b"]),
+            connection("b", &["a"]),
+            connection("self", &["self"]),
+            connection("back", &["c"]),
+            connection("c", &["back"]),
+        ];
+        assert!(jump_chain(&connections, "target").is_err());
+        assert!(jump_chain(&connections, "self").is_err());
+        assert!(jump_chain(&connections, "back").is_err());
+        assert!(jump_chain(&[connection("x", &["missing"])], "x").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+// 远程大文件的分段读取
+// 按偏移/长度或从末尾读取一段内容，并借助远程 awk 建立稀疏行索引，便于分页查看超大文件
+
+use crate::ssh_file_transfer;
+use russh::client::{Handle, Handler};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::RwLock;
+
+/// 单次读取的长度上限
+pub const MAX_RANGE_LENGTH: u64 = 16 * 1024 * 1024;
+/// 默认每隔多少行记录一个偏移
+pub const DEFAULT_LINE_INDEX_INTERVAL: u64 = 1000;
+
+/// 读取位置
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum RangeRequest {
+    /// 从 offset 开始读取最多 length 字节；不足一个完整字符时读满该字符
+    Offset { offset: u64, length: u64 },
+    /// 读取文件最后 bytes 字节
+    Tail { bytes: u64 },
+}
+
+/// 读取结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRange {
+    pub path: String,
+    /// 本段在文件中的起始偏移
+    pub offset: u64,
+    /// 本段实际包含的字节数；下一段从 offset + length 开始
+    pub length: u64,
+    /// 读取时的文件大小
+    pub file_size: u64,
+    /// 本段已到达文件末尾
+    pub eof: bool,
+    /// 内容（UTF-8，无效字节替换为 U+FFFD，末尾被截断的多字节字符留给下一段）
+    pub content: String,
+}
+
+/// 稀疏行索引：offsets[i] 为第 i * interval + 1 行的起始偏移
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineIndex {
+    pub path: String,
+    pub interval: u64,
+    pub total_lines: u64,
+    pub offsets: Vec<u64>,
+}
+
+impl LineIndex {
+    /// 定位第 line 行（从 1 开始）：返回最近的已知偏移，以及从该偏移起还需跳过的行数
+    pub fn locate(&self, line: u64) -> Option<(u64, u64)> {
+        if line == 0 || line > self.total_lines.max(1) {
+            return None;
+        }
+        let slot = ((line - 1) / self.interval) as usize;
+        let offset = *self.offsets.get(slot)?;
+        Some((offset, (line - 1) % self.interval))
+    }
+}
+
+/// 读取远程文件的一段内容
+pub async fn read_range<H: Handler>(handle: &RwLock<Handle<H>>, path: &str, request: RangeRequest) -> Result<FileRange, String> {
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    let file_size = sftp
+        .metadata(path)
+        .await
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .size
+        .unwrap_or(0);
+
+    let (offset, length) = match request {
+        RangeRequest::Offset { offset, length } => (offset.min(file_size), length.min(MAX_RANGE_LENGTH)),
+        RangeRequest::Tail { bytes } => {
+            let bytes = bytes.min(MAX_RANGE_LENGTH);
+            (file_size.saturating_sub(bytes), bytes)
+        }
+    };
+    let length = length.min(file_size - offset);
+
+    let mut file = sftp
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(|e| format!("Failed to seek file: {}", e))?;
+
+    let mut data = Vec::with_capacity(length as usize);
+    (&mut file)
+        .take(length)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut reached_eof = offset + data.len() as u64 >= file_size;
+
+    // 请求的长度不足一个字符时补读到该字符结束，否则分页会停在同一偏移上
+    while !reached_eof {
+        let missing = first_char_missing(&data);
+        if missing == 0 {
+            break;
+        }
+        let read = (&mut file)
+            .take(missing as u64)
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break; // 文件在读取期间被截短
+        }
+        reached_eof = offset + data.len() as u64 >= file_size;
+    }
+    let _ = sftp.close().await;
+
+    // 段内末尾的不完整字符留给下一段，文件末尾则原样替换
+    let keep = if reached_eof { data.len() } else { complete_utf8_prefix(&data) };
+    data.truncate(keep);
+
+    Ok(FileRange {
+        path: path.to_string(),
+        offset,
+        length: data.len() as u64,
+        file_size,
+        eof: reached_eof,
+        content: String::from_utf8_lossy(&data).into_owned(),
+    })
+}
+
+/// 以 lead 开头的 UTF-8 序列长度，ASCII 与无效字节按 1 计
+fn utf8_sequence_len(lead: u8) -> usize {
+    match lead {
+        b if b & 0b1110_0000 == 0b1100_0000 => 2,
+        b if b & 0b1111_0000 == 0b1110_0000 => 3,
+        b if b & 0b1111_1000 == 0b1111_0000 => 4,
+        _ => 1,
+    }
+}
+
+/// 段内连一个完整字符都没有时，还需读入多少字节
+fn first_char_missing(data: &[u8]) -> usize {
+    if complete_utf8_prefix(data) > 0 {
+        return 0;
+    }
+    match data.first() {
+        Some(&lead) => utf8_sequence_len(lead).saturating_sub(data.len()),
+        None => 1,
+    }
+}
+
+/// 去掉末尾被截断的 UTF-8 多字节序列后的长度
+fn complete_utf8_prefix(data: &[u8]) -> usize {
+    // 最多回看 3 个字节寻找序列起始字节
+    for back in 1..=data.len().min(3) {
+        let byte = data[data.len() - back];
+        if byte & 0b1100_0000 == 0b1000_0000 {
+            continue; // 后续字节
+        }
+        // ASCII 与无效字节按 1 计，总是完整的
+        return if back < utf8_sequence_len(byte) { data.len() - back } else { data.len() };
+    }
+    data.len()
+}
+
+/// 在远程生成行索引的命令；按字节计数（LC_ALL=C），最后一行输出 "total <行数> <字节数>"
+pub fn line_index_command(path: &str, interval: u64) -> String {
+    format!(
+        "LC_ALL=C awk -v n={} 'BEGIN {{ off = 0 }} (NR - 1) % n == 0 {{ print off }} {{ off += length($0) + 1 }} END {{ print \"total\", NR, off }}' < '{}'",
+        interval.max(1),
+        path.replace('\'', "'\\''")
+    )
+}
+
+/// 解析 line_index_command 的输出
+pub fn parse_line_index(path: &str, interval: u64, output: &str) -> Result<LineIndex, String> {
+    let mut offsets = Vec::new();
+    let mut total_lines = None;
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(rest) = line.strip_prefix("total") {
+            total_lines = rest.split_whitespace().next().and_then(|n| n.parse().ok());
+        } else {
+            offsets.push(line.parse().map_err(|_| format!("Unexpected line index output: {}", line))?);
+        }
+    }
+    Ok(LineIndex {
+        path: path.to_string(),
+        interval: interval.max(1),
+        total_lines: total_lines.ok_or("Line index output is incomplete")?,
+        offsets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_incomplete_utf8_for_next_range() {
+        let text = "日志".as_bytes();
+        assert_eq!(complete_utf8_prefix(text), 6);
+        assert_eq!(complete_utf8_prefix(&text[..5]), 3);
+        assert_eq!(complete_utf8_prefix(&text[..4]), 3);
+        assert_eq!(complete_utf8_prefix(b"abc"), 3);
+    }
+
+    #[test]
+    fn range_shorter_than_one_char_reads_the_whole_char() {
+        let text = "日志".as_bytes();
+        assert_eq!(first_char_missing(&text[..1]), 2);
+        assert_eq!(first_char_missing(&text[..2]), 1);
+        assert_eq!(first_char_missing(&text[..3]), 0);
+        assert_eq!(first_char_missing(&text[..4]), 0);
+        assert_eq!(first_char_missing(b""), 1);
+        assert_eq!(first_char_missing(b"a"), 0);
+        assert_eq!(first_char_missing("😀".as_bytes().split_at(1).0), 3);
+    }
+
+    #[test]
+    fn parses_and_locates_lines() {
+        let index = parse_line_index("/var/log/x", 2, "0\n20\n41\ntotal 5 60\n").unwrap();
+        assert_eq!(index.offsets, vec![0, 20, 41]);
+        assert_eq!(index.locate(1), Some((0, 0)));
+        assert_eq!(index.locate(4), Some((20, 1)));
+        assert_eq!(index.locate(5), Some((41, 0)));
+        assert_eq!(index.locate(6), None);
+        assert!(parse_line_index("/var/log/x", 2, "0\n").is_err());
+    }
+}
@@ -0,0 +1,469 @@
+// SFTP 目录传输与镜像同步
+// 递归上传/下载整个目录，支持 include/exclude 通配符；镜像模式只传输变化的文件（类似 rsync）
+
+use crate::ssh_file_transfer::{self, TransferDirection, TransferJob, TransferOptions, TransferQueue};
+use crate::types::FileTransferTask;
+use glob::Pattern;
+use russh::client::{Handle, Handler};
+use russh_sftp::client::SftpSession;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::sync::RwLock;
+
+/// 镜像模式下判断文件是否变化的方式
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncCompare {
+    /// 大小与修改时间（秒）都相同视为未变化
+    #[default]
+    SizeMtime,
+    /// 大小相同时再比较 SHA-256
+    Hash,
+}
+
+/// 目录传输选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncOptions {
+    /// 只传输匹配任一模式的文件（为空时不限制）
+    pub include: Vec<String>,
+    /// 跳过匹配任一模式的文件和目录
+    pub exclude: Vec<String>,
+    /// 镜像模式：跳过目标端未变化的文件
+    pub mirror: bool,
+    pub compare: SyncCompare,
+    /// 镜像模式下删除目标端多出的文件和目录
+    pub delete: bool,
+    /// 每个文件传输完成后校验 SHA-256
+    pub verify: bool,
+}
+
+/// 目录传输结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectorySyncSummary {
+    /// 已加入传输队列的文件
+    pub transfers: Vec<FileTransferTask>,
+    /// 未变化而跳过的文件（相对路径）
+    pub skipped: Vec<String>,
+    /// 新建的目录（相对路径）
+    pub created_directories: Vec<String>,
+    /// 删除的目标端文件和目录（相对路径）
+    pub deleted: Vec<String>,
+}
+
+/// 目录树中的一个文件
+#[derive(Debug, Clone, PartialEq)]
+struct FileEntry {
+    size: u64,
+    mtime: Option<u64>,
+}
+
+/// 一棵目录树，键为使用 "/" 分隔的相对路径
+#[derive(Debug, Default)]
+struct TreeListing {
+    dirs: BTreeSet<String>,
+    files: BTreeMap<String, FileEntry>,
+}
+
+/// include/exclude 过滤器；模式同时匹配相对路径和文件名
+struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    fn new(options: &SyncOptions) -> Result<Self, String> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).map_err(|e| format!("Invalid glob pattern '{}': {}", p, e)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: compile(&options.include)?,
+            exclude: compile(&options.exclude)?,
+        })
+    }
+
+    fn matches_any(patterns: &[Pattern], relative_path: &str) -> bool {
+        let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+        patterns.iter().any(|p| p.matches(relative_path) || p.matches(name))
+    }
+
+    /// 目录只受 exclude 影响，被排除的目录整体跳过
+    fn allows_dir(&self, relative_path: &str) -> bool {
+        !Self::matches_any(&self.exclude, relative_path)
+    }
+
+    fn allows_file(&self, relative_path: &str) -> bool {
+        (self.include.is_empty() || Self::matches_any(&self.include, relative_path))
+            && !Self::matches_any(&self.exclude, relative_path)
+    }
+}
+
+/// 源目录与目标目录对比后的操作计划
+#[derive(Debug, Default, PartialEq)]
+struct SyncPlan {
+    create_dirs: Vec<String>,
+    transfer: Vec<String>,
+    /// 大小相同、需要比较 SHA-256 才能确定是否变化的文件
+    hash_check: Vec<String>,
+    skipped: Vec<String>,
+    delete_files: Vec<String>,
+    delete_dirs: Vec<String>,
+}
+
+fn plan_sync(source: &TreeListing, destination: &TreeListing, options: &SyncOptions) -> SyncPlan {
+    let mut plan = SyncPlan {
+        create_dirs: source.dirs.difference(&destination.dirs).cloned().collect(),
+        ..Default::default()
+    };
+
+    for (path, entry) in &source.files {
+        let existing = destination.files.get(path);
+        match existing {
+            Some(dest) if options.mirror && dest.size == entry.size => match options.compare {
+                SyncCompare::SizeMtime if entry.mtime.is_some() && dest.mtime == entry.mtime => {
+                    plan.skipped.push(path.clone())
+                }
+                SyncCompare::SizeMtime => plan.transfer.push(path.clone()),
+                SyncCompare::Hash => plan.hash_check.push(path.clone()),
+            },
+            _ => plan.transfer.push(path.clone()),
+        }
+    }
+
+    if options.mirror && options.delete {
+        plan.delete_files = destination
+            .files
+            .keys()
+            .filter(|p| !source.files.contains_key(*p))
+            .cloned()
+            .collect();
+        // 由深到浅删除目录
+        plan.delete_dirs = destination.dirs.difference(&source.dirs).cloned().collect();
+        plan.delete_dirs.sort_by_key(|p| std::cmp::Reverse(p.matches('/').count()));
+    }
+    plan
+}
+
+fn join_remote(root: &str, relative_path: &str) -> String {
+    if relative_path.is_empty() {
+        return root.to_string();
+    }
+    format!("{}/{}", root.trim_end_matches('/'), relative_path)
+}
+
+fn join_local(root: &str, relative_path: &str) -> PathBuf {
+    relative_path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .fold(PathBuf::from(root), |path, part| path.join(part))
+}
+
+fn join_relative(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// 递归列出本地目录；符号链接不跟随，避免循环
+async fn list_local_tree(root: &str, filter: &PathFilter) -> Result<TreeListing, String> {
+    let mut listing = TreeListing::default();
+    let mut pending = vec![String::new()];
+
+    while let Some(relative_dir) = pending.pop() {
+        let dir_path = join_local(root, &relative_dir);
+        let mut entries = tokio::fs::read_dir(&dir_path)
+            .await
+            .map_err(|e| format!("Failed to read local directory {}: {}", dir_path.display(), e))?;
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to read local directory {}: {}", dir_path.display(), e))?
+        {
+            let relative = join_relative(&relative_dir, &entry.file_name().to_string_lossy());
+            let metadata = match entry.metadata().await {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                if filter.allows_dir(&relative) {
+                    listing.dirs.insert(relative.clone());
+                    pending.push(relative);
+                }
+            } else if metadata.is_file() && filter.allows_file(&relative) {
+                let mtime = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs());
+                listing.files.insert(relative, FileEntry { size: metadata.len(), mtime });
+            }
+        }
+    }
+    Ok(listing)
+}
+
+/// 递归列出远程目录；符号链接不跟随
+async fn list_remote_tree(sftp: &SftpSession, root: &str, filter: &PathFilter) -> Result<TreeListing, String> {
+    let mut listing = TreeListing::default();
+    let mut pending = vec![String::new()];
+
+    while let Some(relative_dir) = pending.pop() {
+        let dir_path = join_remote(root, &relative_dir);
+        let entries = sftp
+            .read_dir(dir_path.as_str())
+            .await
+            .map_err(|e| format!("Failed to read remote directory {}: {}", dir_path, e))?;
+
+        for entry in entries {
+            let relative = join_relative(&relative_dir, &entry.file_name());
+            if entry.file_type().is_dir() {
+                if filter.allows_dir(&relative) {
+                    listing.dirs.insert(relative.clone());
+                    pending.push(relative);
+                }
+            } else if entry.file_type().is_file() && filter.allows_file(&relative) {
+                let metadata = entry.metadata();
+                listing.files.insert(
+                    relative,
+                    FileEntry {
+                        size: metadata.size.unwrap_or(0),
+                        mtime: metadata.mtime.map(u64::from),
+                    },
+                );
+            }
+        }
+    }
+    Ok(listing)
+}
+
+/// 目标目录不存在时视为空目录
+async fn list_destination(
+    sftp: &SftpSession,
+    direction: TransferDirection,
+    root: &str,
+    filter: &PathFilter,
+) -> Result<TreeListing, String> {
+    match direction {
+        TransferDirection::Upload => match sftp.try_exists(root).await {
+            Ok(true) => list_remote_tree(sftp, root, filter).await,
+            _ => Ok(TreeListing::default()),
+        },
+        TransferDirection::Download => match tokio::fs::metadata(root).await {
+            Ok(metadata) if metadata.is_dir() => list_local_tree(root, filter).await,
+            Ok(_) => Err(format!("Destination is not a directory: {}", root)),
+            Err(_) => Ok(TreeListing::default()),
+        },
+    }
+}
+
+/// 按方向解析文件在本地/远程两端的完整路径，返回 (源, 目标)
+fn resolve_paths(direction: TransferDirection, source_root: &str, destination_root: &str, relative: &str) -> (String, String) {
+    match direction {
+        TransferDirection::Upload => (
+            join_local(source_root, relative).to_string_lossy().to_string(),
+            join_remote(destination_root, relative),
+        ),
+        TransferDirection::Download => (
+            join_remote(source_root, relative),
+            join_local(destination_root, relative).to_string_lossy().to_string(),
+        ),
+    }
+}
+
+/// 列出两端目录、创建目录、按需删除多余文件，并为需要传输的文件登记传输任务。
+/// 返回的任务需由调用方交给 run_transfer 执行
+pub async fn prepare_directory_transfer<H: Handler>(
+    handle: &RwLock<Handle<H>>,
+    queue: &TransferQueue,
+    session_id: &str,
+    direction: TransferDirection,
+    source_root: &str,
+    destination_root: &str,
+    options: &SyncOptions,
+) -> Result<(DirectorySyncSummary, Vec<TransferJob>), String> {
+    let filter = PathFilter::new(options)?;
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+
+    let source = match direction {
+        TransferDirection::Upload => {
+            if !Path::new(source_root).is_dir() {
+                return Err(format!("Source is not a directory: {}", source_root));
+            }
+            list_local_tree(source_root, &filter).await?
+        }
+        TransferDirection::Download => {
+            let metadata = sftp
+                .metadata(source_root)
+                .await
+                .map_err(|e| format!("Failed to read remote directory {}: {}", source_root, e))?;
+            if !metadata.is_dir() {
+                return Err(format!("Source is not a directory: {}", source_root));
+            }
+            list_remote_tree(&sftp, source_root, &filter).await?
+        }
+    };
+    let destination = if options.mirror {
+        list_destination(&sftp, direction, destination_root, &filter).await?
+    } else {
+        TreeListing::default()
+    };
+
+    let mut plan = plan_sync(&source, &destination, options);
+
+    // 大小相同的文件再比较两端的 SHA-256
+    for relative in std::mem::take(&mut plan.hash_check) {
+        let (source_path, destination_path) = resolve_paths(direction, source_root, destination_root, &relative);
+        let (local_path, remote_path) = match direction {
+            TransferDirection::Upload => (source_path, destination_path),
+            TransferDirection::Download => (destination_path, source_path),
+        };
+        let local = ssh_file_transfer::local_sha256(&local_path).await.ok();
+        let remote = ssh_file_transfer::remote_sha256(handle, &remote_path).await.ok().flatten();
+        match (local, remote) {
+            (Some(local), Some(remote)) if local == remote => plan.skipped.push(relative),
+            _ => plan.transfer.push(relative),
+        }
+    }
+
+    // 先建根目录，再按层级建子目录（BTreeSet 的顺序保证父目录在前）
+    match direction {
+        TransferDirection::Upload => {
+            if !sftp.try_exists(destination_root).await.unwrap_or(false) {
+                sftp.create_dir(destination_root)
+                    .await
+                    .map_err(|e| format!("Failed to create remote directory {}: {}", destination_root, e))?;
+            }
+            for relative in &plan.create_dirs {
+                let path = join_remote(destination_root, relative);
+                if !sftp.try_exists(path.as_str()).await.unwrap_or(false) {
+                    sftp.create_dir(path.as_str())
+                        .await
+                        .map_err(|e| format!("Failed to create remote directory {}: {}", path, e))?;
+                }
+            }
+        }
+        TransferDirection::Download => {
+            for relative in std::iter::once(&String::new()).chain(&plan.create_dirs) {
+                let path = join_local(destination_root, relative);
+                tokio::fs::create_dir_all(&path)
+                    .await
+                    .map_err(|e| format!("Failed to create local directory {}: {}", path.display(), e))?;
+            }
+        }
+    }
+
+    let mut deleted = Vec::new();
+    for relative in &plan.delete_files {
+        let (_, path) = resolve_paths(direction, source_root, destination_root, relative);
+        let result = match direction {
+            TransferDirection::Upload => sftp.remove_file(path.as_str()).await.map_err(|e| e.to_string()),
+            TransferDirection::Download => tokio::fs::remove_file(&path).await.map_err(|e| e.to_string()),
+        };
+        result.map_err(|e| format!("Failed to delete {}: {}", path, e))?;
+        deleted.push(relative.clone());
+    }
+    for relative in &plan.delete_dirs {
+        let (_, path) = resolve_paths(direction, source_root, destination_root, relative);
+        // 被排除的内容不会列出，目录因此可能非空，这种情况保留目录
+        let removed = match direction {
+            TransferDirection::Upload => sftp.remove_dir(path.as_str()).await.is_ok(),
+            TransferDirection::Download => tokio::fs::remove_dir(&path).await.is_ok(),
+        };
+        if removed {
+            deleted.push(relative.clone());
+        }
+    }
+    let _ = sftp.close().await;
+
+    let transfer_options = TransferOptions {
+        resume: false,
+        verify: options.verify,
+        preserve_mtime: true,
+    };
+    let mut transfers = Vec::with_capacity(plan.transfer.len());
+    let mut jobs = Vec::with_capacity(plan.transfer.len());
+    for relative in &plan.transfer {
+        let (source_path, destination_path) = resolve_paths(direction, source_root, destination_root, relative);
+        let (task, job) = queue.enqueue(session_id, direction, &source_path, &destination_path, transfer_options);
+        transfers.push(task);
+        jobs.push(job);
+    }
+
+    let summary = DirectorySyncSummary {
+        transfers,
+        skipped: plan.skipped,
+        created_directories: plan.create_dirs,
+        deleted,
+    };
+    Ok((summary, jobs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(dirs: &[&str], files: &[(&str, u64, u64)]) -> TreeListing {
+        TreeListing {
+            dirs: dirs.iter().map(|d| d.to_string()).collect(),
+            files: files
+                .iter()
+                .map(|(p, size, mtime)| (p.to_string(), FileEntry { size: *size, mtime: Some(*mtime) }))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn filter_applies_include_and_exclude() {
+        let filter = PathFilter::new(&SyncOptions {
+            include: vec!["*.php".to_string(), "*.html".to_string()],
+            exclude: vec!["cache".to_string(), "*.bak.php".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(filter.allows_file("index.php"));
+        assert!(filter.allows_file("admin/login.html"));
+        assert!(!filter.allows_file("style.css"));
+        assert!(!filter.allows_file("old.bak.php"));
+        assert!(!filter.allows_dir("var/cache"));
+        assert!(filter.allows_dir("admin"));
+        assert!(PathFilter::new(&SyncOptions { include: vec!["[".to_string()], ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn mirror_transfers_only_changed_files() {
+        let source = tree(&["a"], &[("a/same", 10, 100), ("a/newer", 10, 200), ("grown", 20, 100), ("new", 1, 1)]);
+        let destination = tree(
+            &["a", "stale"],
+            &[("a/same", 10, 100), ("a/newer", 10, 100), ("grown", 10, 100), ("stale/x", 1, 1)],
+        );
+        let options = SyncOptions { mirror: true, delete: true, ..Default::default() };
+
+        let plan = plan_sync(&source, &destination, &options);
+        assert_eq!(plan.skipped, vec!["a/same"]);
+        assert_eq!(plan.transfer, vec!["a/newer", "grown", "new"]);
+        assert_eq!(plan.delete_files, vec!["stale/x"]);
+        assert_eq!(plan.delete_dirs, vec!["stale"]);
+        assert!(plan.create_dirs.is_empty());
+
+        let hashed = plan_sync(&source, &destination, &SyncOptions { mirror: true, compare: SyncCompare::Hash, ..Default::default() });
+        assert_eq!(hashed.hash_check, vec!["a/newer", "a/same"]);
+        assert_eq!(hashed.transfer, vec!["grown", "new"]);
+        assert!(hashed.delete_files.is_empty());
+    }
+
+    #[test]
+    fn joins_paths_for_each_side() {
+        assert_eq!(join_remote("/var/www/", "html/index.php"), "/var/www/html/index.php");
+        assert_eq!(join_remote("/", "etc"), "/etc");
+        assert_eq!(join_local("/tmp/site", "a/b.txt"), Path::new("/tmp/site").join("a").join("b.txt"));
+    }
+}
@@ -0,0 +1,832 @@
+// SFTP 文件传输队列
+// 分块流式上传/下载，支持暂停、取消、断点续传与 SHA-256 校验，状态记录在 types::FileTransferTask 中
+
+use crate::types::FileTransferTask;
+use russh::client::{Handle, Handler, Msg};
+use russh::{Channel, ChannelMsg};
+use russh_sftp::client::SftpSession;
+use russh_sftp::protocol::{FileAttributes, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{watch, RwLock, Semaphore};
+
+/// 每次读写的块大小
+pub const TRANSFER_CHUNK_SIZE: usize = 256 * 1024;
+/// 同时进行的传输数量上限，其余任务排队等待
+const MAX_CONCURRENT_TRANSFERS: usize = 3;
+/// 进度事件的最小间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 传输进度事件，载荷为 FileTransferTask
+pub const PROGRESS_EVENT: &str = "sftp_transfer_progress";
+
+/// 传输方向
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
+impl TransferDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferDirection::Upload => "upload",
+            TransferDirection::Download => "download",
+        }
+    }
+}
+
+/// 传输选项
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferOptions {
+    /// 目标文件已存在且不大于源文件时，从其末尾继续传输
+    pub resume: bool,
+    /// 完成后比较本地与远程（sha256sum）的 SHA-256
+    pub verify: bool,
+    /// 目标文件保留源文件的修改时间（目录镜像据此判断文件是否变化）
+    pub preserve_mtime: bool,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            resume: false,
+            verify: true,
+            preserve_mtime: false,
+        }
+    }
+}
+
+/// 前端对传输的控制信号
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TransferSignal {
+    Run,
+    Pause,
+    Cancel,
+}
+
+struct TransferEntry {
+    task: FileTransferTask,
+    session_id: String,
+    options: TransferOptions,
+    control: watch::Sender<TransferSignal>,
+}
+
+/// 已入队、等待执行的传输
+pub struct TransferJob {
+    pub id: String,
+    pub session_id: String,
+    pub direction: TransferDirection,
+    pub source_path: String,
+    pub destination_path: String,
+    pub options: TransferOptions,
+    control: watch::Receiver<TransferSignal>,
+    queue: TransferQueue,
+}
+
+/// 传输队列：同时最多运行 MAX_CONCURRENT_TRANSFERS 个，其余保持 pending 等待空位；
+/// 结束的任务保留到 clear_finished，便于失败后 retry 续传
+#[derive(Clone)]
+pub struct TransferQueue {
+    entries: Arc<Mutex<HashMap<String, TransferEntry>>>,
+    slots: Arc<Semaphore>,
+}
+
+impl Default for TransferQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransferQueue {
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            slots: Arc::new(Semaphore::new(MAX_CONCURRENT_TRANSFERS)),
+        }
+    }
+
+    /// 登记一个新传输，状态为 pending
+    pub fn enqueue(
+        &self,
+        session_id: &str,
+        direction: TransferDirection,
+        source_path: &str,
+        destination_path: &str,
+        options: TransferOptions,
+    ) -> (FileTransferTask, TransferJob) {
+        let id = uuid::Uuid::new_v4().to_string();
+        let task = FileTransferTask {
+            id: id.clone(),
+            source_path: source_path.to_string(),
+            destination_path: destination_path.to_string(),
+            transfer_type: direction.as_str().to_string(),
+            status: "pending".to_string(),
+            progress: 0.0,
+            file_size: 0,
+            transferred_size: 0,
+            created: chrono::Utc::now(),
+            completed: None,
+            error_message: None,
+            resumed_from: 0,
+            local_sha256: None,
+            remote_sha256: None,
+            checksum_verified: None,
+        };
+        let (control, control_rx) = watch::channel(TransferSignal::Run);
+
+        self.entries.lock().unwrap().insert(
+            id.clone(),
+            TransferEntry {
+                task: task.clone(),
+                session_id: session_id.to_string(),
+                options,
+                control,
+            },
+        );
+
+        let job = TransferJob {
+            id,
+            session_id: session_id.to_string(),
+            direction,
+            source_path: source_path.to_string(),
+            destination_path: destination_path.to_string(),
+            options,
+            control: control_rx,
+            queue: self.clone(),
+        };
+        (task, job)
+    }
+
+    /// 重新排队一个失败或已取消的传输，从已传输的位置续传
+    pub fn retry(&self, id: &str) -> Result<(FileTransferTask, TransferJob), String> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .get_mut(id)
+            .ok_or_else(|| format!("Transfer not found: {}", id))?;
+        if !matches!(entry.task.status.as_str(), "failed" | "cancelled") {
+            return Err(format!("Transfer is not failed or cancelled: {}", id));
+        }
+        // ZMODEM 传输由远程的 rz/sz 发起，无法从这里重新开始
+        let zmodem = crate::ssh_zmodem::REMOTE_PATH_PREFIX;
+        if entry.task.source_path.starts_with(zmodem) || entry.task.destination_path.starts_with(zmodem) {
+            return Err(format!("ZMODEM transfers cannot be retried, run rz/sz again: {}", id));
+        }
+
+        let direction = if entry.task.transfer_type == TransferDirection::Upload.as_str() {
+            TransferDirection::Upload
+        } else {
+            TransferDirection::Download
+        };
+        // 校验失败说明目标内容不可信，需要整体重传
+        let corrupted = entry.task.checksum_verified == Some(false);
+        entry.options.resume = !corrupted;
+        entry.task.status = "pending".to_string();
+        entry.task.completed = None;
+        entry.task.error_message = None;
+        entry.task.local_sha256 = None;
+        entry.task.remote_sha256 = None;
+        entry.task.checksum_verified = None;
+        let (control, control_rx) = watch::channel(TransferSignal::Run);
+        entry.control = control;
+
+        let job = TransferJob {
+            id: id.to_string(),
+            session_id: entry.session_id.clone(),
+            direction,
+            source_path: entry.task.source_path.clone(),
+            destination_path: entry.task.destination_path.clone(),
+            options: entry.options,
+            control: control_rx,
+            queue: self.clone(),
+        };
+        Ok((entry.task.clone(), job))
+    }
+
+    /// 所有传输，按创建时间排序
+    pub fn list(&self, session_id: Option<&str>) -> Vec<FileTransferTask> {
+        let entries = self.entries.lock().unwrap();
+        let mut tasks: Vec<FileTransferTask> = entries
+            .values()
+            .filter(|e| session_id.is_none_or(|id| e.session_id == id))
+            .map(|e| e.task.clone())
+            .collect();
+        tasks.sort_by_key(|t| t.created);
+        tasks
+    }
+
+    pub fn get(&self, id: &str) -> Option<FileTransferTask> {
+        self.entries.lock().unwrap().get(id).map(|e| e.task.clone())
+    }
+
+    pub fn pause(&self, id: &str) -> Result<(), String> {
+        self.signal(id, TransferSignal::Pause)
+    }
+
+    pub fn resume(&self, id: &str) -> Result<(), String> {
+        self.signal(id, TransferSignal::Run)
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        self.signal(id, TransferSignal::Cancel)
+    }
+
+    /// 移除已结束（完成、失败、取消）的传输记录，返回移除数量
+    pub fn clear_finished(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|_, e| !is_finished(&e.task.status));
+        before - entries.len()
+    }
+
+    /// 入队后未能启动的传输直接丢弃
+    pub fn discard(&self, id: &str) {
+        self.entries.lock().unwrap().remove(id);
+    }
+
+    fn signal(&self, id: &str, signal: TransferSignal) -> Result<(), String> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries
+            .get(id)
+            .ok_or_else(|| format!("Transfer not found: {}", id))?;
+        if is_finished(&entry.task.status) {
+            return Err(format!("Transfer already finished: {}", id));
+        }
+        entry.control.send_replace(signal);
+        Ok(())
+    }
+
+    pub(crate) fn update(&self, id: &str, f: impl FnOnce(&mut FileTransferTask)) -> Option<FileTransferTask> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(id)?;
+        f(&mut entry.task);
+        Some(entry.task.clone())
+    }
+}
+
+fn is_finished(status: &str) -> bool {
+    matches!(status, "completed" | "failed" | "cancelled")
+}
+
+/// 在会话上打开 session 通道；读锁只在打开期间持有，通道本身不依赖锁，
+/// 长时间的命令或 SFTP 操作因此不会挡住重连与远程转发（它们需要写锁）
+pub async fn open_channel<H: Handler>(handle: &RwLock<Handle<H>>) -> Result<Channel<Msg>, String> {
+    handle
+        .read()
+        .await
+        .channel_open_session()
+        .await
+        .map_err(|e| format!("Failed to open channel: {}", e))
+}
+
+/// 在会话上打开 SFTP 子系统
+pub async fn open_sftp<H: Handler>(handle: &RwLock<Handle<H>>) -> Result<SftpSession, String> {
+    let channel = open_channel(handle).await?;
+
+    channel
+        .request_subsystem(true, "sftp")
+        .await
+        .map_err(|e| format!("Failed to request SFTP subsystem: {}", e))?;
+
+    SftpSession::new(channel.into_stream())
+        .await
+        .map_err(|e| format!("Failed to create SFTP session: {}", e))
+}
+
+impl TransferJob {
+    /// 不经过 run_transfer 的传输（如终端内的 ZMODEM）自行上报进度时使用
+    pub(crate) fn reporter(&self, window: Option<tauri::Window>) -> ProgressReporter {
+        ProgressReporter {
+            queue: self.queue.clone(),
+            id: self.id.clone(),
+            window,
+            last_emit: None,
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        *self.control.borrow() == TransferSignal::Cancel
+    }
+}
+
+/// 进度上报：更新队列中的任务并（节流后）发送事件
+pub(crate) struct ProgressReporter {
+    queue: TransferQueue,
+    id: String,
+    window: Option<tauri::Window>,
+    last_emit: Option<Instant>,
+}
+
+impl ProgressReporter {
+    pub(crate) fn report(&mut self, f: impl FnOnce(&mut FileTransferTask), force: bool) {
+        let Some(task) = self.queue.update(&self.id, f) else {
+            return;
+        };
+        let due = self.last_emit.is_none_or(|t| t.elapsed() >= PROGRESS_INTERVAL);
+        if force || due {
+            self.last_emit = Some(Instant::now());
+            if let Some(window) = &self.window {
+                let _ = window.emit(PROGRESS_EVENT, task);
+            }
+        }
+    }
+
+    pub(crate) fn set_status(&mut self, status: &str) {
+        self.report(|t| t.status = status.to_string(), true);
+    }
+}
+
+/// 执行一个传输直到完成、失败或取消
+pub async fn run_transfer<H: Handler>(
+    handle: Arc<RwLock<Handle<H>>>,
+    job: TransferJob,
+    window: Option<tauri::Window>,
+) -> Result<(), String> {
+    let TransferJob {
+        id,
+        direction,
+        source_path,
+        destination_path,
+        options,
+        mut control,
+        queue,
+        ..
+    } = job;
+    let mut reporter = ProgressReporter {
+        queue: queue.clone(),
+        id,
+        window,
+        last_emit: None,
+    };
+
+    // 排队等待空闲名额；期间取消直接结束
+    let slot = tokio::select! {
+        slot = queue.slots.clone().acquire_owned() => slot.ok(),
+        _ = control.wait_for(|s| *s == TransferSignal::Cancel) => None,
+    };
+    // 本次运行是否创建（或截断）了目标文件；只有这种情况下取消才删除它
+    let mut created = false;
+    let result = match slot {
+        Some(_slot) => {
+            reporter.set_status("in_progress");
+            let copied = transfer(
+                &handle,
+                direction,
+                &source_path,
+                &destination_path,
+                options,
+                &mut created,
+                &mut control,
+                &mut reporter,
+            )
+            .await;
+            match copied {
+                Ok(()) if options.verify => {
+                    reporter.set_status("verifying");
+                    verify_checksum(&handle, direction, &source_path, &destination_path, &mut reporter).await
+                }
+                other => other,
+            }
+        }
+        None => Err(TransferError::Cancelled),
+    };
+
+    match &result {
+        Ok(()) => reporter.report(
+            |t| {
+                t.status = "completed".to_string();
+                t.progress = 100.0;
+                t.completed = Some(chrono::Utc::now());
+            },
+            true,
+        ),
+        Err(TransferError::Cancelled) => {
+            // 取消的传输不保留不完整的目标文件；排队中取消时目标可能是从未写过的已有文件，
+            // 开启续传时保留已传输的部分，供之后继续
+            if created && !options.resume {
+                remove_partial(&handle, direction, &destination_path).await;
+            }
+            reporter.report(
+                |t| {
+                    t.status = "cancelled".to_string();
+                    t.completed = Some(chrono::Utc::now());
+                },
+                true,
+            );
+        }
+        Err(TransferError::Failed(e)) => reporter.report(
+            |t| {
+                t.status = "failed".to_string();
+                t.error_message = Some(e.clone());
+                t.completed = Some(chrono::Utc::now());
+            },
+            true,
+        ),
+    }
+
+    result.map_err(|e| e.to_string())
+}
+
+#[derive(Debug)]
+enum TransferError {
+    Cancelled,
+    Failed(String),
+}
+
+impl std::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::Cancelled => write!(f, "Transfer cancelled"),
+            TransferError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<String> for TransferError {
+    fn from(e: String) -> Self {
+        TransferError::Failed(e)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn transfer<H: Handler>(
+    handle: &RwLock<Handle<H>>,
+    direction: TransferDirection,
+    source_path: &str,
+    destination_path: &str,
+    options: TransferOptions,
+    created: &mut bool,
+    control: &mut watch::Receiver<TransferSignal>,
+    reporter: &mut ProgressReporter,
+) -> Result<(), TransferError> {
+    // 只在打开通道时持有读锁，避免长时间传输阻塞会话重连
+    let sftp = open_sftp(handle).await?;
+
+    match direction {
+        TransferDirection::Upload => {
+            let mut local = tokio::fs::File::open(source_path)
+                .await
+                .map_err(|e| format!("Failed to open local file: {}", e))?;
+            let size = local
+                .metadata()
+                .await
+                .map_err(|e| format!("Failed to read local file metadata: {}", e))?
+                .len();
+            let existing = if options.resume {
+                sftp.metadata(destination_path).await.ok().and_then(|m| m.size)
+            } else {
+                None
+            };
+            let offset = resume_offset(existing, size);
+
+            let mut remote = if offset > 0 {
+                let mut remote = sftp
+                    .open_with_flags(destination_path, OpenFlags::WRITE)
+                    .await
+                    .map_err(|e| format!("Failed to open remote file: {}", e))?;
+                remote
+                    .seek(std::io::SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| format!("Failed to seek remote file: {}", e))?;
+                local
+                    .seek(std::io::SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| format!("Failed to seek local file: {}", e))?;
+                remote
+            } else {
+                let remote = sftp
+                    .create(destination_path)
+                    .await
+                    .map_err(|e| format!("Failed to create remote file: {}", e))?;
+                *created = true;
+                remote
+            };
+            let result = copy_chunks(&mut local, &mut remote, size, offset, control, reporter).await;
+            let _ = remote.shutdown().await;
+            result?;
+
+            if options.preserve_mtime {
+                let modified = local
+                    .metadata()
+                    .await
+                    .ok()
+                    .and_then(|m| m.modified().ok())
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok());
+                if let Some(modified) = modified {
+                    let mut attrs = FileAttributes::empty();
+                    attrs.mtime = Some(modified.as_secs() as u32);
+                    attrs.atime = attrs.mtime;
+                    sftp.set_metadata(destination_path, attrs)
+                        .await
+                        .map_err(|e| format!("Failed to set remote modification time: {}", e))?;
+                }
+            }
+            Ok(())
+        }
+        TransferDirection::Download => {
+            let source_attrs = sftp
+                .metadata(source_path)
+                .await
+                .map_err(|e| format!("Failed to read remote file metadata: {}", e))?;
+            let size = source_attrs.size.unwrap_or(0);
+            let existing = if options.resume {
+                tokio::fs::metadata(destination_path).await.ok().map(|m| m.len())
+            } else {
+                None
+            };
+            let offset = resume_offset(existing, size);
+
+            let mut remote = sftp
+                .open(source_path)
+                .await
+                .map_err(|e| format!("Failed to open remote file: {}", e))?;
+            let mut local = if offset > 0 {
+                remote
+                    .seek(std::io::SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| format!("Failed to seek remote file: {}", e))?;
+                let mut local = tokio::fs::OpenOptions::new()
+                    .write(true)
+                    .open(destination_path)
+                    .await
+                    .map_err(|e| format!("Failed to open local file: {}", e))?;
+                local
+                    .seek(std::io::SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| format!("Failed to seek local file: {}", e))?;
+                local
+            } else {
+                let local = tokio::fs::File::create(destination_path)
+                    .await
+                    .map_err(|e| format!("Failed to create local file: {}", e))?;
+                *created = true;
+                local
+            };
+            let result = copy_chunks(&mut remote, &mut local, size, offset, control, reporter).await;
+            let _ = local.shutdown().await;
+            result?;
+
+            if let (true, Some(mtime)) = (options.preserve_mtime, source_attrs.mtime) {
+                let modified = std::time::UNIX_EPOCH + Duration::from_secs(mtime as u64);
+                local
+                    .into_std()
+                    .await
+                    .set_modified(modified)
+                    .map_err(|e| format!("Failed to set local modification time: {}", e))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// 续传起点：目标已有内容且不超过源文件大小时从其末尾继续，否则重新传输
+fn resume_offset(existing: Option<u64>, size: u64) -> u64 {
+    match existing {
+        Some(len) if len <= size => len,
+        _ => 0,
+    }
+}
+
+async fn copy_chunks<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    size: u64,
+    offset: u64,
+    control: &mut watch::Receiver<TransferSignal>,
+    reporter: &mut ProgressReporter,
+) -> Result<(), TransferError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    reporter.report(
+        |t| {
+            t.file_size = size;
+            t.resumed_from = offset;
+            t.transferred_size = offset;
+        },
+        true,
+    );
+
+    let mut buf = vec![0u8; TRANSFER_CHUNK_SIZE];
+    let mut transferred: u64 = offset;
+    loop {
+        wait_while_paused(control, reporter).await?;
+
+        let n = reader
+            .read(&mut buf)
+            .await
+            .map_err(|e| format!("Failed to read: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        writer
+            .write_all(&buf[..n])
+            .await
+            .map_err(|e| format!("Failed to write: {}", e))?;
+
+        transferred += n as u64;
+        reporter.report(
+            |t| {
+                t.transferred_size = transferred;
+                t.progress = if size > 0 {
+                    (transferred as f64 / size as f64 * 100.0).min(100.0)
+                } else {
+                    0.0
+                };
+            },
+            false,
+        );
+    }
+
+    writer
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to finish writing: {}", e))?;
+    Ok(())
+}
+
+/// 计算本地文件的 SHA-256
+pub(crate) async fn local_sha256(path: &str) -> Result<String, String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open local file: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; TRANSFER_CHUNK_SIZE];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .await
+            .map_err(|e| format!("Failed to read local file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 用远程的 sha256sum（或 shasum -a 256）计算远程文件的 SHA-256；工具不可用时返回 None
+pub(crate) async fn remote_sha256<H: Handler>(handle: &RwLock<Handle<H>>, path: &str) -> Result<Option<String>, String> {
+    let quoted = format!("'{}'", path.replace('\'', "'\\''"));
+    let command = format!(
+        "sha256sum -- {0} 2>/dev/null || shasum -a 256 -- {0} 2>/dev/null",
+        quoted
+    );
+
+    let mut channel = open_channel(handle).await?;
+    channel
+        .exec(true, command)
+        .await
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    let mut stdout = Vec::new();
+    while let Some(msg) = channel.wait().await {
+        match msg {
+            ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+            ChannelMsg::Close => break,
+            _ => {}
+        }
+    }
+
+    Ok(parse_sha256_line(&String::from_utf8_lossy(&stdout)))
+}
+
+/// 解析 "<hash>  <path>" 格式的输出
+fn parse_sha256_line(output: &str) -> Option<String> {
+    let hash = output.split_whitespace().next()?;
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then(|| hash.to_ascii_lowercase())
+}
+
+/// 比较两端的 SHA-256 并记录到任务上，不一致时视为传输失败
+async fn verify_checksum<H: Handler>(
+    handle: &RwLock<Handle<H>>,
+    direction: TransferDirection,
+    source_path: &str,
+    destination_path: &str,
+    reporter: &mut ProgressReporter,
+) -> Result<(), TransferError> {
+    let (local_path, remote_path) = match direction {
+        TransferDirection::Upload => (source_path, destination_path),
+        TransferDirection::Download => (destination_path, source_path),
+    };
+    let local = local_sha256(local_path).await?;
+    let remote = remote_sha256(handle, remote_path).await?;
+    let verified = remote.as_ref().map(|r| *r == local);
+
+    reporter.report(
+        |t| {
+            t.local_sha256 = Some(local.clone());
+            t.remote_sha256 = remote.clone();
+            t.checksum_verified = verified;
+        },
+        true,
+    );
+
+    match (verified, remote) {
+        (Some(false), Some(remote)) => Err(TransferError::Failed(format!(
+            "Checksum mismatch: local {} != remote {}",
+            local, remote
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// 处理暂停/取消信号；暂停时阻塞直到恢复或取消
+async fn wait_while_paused(
+    control: &mut watch::Receiver<TransferSignal>,
+    reporter: &mut ProgressReporter,
+) -> Result<(), TransferError> {
+    match *control.borrow_and_update() {
+        TransferSignal::Run => return Ok(()),
+        TransferSignal::Cancel => return Err(TransferError::Cancelled),
+        TransferSignal::Pause => {}
+    }
+
+    reporter.set_status("paused");
+    let signal = control
+        .wait_for(|s| *s != TransferSignal::Pause)
+        .await
+        .map(|s| *s)
+        .unwrap_or(TransferSignal::Cancel);
+    if signal == TransferSignal::Cancel {
+        return Err(TransferError::Cancelled);
+    }
+    reporter.set_status("in_progress");
+    Ok(())
+}
+
+async fn remove_partial<H: Handler>(
+    handle: &RwLock<Handle<H>>,
+    direction: TransferDirection,
+    destination_path: &str,
+) {
+    match direction {
+        TransferDirection::Download => {
+            let _ = tokio::fs::remove_file(destination_path).await;
+        }
+        TransferDirection::Upload => {
+            if let Ok(sftp) = open_sftp(handle).await {
+                let _ = sftp.remove_file(destination_path).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_resume_cancel_follow_task_status() {
+        let queue = TransferQueue::new();
+        let (task, job) = queue.enqueue("s1", TransferDirection::Upload, "/tmp/a", "/root/a", TransferOptions::default());
+        assert_eq!(task.status, "pending");
+        assert_eq!(task.transfer_type, "upload");
+
+        queue.pause(&task.id).unwrap();
+        assert_eq!(*job.control.borrow(), TransferSignal::Pause);
+        queue.resume(&task.id).unwrap();
+        assert_eq!(*job.control.borrow(), TransferSignal::Run);
+
+        queue.update(&task.id, |t| t.status = "completed".to_string());
+        assert!(queue.cancel(&task.id).is_err());
+        assert!(queue.retry(&task.id).is_err());
+        assert_eq!(queue.clear_finished(), 1);
+        assert!(queue.list(None).is_empty());
+    }
+
+    #[test]
+    fn retry_resumes_failed_transfer() {
+        let queue = TransferQueue::new();
+        let (task, _) = queue.enqueue("s1", TransferDirection::Download, "/var/log/x", "/tmp/x", TransferOptions::default());
+        queue.update(&task.id, |t| t.status = "failed".to_string());
+
+        let (task, job) = queue.retry(&task.id).unwrap();
+        assert_eq!(task.status, "pending");
+        assert_eq!(job.direction, TransferDirection::Download);
+        assert!(job.options.resume);
+    }
+
+    #[test]
+    fn resume_offset_restarts_when_destination_is_larger() {
+        assert_eq!(resume_offset(None, 100), 0);
+        assert_eq!(resume_offset(Some(40), 100), 40);
+        assert_eq!(resume_offset(Some(120), 100), 0);
+    }
+
+    #[test]
+    fn parses_sha256sum_output() {
+        let hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(parse_sha256_line(&format!("{}  /tmp/x\n", hash)), Some(hash.to_string()));
+        assert_eq!(parse_sha256_line(""), None);
+        assert_eq!(parse_sha256_line("sha256sum: /tmp/x: No such file"), None);
+    }
+}
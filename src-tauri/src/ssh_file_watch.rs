@@ -0,0 +1,353 @@
+// 远程文件监视
+// 在独立通道上运行 tail -F 或 inotifywait，把新增行或变更事件推送到窗口；
+// 前端停止、窗口关闭或会话断开时结束
+
+use crate::ssh_file_transfer;
+use russh::client::{Handle, Handler, Msg};
+use russh::{Channel, ChannelMsg};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+use tokio::sync::{oneshot, RwLock};
+
+/// 新增行事件
+pub const LINES_EVENT: &str = "ssh_watch_lines";
+/// 文件变更事件（inotifywait）
+pub const CHANGE_EVENT: &str = "ssh_watch_change";
+/// 监视结束事件
+pub const STOPPED_EVENT: &str = "ssh_watch_stopped";
+
+/// 单行长度上限，超出部分作为一行先行发送
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// 监视方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// tail -F：跟随文件追加的内容，文件被轮转后自动重新打开
+    Tail,
+    /// inotifywait -m：报告文件或目录下的修改、创建、删除、移动
+    Changes,
+}
+
+/// 监视参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchSpec {
+    pub path: String,
+    pub mode: WatchMode,
+    /// tail 模式下先输出的已有行数
+    #[serde(default)]
+    pub initial_lines: u32,
+    /// changes 模式下是否递归监视子目录
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+impl WatchSpec {
+    fn command(&self) -> String {
+        let path = format!("'{}'", self.path.replace('\'', "'\\''"));
+        match self.mode {
+            WatchMode::Tail => format!("tail -n {} -F -- {}", self.initial_lines, path),
+            WatchMode::Changes => format!(
+                "command -v inotifywait >/dev/null 2>&1 || {{ echo 'inotifywait is not installed (inotify-tools)' >&2; exit 127; }}; \
+                 exec inotifywait -m -q {}-e modify,attrib,close_write,create,delete,moved_from,moved_to --format '%e|%w%f' -- {}",
+                if self.recursive { "-r " } else { "" },
+                path
+            ),
+        }
+    }
+}
+
+/// 正在运行的监视（返回给前端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchInfo {
+    pub watch_id: String,
+    pub session_id: String,
+    /// 所属标签页等，按 owner 批量停止
+    pub owner: Option<String>,
+    pub spec: WatchSpec,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LinesPayload<'a> {
+    watch_id: &'a str,
+    path: &'a str,
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangePayload<'a> {
+    watch_id: &'a str,
+    events: Vec<String>,
+    path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StoppedPayload<'a> {
+    watch_id: &'a str,
+    reason: &'a str,
+    error: Option<String>,
+}
+
+struct WatchEntry {
+    info: WatchInfo,
+    stop: oneshot::Sender<&'static str>,
+}
+
+/// 正在运行的监视；每个监视各有一个停止信号，可按会话或发起者一次全部停止
+#[derive(Clone, Default)]
+pub struct WatchRegistry {
+    entries: Arc<Mutex<HashMap<String, WatchEntry>>>,
+}
+
+impl WatchRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个监视，返回的接收端在需要停止时收到原因
+    pub fn register(&self, session_id: &str, owner: Option<String>, spec: WatchSpec) -> (WatchInfo, oneshot::Receiver<&'static str>) {
+        let (stop, stop_rx) = oneshot::channel();
+        let info = WatchInfo {
+            watch_id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            owner,
+            spec,
+            started_at: chrono::Utc::now(),
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(info.watch_id.clone(), WatchEntry { info: info.clone(), stop });
+        (info, stop_rx)
+    }
+
+    pub fn list(&self, session_id: Option<&str>) -> Vec<WatchInfo> {
+        let mut watches: Vec<WatchInfo> = self
+            .entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|e| session_id.is_none_or(|id| e.info.session_id == id))
+            .map(|e| e.info.clone())
+            .collect();
+        watches.sort_by_key(|w| w.started_at);
+        watches
+    }
+
+    pub fn stop(&self, watch_id: &str) -> Result<(), String> {
+        let entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .remove(watch_id)
+            .ok_or_else(|| format!("Watch not found: {}", watch_id))?;
+        let _ = entry.stop.send("stopped");
+        Ok(())
+    }
+
+    /// 停止某个标签页的所有监视，返回停止的数量
+    pub fn stop_owner(&self, owner: &str) -> usize {
+        self.stop_where(|info| info.owner.as_deref() == Some(owner), "stopped")
+    }
+
+    /// 会话断开时停止其上的所有监视
+    pub fn stop_session(&self, session_id: &str) -> usize {
+        self.stop_where(|info| info.session_id == session_id, "disconnected")
+    }
+
+    pub fn stop_all(&self) -> usize {
+        self.stop_where(|_| true, "disconnected")
+    }
+
+    fn stop_where(&self, matches: impl Fn(&WatchInfo) -> bool, reason: &'static str) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let ids: Vec<String> = entries
+            .values()
+            .filter(|e| matches(&e.info))
+            .map(|e| e.info.watch_id.clone())
+            .collect();
+        for id in &ids {
+            if let Some(entry) = entries.remove(id) {
+                let _ = entry.stop.send(reason);
+            }
+        }
+        ids.len()
+    }
+
+    fn finish(&self, watch_id: &str) {
+        self.entries.lock().unwrap().remove(watch_id);
+    }
+}
+
+/// 把字节流切分为行，不完整的行留待下一次
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, data: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(data);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            lines.push(Self::decode(&line[..line.len() - 1]));
+        }
+        if self.pending.len() > MAX_LINE_BYTES {
+            let line = std::mem::take(&mut self.pending);
+            lines.push(Self::decode(&line));
+        }
+        lines
+    }
+
+    fn flush(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| Self::decode(&std::mem::take(&mut self.pending)))
+    }
+
+    fn decode(line: &[u8]) -> String {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        String::from_utf8_lossy(line).into_owned()
+    }
+}
+
+/// 解析 inotifywait --format '%e|%w%f' 的一行，例如 "MODIFY|/var/www/html/index.php"
+fn parse_change(line: &str) -> Option<(Vec<String>, String)> {
+    let (events, path) = line.split_once('|')?;
+    let events = events.split(',').filter(|e| !e.is_empty()).map(str::to_string).collect();
+    Some((events, path.to_string()))
+}
+
+async fn stop_channel(channel: &Channel<Msg>) {
+    let _ = channel.signal(russh::Sig::TERM).await;
+    let _ = channel.eof().await;
+    let _ = channel.close().await;
+}
+
+/// 运行一个监视直到被停止、远程命令退出或连接断开
+pub async fn run_watch<H: Handler>(
+    handle: Arc<RwLock<Handle<H>>>,
+    info: WatchInfo,
+    registry: WatchRegistry,
+    window: tauri::Window,
+    mut stop_rx: oneshot::Receiver<&'static str>,
+) {
+    let watch_id = info.watch_id.clone();
+    let spec = info.spec;
+
+    let opened = async {
+        let channel = ssh_file_transfer::open_channel(&handle).await?;
+        channel
+            .exec(true, spec.command())
+            .await
+            .map_err(|e| format!("Failed to start watch: {}", e))?;
+        Ok::<_, String>(channel)
+    }
+    .await;
+
+    let (reason, error) = match opened {
+        Ok(mut channel) => {
+            let mut lines = LineBuffer::default();
+            let mut stderr = Vec::new();
+            let mut exit_status = None;
+
+            loop {
+                let data = tokio::select! {
+                    reason = &mut stop_rx => {
+                        stop_channel(&channel).await;
+                        break (reason.unwrap_or("stopped"), None);
+                    }
+                    msg = channel.wait() => match msg {
+                        Some(ChannelMsg::Data { data }) => data,
+                        Some(ChannelMsg::ExtendedData { data, .. }) => {
+                            stderr.extend_from_slice(&data);
+                            continue;
+                        }
+                        Some(ChannelMsg::ExitStatus { exit_status: status }) => {
+                            exit_status = Some(status);
+                            continue;
+                        }
+                        Some(ChannelMsg::Close) | None => {
+                            let error = String::from_utf8_lossy(&stderr).trim().to_string();
+                            let reason = if exit_status.is_some() { "exited" } else { "disconnected" };
+                            break (reason, (!error.is_empty()).then_some(error));
+                        }
+                        Some(_) => continue,
+                    },
+                };
+
+                let mut new_lines = lines.push(&data);
+                if exit_status.is_some() {
+                    new_lines.extend(lines.flush());
+                }
+                if new_lines.is_empty() {
+                    continue;
+                }
+                // 窗口已关闭时发送失败，随之停止
+                let emitted = match spec.mode {
+                    WatchMode::Tail => window.emit(
+                        LINES_EVENT,
+                        LinesPayload { watch_id: &watch_id, path: &spec.path, lines: new_lines },
+                    ),
+                    WatchMode::Changes => new_lines
+                        .iter()
+                        .filter_map(|line| parse_change(line))
+                        .try_for_each(|(events, path)| {
+                            window.emit(CHANGE_EVENT, ChangePayload { watch_id: &watch_id, events, path })
+                        }),
+                };
+                if emitted.is_err() {
+                    stop_channel(&channel).await;
+                    break ("window closed", None);
+                }
+            }
+        }
+        Err(e) => ("failed", Some(e)),
+    };
+
+    registry.finish(&watch_id);
+    let _ = window.emit(STOPPED_EVENT, StoppedPayload { watch_id: &watch_id, reason, error });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lines_across_chunks() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.push(b"Failed password for ro").is_empty());
+        assert_eq!(buffer.push(b"ot\r\nAccepted\nsess"), vec!["Failed password for root", "Accepted"]);
+        assert_eq!(buffer.flush(), Some("sess".to_string()));
+        assert_eq!(buffer.flush(), None);
+    }
+
+    #[test]
+    fn parses_inotify_events() {
+        assert_eq!(
+            parse_change("CLOSE_WRITE,CLOSE|/var/www/html/shell.php"),
+            Some((vec!["CLOSE_WRITE".to_string(), "CLOSE".to_string()], "/var/www/html/shell.php".to_string()))
+        );
+        assert_eq!(parse_change("garbage"), None);
+    }
+
+    #[test]
+    fn stop_owner_only_stops_its_watches() {
+        let registry = WatchRegistry::new();
+        let spec = WatchSpec { path: "/var/log/secure".to_string(), mode: WatchMode::Tail, initial_lines: 0, recursive: false };
+        let (_, mut tab1) = registry.register("s1", Some("tab-1".to_string()), spec.clone());
+        let (_, mut tab2) = registry.register("s1", Some("tab-2".to_string()), spec);
+
+        assert_eq!(registry.stop_owner("tab-1"), 1);
+        assert_eq!(tab1.try_recv(), Ok("stopped"));
+        assert!(tab2.try_recv().is_err());
+        assert_eq!(registry.stop_session("s1"), 1);
+        assert_eq!(tab2.try_recv(), Ok("disconnected"));
+        assert!(registry.list(None).is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::oneshot;
+
+/// Event emitted to the frontend when the server asks keyboard-interactive questions
+pub const PROMPT_EVENT: &str = "ssh_keyboard_interactive_prompt";
+
+/// How long to wait for the user to answer before giving up
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(180);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InteractivePromptItem {
+    pub prompt: String,
+    pub echo: bool,
+}
+
+/// Payload of `ssh_keyboard_interactive_prompt`
+#[derive(Debug, Clone, Serialize)]
+pub struct InteractivePrompt {
+    pub request_id: String,
+    pub host: String,
+    pub username: String,
+    pub name: String,
+    pub instructions: String,
+    pub prompts: Vec<InteractivePromptItem>,
+}
+
+/// Pending keyboard-interactive questions waiting for answers from the frontend.
+/// Lives outside the SSH manager lock so answers can arrive while a connect is in progress.
+#[derive(Debug, Default)]
+pub struct InteractiveAuthBroker {
+    pending: Mutex<HashMap<String, oneshot::Sender<Option<Vec<String>>>>>,
+}
+
+impl InteractiveAuthBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(&self, request_id: &str) -> oneshot::Receiver<Option<Vec<String>>> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(request_id.to_string(), tx);
+        rx
+    }
+
+    fn forget(&self, request_id: &str) {
+        self.pending.lock().unwrap().remove(request_id);
+    }
+
+    /// Deliver answers for a prompt; `None` aborts the authentication attempt
+    pub fn respond(&self, request_id: &str, answers: Option<Vec<String>>) -> Result<(), String> {
+        let tx = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(request_id)
+            .ok_or_else(|| format!("No pending authentication prompt: {}", request_id))?;
+        tx.send(answers)
+            .map_err(|_| "Authentication prompt is no longer waiting".to_string())
+    }
+}
+
+/// Forwards keyboard-interactive prompts to a window and waits for the answers
+#[derive(Clone)]
+pub struct InteractivePrompter {
+    window: tauri::Window,
+    broker: Arc<InteractiveAuthBroker>,
+}
+
+impl std::fmt::Debug for InteractivePrompter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InteractivePrompter").finish_non_exhaustive()
+    }
+}
+
+impl InteractivePrompter {
+    pub fn new(window: tauri::Window, broker: Arc<InteractiveAuthBroker>) -> Self {
+        Self { window, broker }
+    }
+
+    pub async fn ask(
+        &self,
+        host: &str,
+        username: &str,
+        name: String,
+        instructions: String,
+        prompts: Vec<InteractivePromptItem>,
+    ) -> Result<Vec<String>, String> {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let expected = prompts.len();
+        let rx = self.broker.register(&request_id);
+
+        let payload = InteractivePrompt {
+            request_id: request_id.clone(),
+            host: host.to_string(),
+            username: username.to_string(),
+            name,
+            instructions,
+            prompts,
+        };
+        if let Err(e) = self.window.emit(PROMPT_EVENT, payload) {
+            self.broker.forget(&request_id);
+            return Err(format!("Failed to emit authentication prompt: {}", e));
+        }
+
+        let answers = match tokio::time::timeout(PROMPT_TIMEOUT, rx).await {
+            Ok(Ok(Some(answers))) => answers,
+            Ok(Ok(None)) | Ok(Err(_)) => return Err("Authentication cancelled by user".to_string()),
+            Err(_) => {
+                self.broker.forget(&request_id);
+                return Err("Timed out waiting for authentication answers".to_string());
+            }
+        };
+
+        if answers.len() != expected {
+            return Err(format!("Expected {} answers, got {}", expected, answers.len()));
+        }
+        Ok(answers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn respond_delivers_answers_once() {
+        let broker = InteractiveAuthBroker::new();
+        let rx = broker.register("req");
+
+        broker.respond("req", Some(vec!["123456".to_string()])).unwrap();
+        assert_eq!(rx.await.unwrap(), Some(vec!["123456".to_string()]));
+        assert!(broker.respond("req", None).is_err());
+    }
+}
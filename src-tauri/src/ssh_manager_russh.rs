@@ -3,24 +3,53 @@
 // to avoid nested runtime issues when called from Tauri's async context
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use serde::{Deserialize, Serialize};
-use russh::client::{Config, Handle, Handler};
-use russh::keys::{PublicKey, PrivateKeyWithHashAlg};
+use russh::client::{Config, Handle, Handler, KeyboardInteractiveAuthResponse};
+use russh::keys::agent::client::{AgentClient, AgentStream};
+use russh::keys::{Certificate, PublicKey, PrivateKeyWithHashAlg};
 use russh::{ChannelMsg, Disconnect};
 use russh_sftp::client::SftpSession;
+use russh_sftp::protocol::{FileAttributes, OpenFlags};
+use futures::FutureExt;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use std::net::ToSocketAddrs;
+use crate::ssh_interactive_auth::{InteractivePromptItem, InteractivePrompter};
+use crate::known_hosts::{self, HostKeyInfo, HostKeyVerification, KnownHostsStore};
+use crate::local_terminal::{LocalShellOptions, LocalTerminals};
+use crate::ssh_archive::{self, ArchiveFormat, ArchiveSummary};
+use crate::ssh_file_range::{self, FileRange, LineIndex, RangeRequest};
+use crate::ssh_file_sync::{self, DirectorySyncSummary, SyncOptions};
+use crate::ssh_file_watch::{self, WatchInfo, WatchRegistry, WatchSpec};
+use crate::ssh_file_transfer::{self, TransferDirection, TransferJob, TransferOptions, TransferQueue};
+use crate::ssh_port_forward::{self, PortForward, PortForwardInfo, PortForwardSpec, RemoteForwardRegistry};
+use crate::ssh_terminal_encoding::{TerminalDecoder, TerminalOutputOptions};
+use crate::ssh_terminal_group::{BroadcastFailure, BroadcastResult, MemberLoss, TerminalGroupInfo, TerminalGroups};
+use crate::ssh_terminal_recording::{RecordingInfo, RecordingOptions, RecordingRegistry};
+use crate::ssh_terminal_scrollback::{ScrollbackInfo, ScrollbackRange, ScrollbackRegistry, ScrollbackSearchOptions, ScrollbackSearchResult};
+use crate::ssh_zmodem::{self, ZmodemAnswer, ZmodemHub};
+use crate::types::LovelyResError;
 
 // ================== Types ==================
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerminalOutput {
     pub command: String,
-    pub output: String,
+    pub output: String, // stdout with stderr appended, kept for existing callers
     pub exit_code: Option<i32>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub truncated: bool, // output hit ExecOptions::max_output_bytes and the command was stopped
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default)]
+    pub exit_signal: Option<String>, // e.g. "KILL" when the process was killed by a signal
+    #[serde(default)]
+    pub duration_ms: u64,
 }
 
 impl TerminalOutput {
@@ -30,10 +59,65 @@ impl TerminalOutput {
             output: output.to_string(),
             exit_code,
             timestamp: chrono::Utc::now(),
+            truncated: false,
+            stdout: output.to_string(),
+            stderr: String::new(),
+            exit_signal: None,
+            duration_ms: 0,
+        }
+    }
+    
+    /// Build from separate streams; `output` is stdout followed by stderr
+    pub fn from_streams(command: &str, stdout: &str, stderr: &str, exit_code: Option<i32>) -> Self {
+        let mut output = stdout.to_string();
+        if !stderr.is_empty() {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str(stderr);
+        }
+        
+        let mut result = Self::new(command, &output, exit_code);
+        result.stdout = stdout.to_string();
+        result.stderr = stderr.to_string();
+        result
+    }
+}
+
+/// Limits for a single remote command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecOptions {
+    pub timeout_ms: Option<u64>,
+    pub cancel_id: Option<String>, // caller-chosen id, pass to `cancel_command` to stop it
+    pub max_output_bytes: Option<usize>,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        Self {
+            timeout_ms: Some(DEFAULT_COMMAND_TIMEOUT_MS),
+            cancel_id: None,
+            max_output_bytes: Some(DEFAULT_MAX_OUTPUT_BYTES),
+        }
+    }
+}
+
+impl ExecOptions {
+    /// No timeout and no output cap; what the plain `execute_command` family and callers
+    /// that pass no options run with
+    pub fn unlimited() -> Self {
+        Self {
+            timeout_ms: None,
+            cancel_id: None,
+            max_output_bytes: None,
         }
     }
 }
 
+const DEFAULT_COMMAND_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 32 * 1024 * 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SftpFileInfo {
     pub name: String,
@@ -43,6 +127,8 @@ pub struct SftpFileInfo {
     pub size: u64,
     pub modified: Option<String>,
     pub permissions: Option<String>,
+    #[serde(default)]
+    pub symlink_target: Option<String>, // only set for symlinks
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +143,81 @@ pub struct SftpFileDetails {
     pub created: Option<String>,
     pub modified: Option<String>,
     pub accessed: Option<String>,
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    #[serde(default)]
+    pub mtime: Option<u32>, // raw unix timestamps, for set_sftp_attributes round trips
+    #[serde(default)]
+    pub atime: Option<u32>,
+    #[serde(default)]
+    pub followed_links: bool, // true: stat (describes the link target), false: lstat
+    #[serde(default)]
+    pub symlink_target: Option<String>, // raw readlink value when the path is a symlink
+    #[serde(default)]
+    pub target_type: Option<String>, // type of what the symlink resolves to, None when dangling
+}
+
+/// Identifies the content of a remote file as it was read, so a later write can tell
+/// whether someone else changed it in between
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileVersion {
+    pub size: u64,
+    pub mtime: Option<u32>,
+    pub sha256: String,
+    pub token: String, // "<size>-<mtime>-<sha256>", what writes pass back
+}
+
+impl FileVersion {
+    fn new(size: u64, mtime: Option<u32>, content: &[u8]) -> Self {
+        use sha2::{Digest, Sha256};
+        let sha256 = hex::encode(Sha256::digest(content));
+        let token = format!("{}-{}-{}", size, mtime.unwrap_or(0), sha256);
+        Self { size, mtime, sha256, token }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionedFile {
+    pub content: Vec<u8>,
+    pub version: FileVersion,
+}
+
+/// A versioned file decoded as UTF-8, for the editor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionedTextFile {
+    pub content: String,
+    pub version: FileVersion,
+}
+
+impl VersionedFile {
+    pub fn into_text(self) -> Result<VersionedTextFile, String> {
+        let content = String::from_utf8(self.content)
+            .map_err(|e| format!("Failed to decode file as UTF-8: {}", e))?;
+        Ok(VersionedTextFile { content, version: self.version })
+    }
+}
+
+/// Error prefix returned when the remote file no longer matches `expected_version`
+pub const FILE_CONFLICT_ERROR: &str = "File changed on the server";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WriteOptions {
+    pub expected_version: Option<String>, // FileVersion::token from the read; None overwrites blindly
+    pub backup: bool, // copy the current file to `<path>.bak` before overwriting it
+}
+
+/// Attribute changes applied with SFTP SETSTAT; unset fields are left alone
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SftpAttributeChange {
+    pub mode: Option<u32>, // permission bits, e.g. 0o644
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub atime: Option<u32>,
+    pub mtime: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,33 +231,162 @@ pub struct SSHConnectionStatus {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionInfo {
+    pub session_id: String,
+    pub target: String, // user@host:port, several sessions may share it
     pub host: String,
     pub port: u16,
     pub username: String,
     pub auth_method: String,
+    pub jump_hosts: Vec<String>,
+    pub connected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Credentials used to authenticate one SSH hop
+#[derive(Debug, Clone, Default)]
+pub struct SSHCredentials {
+    pub password: Option<String>,
+    pub private_key: Option<String>, // PEM content or key file path
+    pub key_passphrase: Option<String>,
+    pub certificate: Option<String>, // OpenSSH certificate (content or path) signed for private_key
+    pub use_agent: bool, // try identities from the running ssh-agent first
+    pub prompter: Option<InteractivePrompter>, // answers keyboard-interactive (OTP/2FA) prompts
+}
+
+impl SSHCredentials {
+    fn auth_method(&self) -> String {
+        if self.use_agent {
+            "agent".to_string()
+        } else if self.certificate.is_some() && self.private_key.is_some() {
+            "certificate".to_string()
+        } else if self.private_key.is_some() {
+            "key".to_string()
+        } else {
+            "password".to_string()
+        }
+    }
+}
+
+/// Resolved credentials for one hop of a ProxyJump chain
+#[derive(Debug, Clone)]
+pub struct JumpHostConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub credentials: SSHCredentials,
 }
 
 // ================== SSH Client Handler ==================
 
 struct ClientHandler {
-    host_key_accepted: bool,
+    host: String,
+    port: u16,
+    // Reason the host key was rejected, read back after a failed handshake
+    host_key_error: Arc<Mutex<Option<String>>>,
+    // Local targets for server-initiated connections on remote (-R) forwards
+    remote_forwards: RemoteForwardRegistry,
 }
 
 impl ClientHandler {
-    fn new() -> Self {
+    fn new(
+        host: &str,
+        port: u16,
+        host_key_error: Arc<Mutex<Option<String>>>,
+        remote_forwards: RemoteForwardRegistry,
+    ) -> Self {
         Self {
-            host_key_accepted: false,
+            host: host.to_string(),
+            port,
+            host_key_error,
+            remote_forwards,
         }
     }
+
+    fn reject(&self, reason: String) -> Result<bool, russh::Error> {
+        if let Ok(mut guard) = self.host_key_error.lock() {
+            *guard = Some(reason);
+        }
+        Ok(false)
+    }
 }
 
 impl Handler for ClientHandler {
     type Error = russh::Error;
 
-    async fn check_server_key(&mut self, _server_public_key: &PublicKey) -> Result<bool, Self::Error> {
-        // Accept all host keys (similar to StrictHostKeyChecking=no)
-        self.host_key_accepted = true;
-        Ok(true)
+    async fn check_server_key(&mut self, server_public_key: &PublicKey) -> Result<bool, Self::Error> {
+        let store = match KnownHostsStore::new() {
+            Ok(store) => store,
+            Err(e) => return self.reject(e.to_string()),
+        };
+
+        match store.verify(&self.host, self.port, server_public_key) {
+            Ok(HostKeyVerification::Trusted) => Ok(true),
+            Ok(HostKeyVerification::Unknown) => {
+                // Trust on first use: remember the key for subsequent connections
+                if let Err(e) = store.add(&self.host, self.port, server_public_key) {
+                    return self.reject(e.to_string());
+                }
+                Ok(true)
+            }
+            Ok(HostKeyVerification::Mismatch { known_fingerprints }) => {
+                let error = LovelyResError::HostKeyMismatch(format!(
+                    "{}:{} presented {}, expected {}",
+                    self.host,
+                    self.port,
+                    known_hosts::fingerprint(server_public_key),
+                    known_fingerprints.join(", ")
+                ));
+                self.reject(error.to_string())
+            }
+            Ok(HostKeyVerification::Revoked) => {
+                let error = LovelyResError::HostKeyRevoked(format!(
+                    "{}:{} presented revoked key {}",
+                    self.host,
+                    self.port,
+                    known_hosts::fingerprint(server_public_key)
+                ));
+                self.reject(error.to_string())
+            }
+            Err(e) => self.reject(e.to_string()),
+        }
+    }
+    
+    async fn server_channel_open_forwarded_tcpip(
+        &mut self,
+        channel: russh::Channel<Msg>,
+        _connected_address: &str,
+        connected_port: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut russh::client::Session,
+    ) -> Result<(), Self::Error> {
+        let target = self
+            .remote_forwards
+            .lock()
+            .ok()
+            .and_then(|forwards| forwards.get(&connected_port).cloned());
+        match target {
+            Some(target) => ssh_port_forward::spawn_remote_connection(channel, target),
+            None => {
+                let _ = channel.close().await;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Handler used only to fetch the server host key, never completes the handshake
+struct HostKeyScanner {
+    server_key: Arc<Mutex<Option<PublicKey>>>,
+}
+
+impl Handler for HostKeyScanner {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, server_public_key: &PublicKey) -> Result<bool, Self::Error> {
+        if let Ok(mut guard) = self.server_key.lock() {
+            *guard = Some(server_public_key.clone());
+        }
+        Ok(false)
     }
 }
 
@@ -107,15 +397,57 @@ enum WorkerCommand {
         host: String,
         port: u16,
         username: String,
-        password: Option<String>,
-        private_key: Option<String>,
+        credentials: SSHCredentials,
+        jump_hosts: Vec<JumpHostConfig>,
         response_tx: mpsc::Sender<Result<String, String>>,
     },
+    ScanHostKey {
+        host: String,
+        port: u16,
+        response_tx: mpsc::Sender<Result<HostKeyInfo, String>>,
+    },
     ExecuteCommand {
         session_id: String,
         command: String,
+        options: ExecOptions,
         response_tx: mpsc::Sender<Result<TerminalOutput, String>>,
     },
+    StartTransfer {
+        job: TransferJob,
+        window: Option<tauri::Window>,
+        // Receives the final result when the caller waits for the transfer
+        done_tx: Option<mpsc::Sender<Result<(), String>>>,
+        response_tx: mpsc::Sender<Result<(), String>>,
+    },
+    StartDirectoryTransfer {
+        session_id: String,
+        direction: TransferDirection,
+        source_path: String,
+        destination_path: String,
+        options: SyncOptions,
+        queue: TransferQueue,
+        window: Option<tauri::Window>,
+        response_tx: mpsc::Sender<Result<DirectorySyncSummary, String>>,
+    },
+    StartWatch {
+        info: WatchInfo,
+        registry: WatchRegistry,
+        stop_rx: tokio::sync::oneshot::Receiver<&'static str>,
+        window: tauri::Window,
+        response_tx: mpsc::Sender<Result<(), String>>,
+    },
+    StreamCommand {
+        session_id: String,
+        command: String,
+        options: ExecOptions,
+        job_id: String,
+        window: tauri::Window,
+        response_tx: mpsc::Sender<Result<(), String>>,
+    },
+    CancelCommand {
+        cancel_id: String,
+        response_tx: mpsc::Sender<Result<(), String>>,
+    },
     ListSftpFiles {
         session_id: String,
         path: String,
@@ -126,11 +458,23 @@ enum WorkerCommand {
         path: String,
         response_tx: mpsc::Sender<Result<Vec<u8>, String>>,
     },
+    ReadSftpRange {
+        session_id: String,
+        path: String,
+        request: RangeRequest,
+        response_tx: mpsc::Sender<Result<FileRange, String>>,
+    },
+    ReadSftpFileVersioned {
+        session_id: String,
+        path: String,
+        response_tx: mpsc::Sender<Result<VersionedFile, String>>,
+    },
     WriteSftpFile {
         session_id: String,
         path: String,
         content: Vec<u8>,
-        response_tx: mpsc::Sender<Result<(), String>>,
+        options: WriteOptions,
+        response_tx: mpsc::Sender<Result<FileVersion, String>>,
     },
     DeleteSftpFile {
         session_id: String,
@@ -148,6 +492,52 @@ enum WorkerCommand {
         new_path: String,
         response_tx: mpsc::Sender<Result<(), String>>,
     },
+    CreateLocalArchive {
+        session_id: String,
+        remote_paths: Vec<String>,
+        archive_path: String,
+        format: ArchiveFormat,
+        response_tx: mpsc::Sender<Result<ArchiveSummary, String>>,
+    },
+    ExtractLocalArchive {
+        session_id: String,
+        archive_path: String,
+        remote_dir: String,
+        format: ArchiveFormat,
+        response_tx: mpsc::Sender<Result<ArchiveSummary, String>>,
+    },
+    StatSftpFile {
+        session_id: String,
+        path: String,
+        follow_links: bool,
+        response_tx: mpsc::Sender<Result<SftpFileDetails, String>>,
+    },
+    CreateSftpSymlink {
+        session_id: String,
+        target: String,
+        link_path: String,
+        response_tx: mpsc::Sender<Result<(), String>>,
+    },
+    SetSftpAttributes {
+        session_id: String,
+        path: String,
+        change: SftpAttributeChange,
+        recursive: bool,
+        response_tx: mpsc::Sender<Result<usize, String>>,
+    },
+    CreatePortForward {
+        session_id: String,
+        spec: PortForwardSpec,
+        response_tx: mpsc::Sender<Result<PortForwardInfo, String>>,
+    },
+    ListPortForwards {
+        session_id: Option<String>,
+        response_tx: mpsc::Sender<Vec<PortForwardInfo>>,
+    },
+    ClosePortForward {
+        forward_id: String,
+        response_tx: mpsc::Sender<Result<(), String>>,
+    },
     Disconnect {
         session_id: String,
         response_tx: mpsc::Sender<Result<(), String>>,
@@ -164,7 +554,12 @@ enum WorkerCommand {
         response_tx: mpsc::Sender<bool>,
     },
     ListSessions {
-        response_tx: mpsc::Sender<Vec<String>>,
+        response_tx: mpsc::Sender<Vec<ConnectionInfo>>,
+    },
+    // Whether any terminal or port forward still runs on the session
+    IsSessionInUse {
+        session_id: String,
+        response_tx: mpsc::Sender<bool>,
     },
     // Terminal session commands
     CreateTerminalSession {
@@ -172,6 +567,7 @@ enum WorkerCommand {
         terminal_id: String,
         cols: u32,
         rows: u32,
+        output: TerminalOutputOptions,
         window: tauri::Window,
         response_tx: mpsc::Sender<Result<(), String>>,
     },
@@ -187,20 +583,82 @@ enum WorkerCommand {
         data: Vec<u8>,
         response_tx: mpsc::Sender<Result<(), String>>,
     },
+    SetTerminalOutput {
+        terminal_id: String,
+        output: TerminalOutputOptions,
+        response_tx: mpsc::Sender<Result<(), String>>,
+    },
+    StartTerminalRecording {
+        terminal_id: String,
+        options: RecordingOptions,
+        response_tx: mpsc::Sender<Result<RecordingInfo, String>>,
+    },
     ResizeTerminal {
         terminal_id: String,
         cols: u32,
         rows: u32,
         response_tx: mpsc::Sender<Result<(), String>>,
     },
+    // Sent by background tasks back to the worker
+    Connected {
+        pending: PendingConnection,
+        result: Result<(Handle<ClientHandler>, Vec<Handle<ClientHandler>>), String>,
+        response_tx: mpsc::Sender<Result<String, String>>,
+    },
+    Reconnected {
+        session_id: String,
+        result: Result<(Handle<ClientHandler>, Vec<Handle<ClientHandler>>), String>,
+    },
+    PortForwardStarted {
+        forward_id: String,
+        result: Result<PortForward, String>,
+        response_tx: mpsc::Sender<Result<PortForwardInfo, String>>,
+    },
+    // The reconnected handle is in place and remote forwards are restored
+    Resumed {
+        session_id: String,
+        handle: SharedHandle,
+    },
     Shutdown,
 }
 
 // ================== Session Data ==================
 
+type SharedHandle = Arc<tokio::sync::RwLock<Handle<ClientHandler>>>;
+
+/// Upper bound of requests running at once on one session; OpenSSH allows 10 channels
+/// per connection by default (MaxSessions) and terminals need some of them
+const MAX_CONCURRENT_REQUESTS_PER_SESSION: usize = 6;
+
 struct SessionData {
-    handle: Handle<ClientHandler>,
+    // Shared with background tasks (port forwards, requests) that open channels on this session
+    handle: SharedHandle,
+    // Jump host sessions carrying the tunnel, outermost first; must outlive `handle`
+    jump_handles: Vec<Handle<ClientHandler>>,
+    remote_forwards: RemoteForwardRegistry,
+    info: ConnectionInfo,
+    // Kept to re-establish the session when the transport drops
+    credentials: SSHCredentials,
+    jump_hosts: Vec<JumpHostConfig>,
+    reconnecting: bool,
+    limiter: Arc<tokio::sync::Semaphore>,
+}
+
+/// A connection established by a background task, waiting to be registered by the worker
+struct PendingConnection {
     info: ConnectionInfo,
+    credentials: SSHCredentials,
+    jump_hosts: Vec<JumpHostConfig>,
+    remote_forwards: RemoteForwardRegistry,
+}
+
+impl SessionData {
+    async fn disconnect(self, reason: &str) {
+        let _ = self.handle.read().await.disconnect(Disconnect::ByApplication, reason, "en").await;
+        for jump in self.jump_handles.into_iter().rev() {
+            let _ = jump.disconnect(Disconnect::ByApplication, reason, "en").await;
+        }
+    }
 }
 
 // ================== Terminal Session Data ==================
@@ -209,162 +667,561 @@ use russh::client::Msg;
 use tauri::Emitter;
 
 struct TerminalSession {
-    channel: russh::Channel<Msg>,
+    // Write half of the PTY channel; the read half belongs to the terminal's reader task
+    channel: Arc<russh::ChannelWriteHalf<Msg>>,
+    // Input queue of the terminal's writer task, see `spawn_terminal_writer`
+    input: tokio::sync::mpsc::UnboundedSender<TerminalInput>,
     session_id: String,
     window: tauri::Window,
+    cols: u32,
+    rows: u32,
+    // Bumped whenever the terminal is re-attached to a new channel after a reconnect
+    generation: u64,
+    // The channel dropped with the transport; waiting for the session to come back
+    detached: bool,
+    // Remote encoding of the output and input, and how output is sent to the frontend
+    decoder: TerminalDecoder,
+    // Finds rz/sz start frames that straddle two reads
+    zmodem_detector: ssh_zmodem::StartDetector,
+}
+
+// Nothing awaits the server while holding the table; channel I/O runs on the
+// terminal's own reader and writer tasks
+type TerminalSessions = Arc<tokio::sync::Mutex<HashMap<String, TerminalSession>>>;
+
+// Encoded input and where to report once it is written
+type TerminalInput = (Vec<u8>, mpsc::Sender<Result<(), String>>);
+
+/// What terminal readers and input feed besides the frontend; shared with the manager
+#[derive(Clone)]
+struct TerminalHooks {
+    zmodem: ZmodemHub,
+    recordings: RecordingRegistry,
+    groups: TerminalGroups,
+    scrollback: ScrollbackRegistry,
 }
 
+/// How often idle sessions are checked for a dropped transport
+const LIVENESS_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+const RECONNECT_BASE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
 // ================== Async Helper Functions ==================
 
-async fn connect_async(
-    host: &str,
-    port: u16,
-    username: &str,
-    password: Option<&str>,
-    private_key: Option<&str>,
-) -> Result<Handle<ClientHandler>, String> {
-    // Configure SSH client
-    let config = Config {
+fn resolve_addr(host: &str, port: u16) -> Result<std::net::SocketAddr, String> {
+    format!("{}:{}", host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve host: {}", e))?
+        .next()
+        .ok_or_else(|| format!("No addresses found for host: {}", host))
+}
+
+fn client_config() -> Arc<Config> {
+    Arc::new(Config {
         inactivity_timeout: Some(std::time::Duration::from_secs(300)),
         keepalive_interval: Some(std::time::Duration::from_secs(30)),
         keepalive_max: 3,
         ..Default::default()
-    };
+    })
+}
+
+/// Establish the SSH transport to `host:port`, either directly or through a
+/// `direct-tcpip` channel opened on an already authenticated jump host
+async fn open_transport(
+    config: Arc<Config>,
+    host: &str,
+    port: u16,
+    via: Option<&Handle<ClientHandler>>,
+    remote_forwards: RemoteForwardRegistry,
+) -> Result<Handle<ClientHandler>, String> {
+    // Connect to server, verifying the host key against known_hosts
+    let host_key_error = Arc::new(Mutex::new(None));
+    let handler = ClientHandler::new(host, port, host_key_error.clone(), remote_forwards);
     
-    // Resolve hostname
-    let addr = format!("{}:{}", host, port)
-        .to_socket_addrs()
-        .map_err(|e| format!("Failed to resolve host: {}", e))?
-        .next()
-        .ok_or_else(|| format!("No addresses found for host: {}", host))?;
+    let result = match via {
+        Some(jump) => {
+            let channel = jump
+                .channel_open_direct_tcpip(host, port as u32, "127.0.0.1", 0)
+                .await
+                .map_err(|e| format!("Failed to open tunnel to {}:{}: {}", host, port, e))?;
+            russh::client::connect_stream(config, channel.into_stream(), handler).await
+        }
+        None => {
+            let addr = resolve_addr(host, port)?;
+            russh::client::connect(config, addr, handler).await
+        }
+    };
     
-    // Connect to server
-    let handler = ClientHandler::new();
-    let mut handle = russh::client::connect(Arc::new(config), addr, handler)
-        .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
-    
-    // Authenticate
-    let auth_result = if let Some(key_str) = private_key {
-        // Try key authentication
-        let key_pair = if key_str.contains("OPENSSH PRIVATE KEY") || key_str.contains("RSA PRIVATE KEY") || key_str.contains("-----BEGIN") {
-            russh_keys::decode_secret_key(key_str, None)
-                .map_err(|e| format!("Failed to decode private key: {}", e))?
-        } else {
-            // Assume it's a file path
-            russh_keys::load_secret_key(key_str, None)
-                .map_err(|e| format!("Failed to load private key: {}", e))?
-        };
-        
-        // Convert russh_keys::PrivateKey to russh::keys::PrivateKey
-        // They should be the same type, but we need to use the one from russh
-        let key_bytes = key_pair.to_openssh(russh_keys::ssh_key::LineEnding::LF)
-            .map_err(|e| format!("Failed to encode key: {}", e))?;
-        let russh_key = russh::keys::decode_secret_key(&key_bytes, None)
-            .map_err(|e| format!("Failed to decode key for russh: {}", e))?;
-        
-        // Wrap key with hash algorithm for authentication
-        let key_with_hash = PrivateKeyWithHashAlg::new(Arc::new(russh_key), None);
-        
-        handle
-            .authenticate_publickey(username, key_with_hash)
-            .await
-            .map_err(|e| format!("Key authentication failed: {}", e))?
-    } else if let Some(pwd) = password {
-        // Password authentication
-        handle
-            .authenticate_password(username, pwd)
-            .await
-            .map_err(|e| format!("Password authentication failed: {}", e))?
+    result.map_err(|e| {
+        host_key_error
+            .lock()
+            .ok()
+            .and_then(|mut g| g.take())
+            .unwrap_or_else(|| format!("Failed to connect: {}", e))
+    })
+}
+
+/// Load a private key given as PEM content or a file path, decrypting it with the passphrase if set
+fn load_private_key(key_str: &str, passphrase: Option<&str>) -> Result<russh::keys::PrivateKey, String> {
+    let key_pair = if key_str.contains("OPENSSH PRIVATE KEY") || key_str.contains("RSA PRIVATE KEY") || key_str.contains("-----BEGIN") {
+        russh_keys::decode_secret_key(key_str, passphrase)
+            .map_err(|e| format!("Failed to decode private key: {}", e))?
     } else {
-        return Err("No authentication method provided".to_string());
+        // Assume it's a file path
+        russh_keys::load_secret_key(key_str, passphrase)
+            .map_err(|e| format!("Failed to load private key: {}", e))?
     };
     
-    // Check authentication result
-    if !auth_result.success() {
-        return Err("Authentication failed".to_string());
-    }
-    
-    Ok(handle)
+    // Convert russh_keys::PrivateKey to russh::keys::PrivateKey
+    // They should be the same type, but we need to use the one from russh
+    let key_bytes = key_pair.to_openssh(russh_keys::ssh_key::LineEnding::LF)
+        .map_err(|e| format!("Failed to encode key: {}", e))?;
+    russh::keys::decode_secret_key(&key_bytes, None)
+        .map_err(|e| format!("Failed to decode key for russh: {}", e))
 }
 
-async fn execute_command_async(
-    handle: &Handle<ClientHandler>,
-    command: &str,
-) -> Result<TerminalOutput, String> {
-    // Open a session channel
-    let channel = handle
-        .channel_open_session()
+#[cfg(unix)]
+async fn connect_agent() -> Result<AgentClient<Box<dyn AgentStream + Send + Unpin>>, String> {
+    AgentClient::connect_env()
         .await
-        .map_err(|e| format!("Failed to open channel: {}", e))?;
-    
-    // Execute command
-    channel
-        .exec(true, command)
+        .map(|agent| agent.dynamic())
+        .map_err(|e| format!("Failed to connect to ssh-agent (SSH_AUTH_SOCK): {}", e))
+}
+
+#[cfg(windows)]
+async fn connect_agent() -> Result<AgentClient<Box<dyn AgentStream + Send + Unpin>>, String> {
+    // Windows OpenSSH agent listens on a named pipe, SSH_AUTH_SOCK may point elsewhere
+    let pipe = std::env::var("SSH_AUTH_SOCK")
+        .unwrap_or_else(|_| r"\\.\pipe\openssh-ssh-agent".to_string());
+    AgentClient::connect_named_pipe(&pipe)
         .await
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
-    
-    // Read output
-    let mut stdout = Vec::new();
-    let mut stderr = Vec::new();
-    let mut exit_code: Option<i32> = None;
+        .map(|agent| agent.dynamic())
+        .map_err(|e| format!("Failed to connect to ssh-agent ({}): {}", pipe, e))
+}
+
+/// ssh-agent signer that owns the key while signing. russh's own `Signer` impl for
+/// `AgentClient` borrows it across the await, which makes connect futures non-`Send`
+/// and rules out running them in spawned tasks (reconnects).
+struct AgentSigner(AgentClient<Box<dyn AgentStream + Send + Unpin>>);
+
+impl russh::Signer for AgentSigner {
+    type Error = russh::AgentAuthError;
     
-    let mut channel = channel;
-    loop {
-        match channel.wait().await {
-            Some(ChannelMsg::Data { data }) => {
-                stdout.extend_from_slice(&data);
-            }
-            Some(ChannelMsg::ExtendedData { data, ext }) => {
-                if ext == 1 {
-                    // stderr
-                    stderr.extend_from_slice(&data);
-                }
-            }
-            Some(ChannelMsg::ExitStatus { exit_status }) => {
-                exit_code = Some(exit_status as i32);
-            }
-            Some(ChannelMsg::Eof) | None => {
-                break;
-            }
-            _ => {}
+    fn auth_publickey_sign(
+        &mut self,
+        key: &PublicKey,
+        hash_alg: Option<russh::keys::HashAlg>,
+        to_sign: russh::CryptoVec,
+    ) -> impl std::future::Future<Output = Result<russh::CryptoVec, Self::Error>> + Send {
+        let key = key.clone();
+        async move {
+            self.0
+                .sign_request(&key, hash_alg, to_sign)
+                .await
+                .map_err(Into::into)
         }
     }
+}
+
+/// Try every identity held by the running ssh-agent
+async fn authenticate_with_agent(handle: &mut Handle<ClientHandler>, username: &str) -> Result<bool, String> {
+    let mut agent = AgentSigner(connect_agent().await?);
+    let identities = agent
+        .0
+        .request_identities()
+        .await
+        .map_err(|e| format!("Failed to list ssh-agent identities: {}", e))?;
     
-    // Combine stdout and stderr, with stderr appended if not empty
-    let mut output = String::from_utf8_lossy(&stdout).to_string();
-    if !stderr.is_empty() {
-        if !output.is_empty() && !output.ends_with('\n') {
-            output.push('\n');
+    for key in identities {
+        let hash_alg = if key.algorithm().is_rsa() {
+            handle.best_supported_rsa_hash().await.ok().flatten().flatten()
+        } else {
+            None
+        };
+        let result = handle
+            .authenticate_publickey_with(username, key, hash_alg, &mut agent)
+            .await
+            .map_err(|e| format!("ssh-agent authentication failed: {}", e))?;
+        if result.success() {
+            return Ok(true);
         }
-        output.push_str(&String::from_utf8_lossy(&stderr));
     }
     
-    Ok(TerminalOutput::new(command, &output, exit_code))
+    Ok(false)
 }
 
-async fn list_sftp_files_async(
-    handle: &Handle<ClientHandler>,
-    path: &str,
-) -> Result<Vec<SftpFileInfo>, String> {
-    // Open SFTP subsystem
-    let channel = handle
-        .channel_open_session()
+/// Load an OpenSSH certificate given as its content or a file path
+fn load_certificate(cert_str: &str) -> Result<Certificate, String> {
+    let trimmed = cert_str.trim();
+    if trimmed.contains("-cert-v01@openssh.com ") {
+        Certificate::from_openssh(trimmed).map_err(|e| format!("Failed to parse certificate: {}", e))
+    } else {
+        russh::keys::load_openssh_certificate(trimmed)
+            .map_err(|e| format!("Failed to load certificate: {}", e))
+    }
+}
+
+/// Run the keyboard-interactive exchange. Prompts go to the frontend when a prompter is set,
+/// otherwise a single hidden prompt is answered with the password.
+async fn authenticate_keyboard_interactive(
+    handle: &mut Handle<ClientHandler>,
+    host: &str,
+    username: &str,
+    credentials: &SSHCredentials,
+) -> Result<bool, String> {
+    let mut response = handle
+        .authenticate_keyboard_interactive_start(username, None)
         .await
-        .map_err(|e| format!("Failed to open channel: {}", e))?;
+        .map_err(|e| format!("Keyboard-interactive authentication failed: {}", e))?;
     
-    channel
-        .request_subsystem(true, "sftp")
-        .await
-        .map_err(|e| format!("Failed to request SFTP subsystem: {}", e))?;
+    loop {
+        match response {
+            KeyboardInteractiveAuthResponse::Success => return Ok(true),
+            KeyboardInteractiveAuthResponse::Failure { .. } => return Ok(false),
+            KeyboardInteractiveAuthResponse::InfoRequest { name, instructions, prompts } => {
+                let answers = if prompts.is_empty() {
+                    Vec::new()
+                } else if let Some(prompter) = &credentials.prompter {
+                    let items = prompts
+                        .into_iter()
+                        .map(|p| InteractivePromptItem { prompt: p.prompt, echo: p.echo })
+                        .collect();
+                    prompter.ask(host, username, name, instructions, items).await?
+                } else {
+                    match (credentials.password.as_ref(), prompts.as_slice()) {
+                        (Some(pwd), [prompt]) if !prompt.echo => vec![pwd.clone()],
+                        _ => return Ok(false),
+                    }
+                };
+                response = handle
+                    .authenticate_keyboard_interactive_respond(answers)
+                    .await
+                    .map_err(|e| format!("Keyboard-interactive authentication failed: {}", e))?;
+            }
+        }
+    }
+}
+
+/// Authenticate with the configured methods in order: ssh-agent, certificate, private key,
+/// password, keyboard-interactive. Servers requiring several factors (partial success) simply
+/// continue with the next method.
+async fn authenticate_async(
+    handle: &mut Handle<ClientHandler>,
+    host: &str,
+    username: &str,
+    credentials: &SSHCredentials,
+) -> Result<(), String> {
+    let mut errors = Vec::new();
     
-    let sftp = SftpSession::new(channel.into_stream())
-        .await
-        .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+    if credentials.use_agent {
+        match authenticate_with_agent(handle, username).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => errors.push("ssh-agent identities rejected".to_string()),
+            Err(e) => errors.push(e),
+        }
+    }
     
-    // Read directory
-    let dir = sftp
-        .read_dir(path)
+    if let Some(key_str) = credentials.private_key.as_deref() {
+        let russh_key = Arc::new(load_private_key(key_str, credentials.key_passphrase.as_deref())?);
+        
+        if let Some(cert_str) = credentials.certificate.as_deref() {
+            let cert = load_certificate(cert_str)?;
+            let result = handle
+                .authenticate_openssh_cert(username, russh_key.clone(), cert)
+                .await
+                .map_err(|e| format!("Certificate authentication failed: {}", e))?;
+            if result.success() {
+                return Ok(());
+            }
+            errors.push("Certificate rejected".to_string());
+        }
+        
+        // RSA keys sign with the best hash the server advertises (rsa-sha2-512/256) rather
+        // than SHA-1, which current OpenSSH servers refuse
+        let hash_alg = if russh_key.algorithm().is_rsa() {
+            handle.best_supported_rsa_hash().await.ok().flatten().flatten()
+        } else {
+            None
+        };
+        let key_with_hash = PrivateKeyWithHashAlg::new(russh_key, hash_alg);
+        
+        let result = handle
+            .authenticate_publickey(username, key_with_hash)
+            .await
+            .map_err(|e| format!("Key authentication failed: {}", e))?;
+        if result.success() {
+            return Ok(());
+        }
+        errors.push("Key rejected".to_string());
+    }
+    
+    if let Some(pwd) = credentials.password.as_deref() {
+        let result = handle
+            .authenticate_password(username, pwd)
+            .await
+            .map_err(|e| format!("Password authentication failed: {}", e))?;
+        if result.success() {
+            return Ok(());
+        }
+        errors.push("Password rejected".to_string());
+    }
+    
+    if credentials.prompter.is_some() || credentials.password.is_some() {
+        if authenticate_keyboard_interactive(handle, host, username, credentials).await? {
+            return Ok(());
+        }
+        errors.push("Keyboard-interactive rejected".to_string());
+    }
+    
+    if errors.is_empty() {
+        return Err("No authentication method provided".to_string());
+    }
+    Err(format!("Authentication failed: {}", errors.join("; ")))
+}
+
+async fn connect_async(
+    host: &str,
+    port: u16,
+    username: &str,
+    credentials: &SSHCredentials,
+    jump_hosts: &[JumpHostConfig],
+    remote_forwards: RemoteForwardRegistry,
+) -> Result<(Handle<ClientHandler>, Vec<Handle<ClientHandler>>), String> {
+    let config = client_config();
+    
+    // Walk the ProxyJump chain, each hop tunnelled through the previous one
+    let mut jump_handles: Vec<Handle<ClientHandler>> = Vec::new();
+    for hop in jump_hosts {
+        let hop_label = format!("{}@{}:{}", hop.username, hop.host, hop.port);
+        let mut hop_handle = open_transport(config.clone(), &hop.host, hop.port, jump_handles.last(), RemoteForwardRegistry::default())
+            .await
+            .map_err(|e| format!("Jump host {}: {}", hop_label, e))?;
+        authenticate_async(&mut hop_handle, &hop.host, &hop.username, &hop.credentials)
+            .await
+            .map_err(|e| format!("Jump host {}: {}", hop_label, e))?;
+        jump_handles.push(hop_handle);
+    }
+    
+    let mut handle = open_transport(config, host, port, jump_handles.last(), remote_forwards).await?;
+    authenticate_async(&mut handle, host, username, credentials).await?;
+    
+    Ok((handle, jump_handles))
+}
+
+async fn scan_host_key_async(host: &str, port: u16) -> Result<HostKeyInfo, String> {
+    let addr = resolve_addr(host, port)?;
+    let server_key = Arc::new(Mutex::new(None));
+    let scanner = HostKeyScanner { server_key: server_key.clone() };
+    
+    // The scanner rejects the key, so the handshake is expected to fail here
+    let _ = russh::client::connect(Arc::new(Config::default()), addr, scanner).await;
+    
+    let key = server_key
+        .lock()
+        .ok()
+        .and_then(|mut g| g.take())
+        .ok_or_else(|| format!("Failed to retrieve host key from {}:{}", host, port))?;
+    
+    KnownHostsStore::new()
+        .and_then(|store| store.describe(host, port, &key))
+        .map_err(|e| e.to_string())
+}
+
+/// Emits output chunks of a streamed command as `ssh_command_output` events. Bytes of a
+/// UTF-8 sequence split across chunks are held back until the rest arrives.
+struct OutputSink {
+    job_id: String,
+    window: tauri::Window,
+    pending: [Vec<u8>; 2],
+}
+
+impl OutputSink {
+    fn new(job_id: String, window: tauri::Window) -> Self {
+        Self { job_id, window, pending: [Vec::new(), Vec::new()] }
+    }
+    
+    fn emit(&mut self, stream: &str, data: &[u8]) {
+        let pending = &mut self.pending[(stream == "stderr") as usize];
+        pending.extend_from_slice(data);
+        let complete = match std::str::from_utf8(pending) {
+            Ok(_) => pending.len(),
+            // Invalid bytes in the middle are replaced; an incomplete tail waits for more data
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => pending.len(),
+        };
+        if complete == 0 {
+            return;
+        }
+        let chunk: Vec<u8> = pending.drain(..complete).collect();
+        let _ = self.window.emit(
+            "ssh_command_output",
+            serde_json::json!({"jobId": self.job_id, "stream": stream, "data": String::from_utf8_lossy(&chunk)}),
+        );
+    }
+    
+    fn finish(mut self, result: &Result<TerminalOutput, String>) {
+        for stream in ["stdout", "stderr"] {
+            let rest = std::mem::take(&mut self.pending[(stream == "stderr") as usize]);
+            if !rest.is_empty() {
+                let _ = self.window.emit(
+                    "ssh_command_output",
+                    serde_json::json!({"jobId": self.job_id, "stream": stream, "data": String::from_utf8_lossy(&rest)}),
+                );
+            }
+        }
+        let payload = match result {
+            Ok(output) => serde_json::json!({
+                "jobId": self.job_id,
+                "exitCode": output.exit_code,
+                "exitSignal": output.exit_signal,
+                "durationMs": output.duration_ms,
+                "truncated": output.truncated,
+            }),
+            Err(e) => serde_json::json!({"jobId": self.job_id, "error": e}),
+        };
+        let _ = self.window.emit("ssh_command_finished", payload);
+    }
+}
+
+/// Ask the remote process to stop and tear down its channel
+async fn stop_remote_command(channel: &russh::Channel<Msg>) {
+    let _ = channel.signal(russh::Sig::TERM).await;
+    let _ = channel.eof().await;
+    let _ = channel.close().await;
+}
+
+async fn execute_command_async(
+    handle: &SharedHandle,
+    command: &str,
+    options: &ExecOptions,
+    cancel_rx: Option<tokio::sync::oneshot::Receiver<()>>,
+    mut sink: Option<&mut OutputSink>,
+) -> Result<TerminalOutput, String> {
+    let started = std::time::Instant::now();
+    let deadline = options
+        .timeout_ms
+        .map(|ms| tokio::time::Instant::now() + std::time::Duration::from_millis(ms));
+    let timed_out = async move {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    };
+    let cancelled = async move {
+        if let Some(rx) = cancel_rx {
+            // A dropped sender is not a cancellation
+            if rx.await.is_ok() {
+                return;
+            }
+        }
+        std::future::pending::<()>().await
+    };
+    tokio::pin!(timed_out, cancelled);
+    
+    // The request may have waited for a free slot; don't start it if it was cancelled meanwhile
+    if (&mut cancelled).now_or_never().is_some() {
+        return Err("Command cancelled".to_string());
+    }
+    
+    // Open a session channel
+    let channel = ssh_file_transfer::open_channel(handle).await?;
+    
+    // Execute command
+    channel
+        .exec(true, command)
+        .await
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+    
+    // Read output; streamed output goes straight to the sink and is not kept
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut received = 0usize;
+    let mut exit_code: Option<i32> = None;
+    let mut exit_signal: Option<String> = None;
+    let mut truncated = false;
+    let max_output = options.max_output_bytes.unwrap_or(usize::MAX);
+    
+    let mut channel = channel;
+    loop {
+        let msg = tokio::select! {
+            msg = channel.wait() => msg,
+            _ = &mut timed_out => {
+                stop_remote_command(&channel).await;
+                return Err(format!("Command timed out after {} ms", options.timeout_ms.unwrap_or_default()));
+            }
+            _ = &mut cancelled => {
+                stop_remote_command(&channel).await;
+                return Err("Command cancelled".to_string());
+            }
+        };
+        let (stream, data) = match msg {
+            Some(ChannelMsg::Data { data }) => ("stdout", data),
+            // ext 1 is stderr
+            Some(ChannelMsg::ExtendedData { data, ext: 1 }) => ("stderr", data),
+            Some(ChannelMsg::ExitStatus { exit_status }) => {
+                exit_code = Some(exit_status as i32);
+                continue;
+            }
+            Some(ChannelMsg::ExitSignal { signal_name, .. }) => {
+                exit_signal = Some(match signal_name {
+                    russh::Sig::Custom(name) => name,
+                    sig => format!("{:?}", sig),
+                });
+                continue;
+            }
+            // The exit status/signal may follow EOF, so wait for the channel to close
+            Some(ChannelMsg::Close) | None => break,
+            _ => continue,
+        };
+        
+        let room = max_output - received;
+        let over_limit = data.len() > room;
+        let data = &data[..data.len().min(room)];
+        received += data.len();
+        match sink.as_deref_mut() {
+            Some(sink) => sink.emit(stream, data),
+            None if stream == "stderr" => stderr.extend_from_slice(data),
+            None => stdout.extend_from_slice(data),
+        }
+        
+        if over_limit {
+            truncated = true;
+            stop_remote_command(&channel).await;
+            break;
+        }
+    }
+    
+    let mut result = TerminalOutput::from_streams(
+        command,
+        &String::from_utf8_lossy(&stdout),
+        &String::from_utf8_lossy(&stderr),
+        exit_code,
+    );
+    result.truncated = truncated;
+    result.exit_signal = exit_signal;
+    result.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+async fn list_sftp_files_async(
+    handle: &SharedHandle,
+    path: &str,
+) -> Result<Vec<SftpFileInfo>, String> {
+    // Open SFTP subsystem
+    let channel = ssh_file_transfer::open_channel(handle).await?;
+    
+    channel
+        .request_subsystem(true, "sftp")
+        .await
+        .map_err(|e| format!("Failed to request SFTP subsystem: {}", e))?;
+    
+    let sftp = SftpSession::new(channel.into_stream())
+        .await
+        .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+    
+    // Read directory
+    let dir = sftp
+        .read_dir(path)
         .await
         .map_err(|e| format!("Failed to read directory: {}", e))?;
     
@@ -420,20 +1277,188 @@ async fn list_sftp_files_async(
             size,
             modified,
             permissions,
+            symlink_target: None,
         });
     }
     
+    for file in files.iter_mut().filter(|f| f.file_type == "symlink") {
+        file.symlink_target = sftp.read_link(file.path.as_str()).await.ok();
+    }
+    
     Ok(files)
 }
 
+/// Map the S_IFMT bits of an SFTP mode to the names used by SftpFileDetails
+fn sftp_file_type(attrs: &FileAttributes) -> &'static str {
+    match attrs.permissions.map(|p| p & 0o170000) {
+        Some(0o040000) => "directory",
+        Some(0o100000) => "file",
+        Some(0o120000) => "symlink",
+        Some(0o060000) => "block",
+        Some(0o020000) => "char",
+        Some(0o140000) => "socket",
+        Some(0o010000) => "fifo",
+        _ => "unknown",
+    }
+}
+
+fn format_sftp_time(t: Option<u32>) -> Option<String> {
+    t.and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// lstat (or stat when `follow_links`) a remote path, including where a symlink points
+async fn stat_sftp_async(
+    handle: &SharedHandle,
+    path: &str,
+    follow_links: bool,
+) -> Result<SftpFileDetails, String> {
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    
+    let link_attrs = sftp
+        .symlink_metadata(path)
+        .await
+        .map_err(|e| format!("Failed to stat {}: {}", path, e))?;
+    let is_symlink = sftp_file_type(&link_attrs) == "symlink";
+    
+    let (symlink_target, target_attrs) = if is_symlink {
+        let target = sftp.read_link(path).await.ok();
+        // A dangling link has no target attributes
+        (target, sftp.metadata(path).await.ok())
+    } else {
+        (None, None)
+    };
+    
+    let attrs = match (follow_links, &target_attrs) {
+        (true, Some(target)) => target.clone(),
+        _ => link_attrs,
+    };
+    let name = std::path::Path::new(path)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    
+    Ok(SftpFileDetails {
+        name,
+        path: path.to_string(),
+        file_type: sftp_file_type(&attrs).to_string(),
+        size: attrs.size.unwrap_or(0),
+        permissions: attrs.permissions.map(|p| format!("{:o}", p & 0o7777)).unwrap_or_else(|| "unknown".to_string()),
+        owner: attrs.user.clone().or_else(|| attrs.uid.map(|u| u.to_string())),
+        group: attrs.group.clone().or_else(|| attrs.gid.map(|g| g.to_string())),
+        created: None, // SFTP v3 has no birth time
+        modified: format_sftp_time(attrs.mtime),
+        accessed: format_sftp_time(attrs.atime),
+        uid: attrs.uid,
+        gid: attrs.gid,
+        mtime: attrs.mtime,
+        atime: attrs.atime,
+        followed_links: follow_links,
+        symlink_target,
+        target_type: target_attrs.as_ref().map(|a| sftp_file_type(a).to_string()),
+    })
+}
+
+/// Create `link_path` pointing at `target`
+async fn create_sftp_symlink_async(
+    handle: &SharedHandle,
+    target: &str,
+    link_path: &str,
+) -> Result<(), String> {
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    
+    // OpenSSH's sftp-server reads SSH_FXP_SYMLINK arguments in reverse order
+    // (target first), and russh-sftp sends them as (linkpath, targetpath)
+    sftp.symlink(target, link_path)
+        .await
+        .map_err(|e| format!("Failed to create symlink: {}", e))
+}
+
+/// SFTP sets atime/mtime and uid/gid in pairs, so changing only one of a pair
+/// needs the path's current value for the other
+fn needs_existing_attributes(change: &SftpAttributeChange) -> bool {
+    change.atime.is_some() != change.mtime.is_some() || change.uid.is_some() != change.gid.is_some()
+}
+
+/// Build the SETSTAT attributes for `change`, filling the other half of a pair
+/// from `existing` (the path's current attributes) when only one half is given
+fn merge_attribute_change(change: &SftpAttributeChange, existing: Option<&FileAttributes>) -> FileAttributes {
+    let mut attrs = FileAttributes::empty();
+    attrs.permissions = change.mode.map(|m| m & 0o7777);
+    if change.uid.is_some() || change.gid.is_some() {
+        // russh-sftp writes a missing half of the pair as 0 (root)
+        attrs.uid = change.uid.or(existing.and_then(|a| a.uid));
+        attrs.gid = change.gid.or(existing.and_then(|a| a.gid));
+    }
+    if change.atime.is_some() || change.mtime.is_some() {
+        attrs.atime = change.atime.or(existing.and_then(|a| a.atime)).or(change.mtime);
+        attrs.mtime = change.mtime.or(existing.and_then(|a| a.mtime)).or(change.atime);
+    }
+    attrs
+}
+
+/// Apply attribute changes to a path, and below it when `recursive`.
+/// Symlinks met during recursion are skipped since SETSTAT would follow them.
+/// Returns how many paths were changed.
+async fn set_sftp_attributes_async(
+    handle: &SharedHandle,
+    path: &str,
+    change: &SftpAttributeChange,
+    recursive: bool,
+) -> Result<usize, String> {
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    
+    let needs_existing = needs_existing_attributes(change);
+    
+    let mut pending = vec![path.to_string()];
+    let mut changed = 0;
+    while let Some(current) = pending.pop() {
+        let existing = if needs_existing {
+            let existing = sftp
+                .metadata(current.as_str())
+                .await
+                .map_err(|e| format!("Failed to stat {}: {}", current, e))?;
+            Some(existing)
+        } else {
+            None
+        };
+        let new_attrs = merge_attribute_change(change, existing.as_ref());
+        sftp.set_metadata(current.as_str(), new_attrs)
+            .await
+            .map_err(|e| format!("Failed to set attributes on {}: {}", current, e))?;
+        changed += 1;
+        
+        if !recursive {
+            continue;
+        }
+        let is_dir = sftp
+            .symlink_metadata(current.as_str())
+            .await
+            .map(|a| a.is_dir())
+            .unwrap_or(false);
+        if !is_dir {
+            continue;
+        }
+        let entries = sftp
+            .read_dir(current.as_str())
+            .await
+            .map_err(|e| format!("Failed to read directory {}: {}", current, e))?;
+        for entry in entries {
+            if entry.file_type().is_symlink() {
+                continue;
+            }
+            pending.push(format!("{}/{}", current.trim_end_matches('/'), entry.file_name()));
+        }
+    }
+    
+    Ok(changed)
+}
+
 async fn read_sftp_file_async(
-    handle: &Handle<ClientHandler>,
+    handle: &SharedHandle,
     path: &str,
 ) -> Result<Vec<u8>, String> {
-    let channel = handle
-        .channel_open_session()
-        .await
-        .map_err(|e| format!("Failed to open channel: {}", e))?;
+    let channel = ssh_file_transfer::open_channel(handle).await?;
     
     channel
         .request_subsystem(true, "sftp")
@@ -457,24 +1482,114 @@ async fn read_sftp_file_async(
     Ok(content)
 }
 
-async fn write_sftp_file_async(
-    handle: &Handle<ClientHandler>,
-    path: &str,
-    content: &[u8],
-) -> Result<(), String> {
-    let channel = handle
-        .channel_open_session()
+/// Read a whole file along with its version; None when it does not exist
+async fn read_versioned(sftp: &SftpSession, path: &str) -> Result<Option<VersionedFile>, String> {
+    let attrs = match sftp.metadata(path).await {
+        Ok(attrs) => attrs,
+        Err(russh_sftp::client::error::Error::Status(status))
+            if status.status_code == russh_sftp::protocol::StatusCode::NoSuchFile =>
+        {
+            return Ok(None)
+        }
+        Err(e) => return Err(format!("Failed to stat file: {}", e)),
+    };
+    
+    let mut file = sftp
+        .open(path)
         .await
-        .map_err(|e| format!("Failed to open channel: {}", e))?;
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
     
-    channel
-        .request_subsystem(true, "sftp")
+    let version = FileVersion::new(content.len() as u64, attrs.mtime, &content);
+    Ok(Some(VersionedFile { content, version }))
+}
+
+async fn read_sftp_file_versioned_async(
+    handle: &SharedHandle,
+    path: &str,
+) -> Result<VersionedFile, String> {
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    read_versioned(&sftp, path)
+        .await?
+        .ok_or_else(|| format!("File not found: {}", path))
+}
+
+/// Compare the version a write expects with what is on the server now (None: deleted)
+fn check_expected_version(path: &str, current: Option<&FileVersion>, expected: &str) -> Result<(), String> {
+    match current {
+        Some(current) if current.token == expected => Ok(()),
+        Some(current) => Err(format!(
+            "{}: {} (size {}, mtime {:?}) was modified after it was read",
+            FILE_CONFLICT_ERROR, path, current.size, current.mtime
+        )),
+        None => Err(format!("{}: {} was deleted after it was read", FILE_CONFLICT_ERROR, path)),
+    }
+}
+
+/// Copy the current content to `<path>.bak`. The backup is created fresh with the source's
+/// mode, so a copy of a secret is never readable by others, not even while it is written;
+/// owner and group are copied too when the server lets us.
+async fn write_backup(sftp: &SftpSession, path: &str, content: &[u8]) -> Result<(), String> {
+    let backup_path = format!("{}.bak", path);
+    let source = sftp
+        .metadata(path)
         .await
-        .map_err(|e| format!("Failed to request SFTP subsystem: {}", e))?;
+        .map_err(|e| format!("Failed to stat {}: {}", path, e))?;
     
-    let sftp = SftpSession::new(channel.into_stream())
+    // An existing backup may have looser permissions or be a symlink; replace it
+    let _ = sftp.remove_file(backup_path.as_str()).await;
+    let mut mode = FileAttributes::empty();
+    mode.permissions = Some(source.permissions.unwrap_or(0o600) & 0o777);
+    let mut backup = sftp
+        .open_with_flags_and_attributes(
+            backup_path.as_str(),
+            OpenFlags::CREATE | OpenFlags::EXCLUDE | OpenFlags::WRITE,
+            mode,
+        )
         .await
-        .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        .map_err(|e| format!("Failed to create backup {}: {}", backup_path, e))?;
+    backup.write_all(content)
+        .await
+        .map_err(|e| format!("Failed to write backup {}: {}", backup_path, e))?;
+    backup.shutdown()
+        .await
+        .map_err(|e| format!("Failed to write backup {}: {}", backup_path, e))?;
+    
+    // The umask may have narrowed the mode at creation; chown needs privileges and is best effort
+    let mut mode = FileAttributes::empty();
+    mode.permissions = source.permissions.map(|p| p & 0o7777);
+    let _ = sftp.set_metadata(backup_path.as_str(), mode).await;
+    if let (Some(uid), Some(gid)) = (source.uid, source.gid) {
+        let mut owner = FileAttributes::empty();
+        owner.uid = Some(uid);
+        owner.gid = Some(gid);
+        let _ = sftp.set_metadata(backup_path.as_str(), owner).await;
+    }
+    Ok(())
+}
+
+async fn write_sftp_file_async(
+    handle: &SharedHandle,
+    path: &str,
+    content: &[u8],
+    options: &WriteOptions,
+) -> Result<FileVersion, String> {
+    let sftp = ssh_file_transfer::open_sftp(handle).await?;
+    
+    if options.expected_version.is_some() || options.backup {
+        let current = read_versioned(&sftp, path).await?;
+        
+        if let Some(expected) = &options.expected_version {
+            check_expected_version(path, current.as_ref().map(|c| &c.version), expected)?;
+        }
+        
+        if let (true, Some(current)) = (options.backup, &current) {
+            write_backup(&sftp, path, &current.content).await?;
+        }
+    }
     
     let mut file = sftp
         .create(path)
@@ -484,18 +1599,19 @@ async fn write_sftp_file_async(
     file.write_all(content)
         .await
         .map_err(|e| format!("Failed to write file: {}", e))?;
+    file.shutdown()
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
     
-    Ok(())
+    let mtime = sftp.metadata(path).await.ok().and_then(|attrs| attrs.mtime);
+    Ok(FileVersion::new(content.len() as u64, mtime, content))
 }
 
 async fn delete_sftp_file_async(
-    handle: &Handle<ClientHandler>,
+    handle: &SharedHandle,
     path: &str,
 ) -> Result<(), String> {
-    let channel = handle
-        .channel_open_session()
-        .await
-        .map_err(|e| format!("Failed to open channel: {}", e))?;
+    let channel = ssh_file_transfer::open_channel(handle).await?;
     
     channel
         .request_subsystem(true, "sftp")
@@ -514,13 +1630,10 @@ async fn delete_sftp_file_async(
 }
 
 async fn create_sftp_directory_async(
-    handle: &Handle<ClientHandler>,
+    handle: &SharedHandle,
     path: &str,
 ) -> Result<(), String> {
-    let channel = handle
-        .channel_open_session()
-        .await
-        .map_err(|e| format!("Failed to open channel: {}", e))?;
+    let channel = ssh_file_transfer::open_channel(handle).await?;
     
     channel
         .request_subsystem(true, "sftp")
@@ -539,14 +1652,11 @@ async fn create_sftp_directory_async(
 }
 
 async fn rename_sftp_file_async(
-    handle: &Handle<ClientHandler>,
+    handle: &SharedHandle,
     old_path: &str,
     new_path: &str,
 ) -> Result<(), String> {
-    let channel = handle
-        .channel_open_session()
-        .await
-        .map_err(|e| format!("Failed to open channel: {}", e))?;
+    let channel = ssh_file_transfer::open_channel(handle).await?;
     
     channel
         .request_subsystem(true, "sftp")
@@ -564,41 +1674,506 @@ async fn rename_sftp_file_async(
     Ok(())
 }
 
+/// Tear down every port forward that belongs to a session
+async fn stop_port_forwards(
+    port_forwards: &mut HashMap<String, PortForward>,
+    session: &SessionData,
+    session_id: &str,
+) {
+    let forward_ids: Vec<String> = port_forwards
+        .iter()
+        .filter(|(_, f)| f.session_id() == session_id)
+        .map(|(id, _)| id.clone())
+        .collect();
+    for forward_id in forward_ids {
+        if let Some(forward) = port_forwards.remove(&forward_id) {
+            forward.stop(&session.handle, &session.remote_forwards).await;
+        }
+    }
+}
+
+/// Open a session channel with a PTY and an interactive shell
+async fn open_pty_channel(
+    handle: &SharedHandle,
+    cols: u32,
+    rows: u32,
+) -> Result<russh::Channel<Msg>, String> {
+    let channel = ssh_file_transfer::open_channel(handle).await?;
+    
+    // Request PTY
+    channel
+        .request_pty(
+            true,
+            "xterm-256color",
+            cols,
+            rows,
+            0, // pixel width
+            0, // pixel height
+            &[], // modes
+        )
+        .await
+        .map_err(|e| format!("Failed to request PTY: {}", e))?;
+    
+    // Request shell
+    channel
+        .request_shell(true)
+        .await
+        .map_err(|e| format!("Failed to request shell: {}", e))?;
+    
+    Ok(channel)
+}
+
+/// Split a freshly opened PTY channel into the read half for `spawn_terminal_reader`,
+/// the write half kept on the terminal and the input queue of its writer task
+fn split_terminal_channel(
+    channel: russh::Channel<Msg>,
+) -> (russh::ChannelReadHalf, Arc<russh::ChannelWriteHalf<Msg>>, tokio::sync::mpsc::UnboundedSender<TerminalInput>) {
+    let (reader, channel) = channel.split();
+    let input = spawn_terminal_writer(Box::pin(channel.make_writer()));
+    (reader, Arc::new(channel), input)
+}
+
+/// Write a terminal's input in order on a task of its own, so a terminal whose channel
+/// window is full holds up only its own keystrokes. The task ends once the queue is dropped.
+fn spawn_terminal_writer<W>(mut writer: W) -> tokio::sync::mpsc::UnboundedSender<TerminalInput>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel::<TerminalInput>();
+    tokio::spawn(async move {
+        while let Some((data, response_tx)) = input_rx.recv().await {
+            let written = match writer.write_all(&data).await {
+                Ok(()) => writer.flush().await,
+                Err(e) => Err(e),
+            };
+            let _ = response_tx.send(written.map_err(|e| format!("Failed to send data: {}", e)));
+        }
+    });
+    input_tx
+}
+
+/// Spawn a task to read output from the terminal channel and emit it to the window.
+/// The task exits once the terminal is re-attached to a newer channel (`generation` changes).
+fn spawn_terminal_reader(
+    terminal_sessions: TerminalSessions,
+    terminal_id: String,
+    generation: u64,
+    mut reader: russh::ChannelReadHalf,
+    window: tauri::Window,
+    hooks: TerminalHooks,
+) {
+    tokio::spawn(async move {
+        let mut remove = true;
+        loop {
+            // Wait without the table so other terminals stay usable
+            let msg = reader.wait().await;
+            let mut terminals = terminal_sessions.lock().await;
+            let Some(term) = terminals.get_mut(&terminal_id) else {
+                // Terminal removed
+                hooks.zmodem.detach(&terminal_id);
+                let _ = hooks.recordings.stop(&terminal_id);
+                hooks.groups.member_lost(&terminal_id, MemberLoss::Closed, &window);
+                return;
+            };
+            if term.generation != generation {
+                return;
+            }
+            match msg {
+                Some(ChannelMsg::Data { data }) => {
+                    // A running rz/sz transfer owns the output until it finishes
+                    if hooks.zmodem.forward(&terminal_id, &data) {
+                        continue;
+                    }
+                    let end = match term.zmodem_detector.scan(&data) {
+                        Some(found) => {
+                            let writer = term.channel.make_writer();
+                            hooks.zmodem.start(&terminal_id, &term.session_id, found.direction, &found.frame, writer, window.clone());
+                            found.output_end
+                        }
+                        None => data.len(),
+                    };
+                    if end == 0 {
+                        continue;
+                    }
+                    emit_terminal_output(term, &terminal_id, &data[..end], false, &hooks);
+                }
+                Some(ChannelMsg::ExtendedData { data, ext }) => {
+                    // stderr (ext == 1)
+                    if ext == 1 {
+                        emit_terminal_output(term, &terminal_id, &data, true, &hooks);
+                    }
+                }
+                Some(ChannelMsg::ExitStatus { exit_status: _ }) | Some(ChannelMsg::Eof) => {
+                    let _ = window.emit(
+                        "ssh_terminal_closed",
+                        serde_json::json!({"terminalId": terminal_id}),
+                    );
+                    break;
+                }
+                Some(ChannelMsg::Close) => {
+                    break;
+                }
+                Some(_) => {}
+                None => {
+                    // Transport went away without the shell exiting; keep the terminal
+                    // so it can be re-attached once the session is reconnected
+                    term.detached = true;
+                    remove = false;
+                    break;
+                }
+            }
+        }
+        
+        // Clean up terminal session when done; a transfer cannot outlive its channel
+        hooks.zmodem.detach(&terminal_id);
+        let loss = if remove { MemberLoss::Closed } else { MemberLoss::Disconnected };
+        hooks.groups.member_lost(&terminal_id, loss, &window);
+        if remove {
+            let _ = hooks.recordings.stop(&terminal_id);
+            terminal_sessions.lock().await.remove(&terminal_id);
+        }
+    });
+}
+
+/// Decode a chunk of terminal output, tee it to an active recording and send it to the
+/// frontend. Incomplete multi-byte characters wait in the decoder for the next chunk.
+fn emit_terminal_output(term: &mut TerminalSession, terminal_id: &str, data: &[u8], stderr: bool, hooks: &TerminalHooks) {
+    let text = term.decoder.decode(data, stderr);
+    if !text.is_empty() {
+        hooks.recordings.output(terminal_id, &text);
+        hooks.scrollback.output(terminal_id, &text);
+    } else if !term.decoder.options().raw {
+        return;
+    }
+    let _ = term.window.emit("ssh_terminal_data", term.decoder.payload(terminal_id, data, &text));
+}
+
+/// Run a request against a session on its own task so slow commands and transfers do not
+/// hold up other sessions or terminal input. The session's limiter bounds how many run at once.
+fn spawn_session_request<T, F, Fut>(
+    sessions: &HashMap<String, SessionData>,
+    session_id: &str,
+    response_tx: mpsc::Sender<Result<T, String>>,
+    request: F,
+) where
+    T: Send + 'static,
+    F: FnOnce(SharedHandle) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<T, String>> + Send,
+{
+    let session = match live_session(sessions, session_id) {
+        Ok(session) => session,
+        Err(e) => {
+            let _ = response_tx.send(Err(e));
+            return;
+        }
+    };
+    let handle = session.handle.clone();
+    let limiter = session.limiter.clone();
+    tokio::spawn(async move {
+        // The semaphore is never closed, so acquiring only waits for a free slot
+        let _permit = limiter.acquire_owned().await;
+        let _ = response_tx.send(request(handle).await);
+    });
+}
+
+/// Look up a session that is usable right now
+fn live_session<'a>(sessions: &'a HashMap<String, SessionData>, session_id: &str) -> Result<&'a SessionData, String> {
+    match sessions.get(session_id) {
+        Some(session) if session.reconnecting => Err(format!("Session is reconnecting: {}", session_id)),
+        Some(session) => Ok(session),
+        None => Err(format!("Session not found: {}", session_id)),
+    }
+}
+
+/// Register a cancellable command under `cancel_id`; an id that is still running is
+/// refused, since the second registration would take over the first one's cancel handle
+fn register_running_command(
+    running_commands: &Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>>,
+    cancel_id: &str,
+) -> Result<tokio::sync::oneshot::Receiver<()>, String> {
+    let mut running = running_commands.lock().unwrap();
+    if running.contains_key(cancel_id) {
+        return Err(format!("A command with id {} is already running", cancel_id));
+    }
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    running.insert(cancel_id.to_string(), cancel_tx);
+    Ok(cancel_rx)
+}
+
+/// Re-establish a dropped session with exponential backoff; `max_retries` of 0 disables
+/// auto-reconnect and the session is dropped right away
+async fn reconnect_with_backoff(
+    info: ConnectionInfo,
+    credentials: SSHCredentials,
+    jump_hosts: Vec<JumpHostConfig>,
+    remote_forwards: RemoteForwardRegistry,
+    max_retries: u32,
+) -> Result<(Handle<ClientHandler>, Vec<Handle<ClientHandler>>), String> {
+    if max_retries == 0 {
+        return Err("Auto-reconnect is disabled".to_string());
+    }
+    let attempts = max_retries;
+    let mut delay = RECONNECT_BASE_DELAY;
+    let mut last_error = String::new();
+    
+    for _ in 0..attempts {
+        tokio::time::sleep(delay).await;
+        match connect_async(&info.host, info.port, &info.username, &credentials, &jump_hosts, remote_forwards.clone()).await {
+            Ok(handles) => return Ok(handles),
+            Err(e) => last_error = e,
+        }
+        delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+    }
+    
+    Err(format!("Reconnect failed after {} attempts: {}", attempts, last_error))
+}
+
+/// The previous current session to disconnect after switching to `opened`: only one
+/// that nothing runs on any more, since a session to another host can still carry
+/// terminals, port forwards or watches the user left open
+fn idle_previous_session(previous: Option<String>, opened: &str, in_use: impl Fn(&str) -> bool) -> Option<String> {
+    previous.filter(|id| id != opened && !in_use(id))
+}
+
+/// Bind address and port of the remote forwards of a session
+fn remote_forward_binds(port_forwards: &HashMap<String, PortForward>, session_id: &str) -> Vec<(String, u16)> {
+    port_forwards
+        .values()
+        .filter(|f| f.session_id() == session_id)
+        .map(|f| f.info())
+        .filter(|info| info.kind == ssh_port_forward::PortForwardKind::Remote)
+        .map(|info| (info.bind_address, info.bind_port))
+        .collect()
+}
+
+/// Ask the server to listen again for the remote forwards of a reconnected session
+async fn restore_remote_forwards(handle: &SharedHandle, binds: &[(String, u16)]) {
+    if binds.is_empty() {
+        return;
+    }
+    let mut handle = handle.write().await;
+    for (address, port) in binds {
+        let _ = handle.tcpip_forward(address.clone(), *port as u32).await;
+    }
+}
+
+/// Open fresh PTYs for the terminals of a session and tell the frontend. After a reconnect
+/// every terminal of the session needs one, otherwise only those whose channel dropped.
+/// Runs on a task; a terminal closed or resumed again in the meantime keeps its state
+/// and the PTY opened for it is closed again.
+fn resume_terminals(
+    terminal_sessions: &TerminalSessions,
+    session: &SessionData,
+    session_id: &str,
+    include_attached: bool,
+    hooks: &TerminalHooks,
+) {
+    let terminal_sessions = terminal_sessions.clone();
+    let handle = session.handle.clone();
+    let session_id = session_id.to_string();
+    let hooks = hooks.clone();
+    
+    tokio::spawn(async move {
+        // Bumping the generation retires the current readers
+        let targets: Vec<(String, u32, u32, u64)> = terminal_sessions
+            .lock()
+            .await
+            .iter_mut()
+            .filter(|(_, t)| t.session_id == session_id && (include_attached || t.detached))
+            .map(|(terminal_id, term)| {
+                term.generation += 1;
+                (terminal_id.clone(), term.cols, term.rows, term.generation)
+            })
+            .collect();
+        
+        for (terminal_id, cols, rows, generation) in targets {
+            let opened = open_pty_channel(&handle, cols, rows).await;
+            let mut terminals = terminal_sessions.lock().await;
+            let Some(term) = terminals.get_mut(&terminal_id).filter(|t| t.generation == generation) else {
+                drop(terminals);
+                if let Ok(channel) = opened {
+                    let _ = channel.close().await;
+                }
+                continue;
+            };
+            match opened {
+                Ok(channel) => {
+                    let (reader, channel, input) = split_terminal_channel(channel);
+                    term.channel = channel;
+                    term.input = input;
+                    term.detached = false;
+                    term.decoder.reset();
+                    term.zmodem_detector.reset();
+                    spawn_terminal_reader(terminal_sessions.clone(), terminal_id.clone(), generation, reader, term.window.clone(), hooks.clone());
+                    let _ = term.window.emit(
+                        "ssh_session_reconnected",
+                        serde_json::json!({"sessionId": session_id, "terminalId": terminal_id}),
+                    );
+                }
+                Err(_) => {
+                    if let Some(term) = terminals.remove(&terminal_id) {
+                        let _ = term.window.emit("ssh_terminal_closed", serde_json::json!({"terminalId": terminal_id}));
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Drop the terminals of a session that is gone for good
+async fn close_session_terminals(terminal_sessions: &TerminalSessions, session_id: &str) {
+    let mut terminals = terminal_sessions.lock().await;
+    let closed: Vec<String> = terminals
+        .iter()
+        .filter(|(_, t)| t.session_id == session_id)
+        .map(|(id, _)| id.clone())
+        .collect();
+    let mut channels = Vec::new();
+    for terminal_id in closed {
+        if let Some(term) = terminals.remove(&terminal_id) {
+            let _ = term.window.emit("ssh_terminal_closed", serde_json::json!({"terminalId": terminal_id}));
+            channels.push(term.channel);
+        }
+    }
+    drop(terminals);
+    tokio::spawn(close_terminal_channels(channels));
+}
+
+/// Send EOF and close on terminal channels already taken out of the table
+async fn close_terminal_channels(channels: Vec<Arc<russh::ChannelWriteHalf<Msg>>>) {
+    for channel in channels {
+        let _ = channel.eof().await;
+        let _ = channel.close().await;
+    }
+}
+
 // ================== Worker Thread ==================
 
-fn run_worker(rx: mpsc::Receiver<WorkerCommand>) {
+/// The worker owns the session/terminal maps and dispatches commands one by one. Anything
+/// that talks to the server for longer than a round-trip (connects, commands, SFTP) runs on
+/// its own task and answers `response_tx` from there, so the loop itself never waits on it.
+fn run_worker(
+    rx: mpsc::Receiver<WorkerCommand>,
+    worker_tx: mpsc::Sender<WorkerCommand>,
+    max_retries: Arc<AtomicU32>,
+    hooks: TerminalHooks,
+) {
     // Create a new Tokio runtime in this dedicated thread
     let rt = tokio::runtime::Builder::new_multi_thread()
-        .worker_threads(2)
+        .worker_threads(4)
         .enable_all()
         .build()
         .expect("Failed to create Tokio runtime for SSH worker");
     
     rt.block_on(async {
         let mut sessions: HashMap<String, SessionData> = HashMap::new();
-        let terminal_sessions: Arc<tokio::sync::Mutex<HashMap<String, TerminalSession>>> = 
-            Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+        let mut port_forwards: HashMap<String, PortForward> = HashMap::new();
+        let terminal_sessions: TerminalSessions = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+        let mut last_terminal_check = std::time::Instant::now();
+        // Cancellation handles of in-flight commands started with an ExecOptions::cancel_id
+        let running_commands: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
         
         loop {
-            // Check for commands (non-blocking with a small timeout to allow checking)
-            let cmd = match rx.recv() {
-                Ok(cmd) => cmd,
-                Err(_) => break, // Channel closed
+            // Check for commands (with a timeout so dropped sessions are noticed while idle)
+            let cmd = match rx.recv_timeout(LIVENESS_CHECK_INTERVAL) {
+                Ok(cmd) => Some(cmd),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break, // Channel closed
+            };
+            
+            // Liveness: start reconnecting sessions whose transport has dropped
+            for (session_id, session) in sessions.iter_mut() {
+                // Never wait for the lock here: a writer (remote forward, handle swap) may be
+                // waiting on the server, and the session is checked again next round
+                let closed = !session.reconnecting && session.handle.try_read().is_ok_and(|handle| handle.is_closed());
+                if !closed {
+                    continue;
+                }
+                session.reconnecting = true;
+                let worker_tx = worker_tx.clone();
+                let session_id = session_id.clone();
+                let reconnect = reconnect_with_backoff(
+                    session.info.clone(),
+                    session.credentials.clone(),
+                    session.jump_hosts.clone(),
+                    session.remote_forwards.clone(),
+                    max_retries.load(Ordering::Relaxed),
+                );
+                tokio::spawn(async move {
+                    let result = reconnect.await;
+                    let _ = worker_tx.send(WorkerCommand::Reconnected { session_id, result });
+                });
+            }
+            
+            // Terminals whose channel dropped on a session that is still up get a new PTY,
+            // those left without a session are closed
+            if last_terminal_check.elapsed() >= LIVENESS_CHECK_INTERVAL {
+                last_terminal_check = std::time::Instant::now();
+                let orphaned: Vec<String> = terminal_sessions
+                    .lock()
+                    .await
+                    .values()
+                    .filter(|t| t.detached)
+                    .map(|t| t.session_id.clone())
+                    .collect();
+                for session_id in orphaned {
+                    match sessions.get(&session_id) {
+                        Some(session) if session.reconnecting => {}
+                        Some(session) => resume_terminals(&terminal_sessions, session, &session_id, false, &hooks),
+                        None => close_session_terminals(&terminal_sessions, &session_id).await,
+                    }
+                }
+            }
+            
+            let Some(cmd) = cmd else {
+                continue;
             };
             
             match cmd {
-                WorkerCommand::Connect { host, port, username, password, private_key, response_tx } => {
-                    let result = connect_async(&host, port, &username, password.as_deref(), private_key.as_deref()).await;
-                    match result {
-                        Ok(handle) => {
-                            let session_id = format!("{}@{}:{}", username, host, port);
-                            let info = ConnectionInfo {
-                                host: host.clone(),
+                // Connecting can take a while (jump hosts, interactive prompts), so it runs on
+                // its own task and reports back through `Connected`
+                WorkerCommand::Connect { host, port, username, credentials, jump_hosts, response_tx } => {
+                    let worker_tx = worker_tx.clone();
+                    tokio::spawn(async move {
+                        let remote_forwards = RemoteForwardRegistry::default();
+                        let result = connect_async(&host, port, &username, &credentials, &jump_hosts, remote_forwards.clone()).await;
+                        let pending = PendingConnection {
+                            info: ConnectionInfo {
+                                session_id: String::new(), // assigned once registered
+                                target: format!("{}@{}:{}", username, host, port),
+                                host,
                                 port,
-                                username: username.clone(),
-                                auth_method: if private_key.is_some() { "key".to_string() } else { "password".to_string() },
-                            };
-                            sessions.insert(session_id.clone(), SessionData { handle, info });
+                                username,
+                                connected_at: chrono::Utc::now(),
+                                auth_method: credentials.auth_method(),
+                                jump_hosts: jump_hosts.iter().map(|hop| format!("{}@{}:{}", hop.username, hop.host, hop.port)).collect(),
+                            },
+                            credentials,
+                            jump_hosts,
+                            remote_forwards,
+                        };
+                        let _ = worker_tx.send(WorkerCommand::Connected { pending, result, response_tx });
+                    });
+                }
+                
+                WorkerCommand::Connected { pending, result, response_tx } => {
+                    match result {
+                        Ok((handle, jump_handles)) => {
+                            // Unique per connection so the same target can be opened several times
+                            let session_id = uuid::Uuid::new_v4().to_string();
+                            let mut info = pending.info;
+                            info.session_id = session_id.clone();
+                            sessions.insert(session_id.clone(), SessionData {
+                                handle: Arc::new(tokio::sync::RwLock::new(handle)),
+                                jump_handles,
+                                remote_forwards: pending.remote_forwards,
+                                info,
+                                credentials: pending.credentials,
+                                jump_hosts: pending.jump_hosts,
+                                reconnecting: false,
+                                limiter: Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_REQUESTS_PER_SESSION)),
+                            });
                             let _ = response_tx.send(Ok(session_id));
                         }
                         Err(e) => {
@@ -607,72 +2182,265 @@ fn run_worker(rx: mpsc::Receiver<WorkerCommand>) {
                     }
                 }
                 
-                WorkerCommand::ExecuteCommand { session_id, command, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        execute_command_async(&session.handle, &command).await
-                    } else {
-                        Err(format!("Session not found: {}", session_id))
+                WorkerCommand::ScanHostKey { host, port, response_tx } => {
+                    tokio::spawn(async move {
+                        let _ = response_tx.send(scan_host_key_async(&host, port).await);
+                    });
+                }
+                
+                WorkerCommand::ExecuteCommand { session_id, command, options, response_tx } => {
+                    let cancel_rx = match (&options.cancel_id, live_session(&sessions, &session_id)) {
+                        (Some(cancel_id), Ok(_)) => match register_running_command(&running_commands, cancel_id) {
+                            Ok(cancel_rx) => Some(cancel_rx),
+                            Err(e) => {
+                                let _ = response_tx.send(Err(e));
+                                continue;
+                            }
+                        },
+                        _ => None,
                     };
-                    let _ = response_tx.send(result);
+                    let running_commands = running_commands.clone();
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        let result = execute_command_async(&handle, &command, &options, cancel_rx, None).await;
+                        if let Some(cancel_id) = &options.cancel_id {
+                            running_commands.lock().unwrap().remove(cancel_id);
+                        }
+                        result
+                    });
                 }
                 
-                WorkerCommand::ListSftpFiles { session_id, path, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        list_sftp_files_async(&session.handle, &path).await
-                    } else {
-                        Err(format!("Session not found: {}", session_id))
+                // Transfers are long-running and bounded by the transfer queue rather than the
+                // session's request limiter
+                WorkerCommand::StartTransfer { job, window, done_tx, response_tx } => {
+                    let result = match live_session(&sessions, &job.session_id) {
+                        Ok(session) => {
+                            let handle = session.handle.clone();
+                            tokio::spawn(async move {
+                                let result = ssh_file_transfer::run_transfer(handle, job, window).await;
+                                if let Some(done_tx) = done_tx {
+                                    let _ = done_tx.send(result);
+                                }
+                            });
+                            Ok(())
+                        }
+                        Err(e) => Err(e),
                     };
                     let _ = response_tx.send(result);
                 }
                 
-                WorkerCommand::ReadSftpFile { session_id, path, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        read_sftp_file_async(&session.handle, &path).await
-                    } else {
-                        Err(format!("Session not found: {}", session_id))
-                    };
+                // Plans the whole tree, then runs every file through the transfer queue;
+                // answers once the files are queued
+                WorkerCommand::StartDirectoryTransfer { session_id, direction, source_path, destination_path, options, queue, window, response_tx } => {
+                    spawn_session_request(&sessions, &session_id.clone(), response_tx, move |handle| async move {
+                        let (summary, jobs) = ssh_file_sync::prepare_directory_transfer(
+                            &handle,
+                            &queue,
+                            &session_id,
+                            direction,
+                            &source_path,
+                            &destination_path,
+                            &options,
+                        )
+                        .await?;
+                        for job in jobs {
+                            let handle = handle.clone();
+                            let window = window.clone();
+                            tokio::spawn(async move {
+                                let _ = ssh_file_transfer::run_transfer(handle, job, window).await;
+                            });
+                        }
+                        Ok(summary)
+                    });
+                }
+                
+                // Watches hold their own channel for as long as they run, so they skip the
+                // per-session request limiter
+                WorkerCommand::StartWatch { info, registry, stop_rx, window, response_tx } => {
+                    let result = live_session(&sessions, &info.session_id).map(|session| {
+                        let handle = session.handle.clone();
+                        tokio::spawn(ssh_file_watch::run_watch(handle, info, registry, window, stop_rx));
+                    });
                     let _ = response_tx.send(result);
                 }
                 
-                WorkerCommand::WriteSftpFile { session_id, path, content, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        write_sftp_file_async(&session.handle, &path, &content).await
-                    } else {
-                        Err(format!("Session not found: {}", session_id))
+                // Answers as soon as the command is queued; output and the exit status
+                // arrive as events keyed by the job id
+                WorkerCommand::StreamCommand { session_id, command, options, job_id, window, response_tx } => {
+                    let session = match live_session(&sessions, &session_id) {
+                        Ok(session) => session,
+                        Err(e) => {
+                            let _ = response_tx.send(Err(e));
+                            continue;
+                        }
                     };
-                    let _ = response_tx.send(result);
+                    let cancel_rx = match register_running_command(&running_commands, &job_id) {
+                        Ok(cancel_rx) => cancel_rx,
+                        Err(e) => {
+                            let _ = response_tx.send(Err(e));
+                            continue;
+                        }
+                    };
+                    let handle = session.handle.clone();
+                    let limiter = session.limiter.clone();
+                    let running_commands = running_commands.clone();
+                    tokio::spawn(async move {
+                        let _permit = limiter.acquire_owned().await;
+                        let mut sink = OutputSink::new(job_id.clone(), window);
+                        let result = execute_command_async(&handle, &command, &options, Some(cancel_rx), Some(&mut sink)).await;
+                        running_commands.lock().unwrap().remove(&job_id);
+                        sink.finish(&result);
+                    });
+                    let _ = response_tx.send(Ok(()));
                 }
                 
-                WorkerCommand::DeleteSftpFile { session_id, path, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        delete_sftp_file_async(&session.handle, &path).await
-                    } else {
-                        Err(format!("Session not found: {}", session_id))
+                WorkerCommand::CancelCommand { cancel_id, response_tx } => {
+                    let result = match running_commands.lock().unwrap().remove(&cancel_id) {
+                        Some(cancel_tx) => {
+                            let _ = cancel_tx.send(());
+                            Ok(())
+                        }
+                        None => Err(format!("No running command with id: {}", cancel_id)),
                     };
                     let _ = response_tx.send(result);
                 }
                 
+                WorkerCommand::ListSftpFiles { session_id, path, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        list_sftp_files_async(&handle, &path).await
+                    });
+                }
+                
+                WorkerCommand::ReadSftpFile { session_id, path, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        read_sftp_file_async(&handle, &path).await
+                    });
+                }
+                
+                WorkerCommand::ReadSftpRange { session_id, path, request, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        ssh_file_range::read_range(&handle, &path, request).await
+                    });
+                }
+                
+                WorkerCommand::ReadSftpFileVersioned { session_id, path, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        read_sftp_file_versioned_async(&handle, &path).await
+                    });
+                }
+                
+                WorkerCommand::WriteSftpFile { session_id, path, content, options, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        write_sftp_file_async(&handle, &path, &content, &options).await
+                    });
+                }
+                
+                WorkerCommand::DeleteSftpFile { session_id, path, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        delete_sftp_file_async(&handle, &path).await
+                    });
+                }
+                
                 WorkerCommand::CreateSftpDirectory { session_id, path, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        create_sftp_directory_async(&session.handle, &path).await
-                    } else {
-                        Err(format!("Session not found: {}", session_id))
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        create_sftp_directory_async(&handle, &path).await
+                    });
+                }
+                
+                WorkerCommand::RenameSftpFile { session_id, old_path, new_path, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        rename_sftp_file_async(&handle, &old_path, &new_path).await
+                    });
+                }
+                
+                WorkerCommand::CreateLocalArchive { session_id, remote_paths, archive_path, format, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        ssh_archive::create_local_archive(&handle, &remote_paths, &archive_path, format).await
+                    });
+                }
+                
+                WorkerCommand::ExtractLocalArchive { session_id, archive_path, remote_dir, format, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        ssh_archive::extract_local_archive(&handle, &archive_path, &remote_dir, format).await
+                    });
+                }
+                
+                WorkerCommand::StatSftpFile { session_id, path, follow_links, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        stat_sftp_async(&handle, &path, follow_links).await
+                    });
+                }
+                
+                WorkerCommand::CreateSftpSymlink { session_id, target, link_path, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        create_sftp_symlink_async(&handle, &target, &link_path).await
+                    });
+                }
+                
+                WorkerCommand::SetSftpAttributes { session_id, path, change, recursive, response_tx } => {
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        set_sftp_attributes_async(&handle, &path, &change, recursive).await
+                    });
+                }
+                
+                // A remote forward waits for the server's answer while holding the handle's write
+                // lock, so forwards start on their own task and report back through `PortForwardStarted`
+                WorkerCommand::CreatePortForward { session_id, spec, response_tx } => {
+                    let session = match live_session(&sessions, &session_id) {
+                        Ok(session) => session,
+                        Err(e) => {
+                            let _ = response_tx.send(Err(e));
+                            continue;
+                        }
                     };
+                    let handle = session.handle.clone();
+                    let registry = session.remote_forwards.clone();
+                    let worker_tx = worker_tx.clone();
+                    tokio::spawn(async move {
+                        let forward_id = uuid::Uuid::new_v4().to_string();
+                        let result = ssh_port_forward::start_forward(handle, &registry, &forward_id, &session_id, &spec).await;
+                        let _ = worker_tx.send(WorkerCommand::PortForwardStarted { forward_id, result, response_tx });
+                    });
+                }
+                
+                WorkerCommand::PortForwardStarted { forward_id, result, response_tx } => {
+                    let result = result.and_then(|mut forward| {
+                        if !sessions.contains_key(forward.session_id()) {
+                            // Disconnected while the forward was starting
+                            forward.abandon();
+                            return Err(format!("Session not found: {}", forward.session_id()));
+                        }
+                        let info = forward.info();
+                        port_forwards.insert(forward_id, forward);
+                        Ok(info)
+                    });
                     let _ = response_tx.send(result);
                 }
                 
-                WorkerCommand::RenameSftpFile { session_id, old_path, new_path, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        rename_sftp_file_async(&session.handle, &old_path, &new_path).await
+                WorkerCommand::ListPortForwards { session_id, response_tx } => {
+                    let forwards = port_forwards
+                        .values()
+                        .filter(|f| session_id.as_deref().is_none_or(|id| f.session_id() == id))
+                        .map(|f| f.info())
+                        .collect();
+                    let _ = response_tx.send(forwards);
+                }
+                
+                WorkerCommand::ClosePortForward { forward_id, response_tx } => {
+                    let result = if let Some(forward) = port_forwards.remove(&forward_id) {
+                        if let Some(session) = sessions.get(forward.session_id()) {
+                            forward.stop(&session.handle, &session.remote_forwards).await;
+                        }
+                        Ok(())
                     } else {
-                        Err(format!("Session not found: {}", session_id))
+                        Err(format!("Port forward not found: {}", forward_id))
                     };
                     let _ = response_tx.send(result);
                 }
                 
                 WorkerCommand::Disconnect { session_id, response_tx } => {
-                    let result = if let Some(mut session) = sessions.remove(&session_id) {
-                        let _ = session.handle.disconnect(Disconnect::ByApplication, "User disconnected", "en").await;
+                    let result = if let Some(session) = sessions.remove(&session_id) {
+                        stop_port_forwards(&mut port_forwards, &session, &session_id).await;
+                        session.disconnect("User disconnected").await;
                         Ok(())
                     } else {
                         Err(format!("Session not found: {}", session_id))
@@ -681,8 +2449,9 @@ fn run_worker(rx: mpsc::Receiver<WorkerCommand>) {
                 }
                 
                 WorkerCommand::DisconnectAll { response_tx } => {
-                    for (_, mut session) in sessions.drain() {
-                        let _ = session.handle.disconnect(Disconnect::ByApplication, "User disconnected", "en").await;
+                    for (session_id, session) in sessions.drain() {
+                        stop_port_forwards(&mut port_forwards, &session, &session_id).await;
+                        session.disconnect("User disconnected").await;
                     }
                     let _ = response_tx.send(Ok(()));
                 }
@@ -698,187 +2467,214 @@ fn run_worker(rx: mpsc::Receiver<WorkerCommand>) {
                 }
                 
                 WorkerCommand::ListSessions { response_tx } => {
-                    let session_ids: Vec<String> = sessions.keys().cloned().collect();
-                    let _ = response_tx.send(session_ids);
+                    let mut infos: Vec<ConnectionInfo> = sessions.values().map(|s| s.info.clone()).collect();
+                    infos.sort_by_key(|info| info.connected_at);
+                    let _ = response_tx.send(infos);
+                }
+                
+                WorkerCommand::IsSessionInUse { session_id, response_tx } => {
+                    let in_use = port_forwards.values().any(|f| f.session_id() == session_id)
+                        || terminal_sessions.lock().await.values().any(|t| t.session_id == session_id);
+                    let _ = response_tx.send(in_use);
                 }
                 
                 // Terminal session commands
-                WorkerCommand::CreateTerminalSession { session_id, terminal_id, cols, rows, window, response_tx } => {
-                    let result = if let Some(session) = sessions.get(&session_id) {
-                        // Open a channel for the terminal
-                        match session.handle.channel_open_session().await {
-                            Ok(channel) => {
-                                // Request PTY
-                                let pty_result = channel.request_pty(
-                                    true,
-                                    "xterm-256color",
-                                    cols,
-                                    rows,
-                                    0, // pixel width
-                                    0, // pixel height
-                                    &[], // modes
-                                ).await;
-                                
-                                if let Err(e) = pty_result {
-                                    let _ = response_tx.send(Err(format!("Failed to request PTY: {}", e)));
-                                    continue;
-                                }
-                                
-                                // Request shell
-                                if let Err(e) = channel.request_shell(true).await {
-                                    let _ = response_tx.send(Err(format!("Failed to request shell: {}", e)));
-                                    continue;
-                                }
-                                
-                                // Create terminal session
-                                let terminal_session = TerminalSession {
-                                    channel,
-                                    session_id: session_id.clone(),
-                                    window: window.clone(),
-                                };
-                                
-                                // Store it
-                                let terminal_id_clone = terminal_id.clone();
-                                let mut terminals = terminal_sessions.lock().await;
-                                terminals.insert(terminal_id.clone(), terminal_session);
-                                drop(terminals);
-                                
-                                // Spawn a task to read output from the channel and emit to window
-                                let terminal_sessions_clone = terminal_sessions.clone();
-                                let window_clone = window.clone();
-                                
-                                tokio::spawn(async move {
-                                    loop {
-                                        let mut terminals = terminal_sessions_clone.lock().await;
-                                        if let Some(term) = terminals.get_mut(&terminal_id_clone) {
-                                            // Try to receive data from the channel
-                                            match tokio::time::timeout(
-                                                std::time::Duration::from_millis(50),
-                                                term.channel.wait()
-                                            ).await {
-                                                Ok(Some(msg)) => {
-                                                    match msg {
-                                                        ChannelMsg::Data { data } => {
-                                                            // Send data to frontend using the same format as ssh_manager.rs
-                                                            let output = String::from_utf8_lossy(&data).to_string();
-                                                            let _ = window_clone.emit(
-                                                                "ssh_terminal_data",
-                                                                serde_json::json!({"terminalId": terminal_id_clone, "data": output}),
-                                                            );
-                                                        }
-                                                        ChannelMsg::ExtendedData { data, ext } => {
-                                                            // stderr (ext == 1)
-                                                            if ext == 1 {
-                                                                let output = String::from_utf8_lossy(&data).to_string();
-                                                                let _ = window_clone.emit(
-                                                                    "ssh_terminal_data",
-                                                                    serde_json::json!({"terminalId": terminal_id_clone, "data": output}),
-                                                                );
-                                                            }
-                                                        }
-                                                        ChannelMsg::ExitStatus { exit_status: _ } => {
-                                                            let _ = window_clone.emit(
-                                                                "ssh_terminal_closed",
-                                                                serde_json::json!({"terminalId": terminal_id_clone}),
-                                                            );
-                                                            break;
-                                                        }
-                                                        ChannelMsg::Eof => {
-                                                            let _ = window_clone.emit(
-                                                                "ssh_terminal_closed",
-                                                                serde_json::json!({"terminalId": terminal_id_clone}),
-                                                            );
-                                                            break;
-                                                        }
-                                                        ChannelMsg::Close => {
-                                                            break;
-                                                        }
-                                                        _ => {}
-                                                    }
-                                                }
-                                                Ok(None) => {
-                                                    // Channel closed
-                                                    break;
-                                                }
-                                                Err(_) => {
-                                                    // Timeout - continue
-                                                }
-                                            }
-                                        } else {
-                                            // Terminal removed
-                                            break;
-                                        }
-                                        drop(terminals);
-                                        // Small yield to prevent busy loop
-                                        tokio::task::yield_now().await;
-                                    }
-                                    
-                                    // Clean up terminal session when done
-                                    let mut terminals = terminal_sessions_clone.lock().await;
-                                    terminals.remove(&terminal_id_clone);
-                                });
-                                
-                                Ok(())
+                WorkerCommand::CreateTerminalSession { session_id, terminal_id, cols, rows, output, window, response_tx } => {
+                    // Opening the PTY waits on the server, so it runs on a task like other requests
+                    let terminal_sessions = terminal_sessions.clone();
+                    let hooks = hooks.clone();
+                    let owner = session_id.clone();
+                    spawn_session_request(&sessions, &session_id, response_tx, move |handle| async move {
+                        let channel = open_pty_channel(&handle, cols, rows).await?;
+                        let (reader, channel, input) = split_terminal_channel(channel);
+                        let terminal_session = TerminalSession {
+                            channel,
+                            input,
+                            session_id: owner,
+                            window: window.clone(),
+                            cols,
+                            rows,
+                            generation: 0,
+                            detached: false,
+                            decoder: TerminalDecoder::new(output),
+                            zmodem_detector: ssh_zmodem::StartDetector::new(),
+                        };
+                        hooks.scrollback.register(&terminal_id);
+                        terminal_sessions.lock().await.insert(terminal_id.clone(), terminal_session);
+                        spawn_terminal_reader(terminal_sessions, terminal_id, 0, reader, window, hooks);
+                        Ok(())
+                    });
+                }
+                
+                WorkerCommand::SendTerminalInput { terminal_id, data, response_tx } => {
+                    // Keystrokes would corrupt a running rz/sz transfer; Ctrl+C cancels it instead
+                    if hooks.zmodem.is_active(&terminal_id) {
+                        if data.contains(&0x03) {
+                            hooks.zmodem.cancel(&terminal_id);
+                        }
+                        let _ = response_tx.send(Ok(()));
+                        continue;
+                    }
+                    hooks.recordings.input(&terminal_id, &data);
+                    let terminals = terminal_sessions.lock().await;
+                    match terminals.get(&terminal_id) {
+                        Some(term) if term.detached => {
+                            let _ = response_tx.send(Err(format!("Terminal is reconnecting: {}", terminal_id)));
+                        }
+                        Some(term) => {
+                            // The terminal's writer task answers once the data is written
+                            let data = term.decoder.encode_input(&data).into_owned();
+                            if let Err(tokio::sync::mpsc::error::SendError((_, response_tx))) = term.input.send((data, response_tx)) {
+                                let _ = response_tx.send(Err(format!("Terminal session not found: {}", terminal_id)));
                             }
-                            Err(e) => Err(format!("Failed to open channel: {}", e)),
                         }
-                    } else {
-                        Err(format!("Session not found: {}", session_id))
+                        None => {
+                            let _ = response_tx.send(Err(format!("Terminal session not found: {}", terminal_id)));
+                        }
+                    }
+                }
+                
+                WorkerCommand::CloseTerminalSession { terminal_id, response_tx } => {
+                    let removed = terminal_sessions.lock().await.remove(&terminal_id);
+                    match removed {
+                        Some(term) => {
+                            tokio::spawn(async move {
+                                close_terminal_channels(vec![term.channel]).await;
+                                let _ = response_tx.send(Ok(()));
+                            });
+                        }
+                        None => {
+                            let _ = response_tx.send(Ok(())); // Already closed
+                        }
+                    }
+                }
+                
+                WorkerCommand::CloseAllTerminalSessions { response_tx } => {
+                    let mut channels = Vec::new();
+                    for (terminal_id, term) in terminal_sessions.lock().await.drain() {
+                        hooks.scrollback.remove(&terminal_id);
+                        channels.push(term.channel);
+                    }
+                    tokio::spawn(async move {
+                        close_terminal_channels(channels).await;
+                        let _ = response_tx.send(Ok(()));
+                    });
+                }
+                
+                WorkerCommand::SetTerminalOutput { terminal_id, output, response_tx } => {
+                    let mut terminals = terminal_sessions.lock().await;
+                    let result = match terminals.get_mut(&terminal_id) {
+                        Some(term) => {
+                            term.decoder = TerminalDecoder::new(output);
+                            Ok(())
+                        }
+                        None => Err(format!("Terminal session not found: {}", terminal_id)),
                     };
+                    drop(terminals);
                     let _ = response_tx.send(result);
                 }
                 
-                WorkerCommand::SendTerminalInput { terminal_id, data, response_tx } => {
-                    let mut terminals = terminal_sessions.lock().await;
-                    let result = if let Some(term) = terminals.get_mut(&terminal_id) {
-                        term.channel.data(&data[..]).await
-                            .map_err(|e| format!("Failed to send data: {}", e))
-                    } else {
-                        Err(format!("Terminal session not found: {}", terminal_id))
+                WorkerCommand::StartTerminalRecording { terminal_id, options, response_tx } => {
+                    let terminals = terminal_sessions.lock().await;
+                    let result = match terminals.get(&terminal_id) {
+                        Some(term) => hooks.recordings.start(&terminal_id, term.cols, term.rows, options),
+                        None => Err(format!("Terminal session not found: {}", terminal_id)),
                     };
                     drop(terminals);
                     let _ = response_tx.send(result);
                 }
                 
-                WorkerCommand::CloseTerminalSession { terminal_id, response_tx } => {
+                WorkerCommand::ResizeTerminal { terminal_id, cols, rows, response_tx } => {
                     let mut terminals = terminal_sessions.lock().await;
-                    let result = if let Some(mut term) = terminals.remove(&terminal_id) {
-                        let _ = term.channel.eof().await;
-                        let _ = term.channel.close().await;
-                        Ok(())
-                    } else {
-                        Ok(()) // Already closed
+                    let channel = match terminals.get_mut(&terminal_id) {
+                        Some(term) => {
+                            // Remember the size so a re-created PTY matches the tab
+                            term.cols = cols;
+                            term.rows = rows;
+                            hooks.recordings.resize(&terminal_id, cols, rows);
+                            Ok((!term.detached).then(|| term.channel.clone()))
+                        }
+                        None => Err(format!("Terminal session not found: {}", terminal_id)),
                     };
                     drop(terminals);
-                    let _ = response_tx.send(result);
+                    match channel {
+                        Ok(Some(channel)) => {
+                            tokio::spawn(async move {
+                                let result = channel.window_change(cols, rows, 0, 0).await
+                                    .map_err(|e| format!("Failed to resize terminal: {}", e));
+                                let _ = response_tx.send(result);
+                            });
+                        }
+                        Ok(None) => {
+                            let _ = response_tx.send(Ok(()));
+                        }
+                        Err(e) => {
+                            let _ = response_tx.send(Err(e));
+                        }
+                    }
                 }
                 
-                WorkerCommand::CloseAllTerminalSessions { response_tx } => {
-                    let mut terminals = terminal_sessions.lock().await;
-                    for (_, mut term) in terminals.drain() {
-                        let _ = term.channel.eof().await;
-                        let _ = term.channel.close().await;
+                WorkerCommand::Reconnected { session_id, result } => {
+                    let session = match sessions.get_mut(&session_id) {
+                        // Ignore results for sessions that were disconnected in the meantime
+                        Some(session) if session.reconnecting => session,
+                        _ => {
+                            if let Ok((handle, jump_handles)) = result {
+                                let _ = handle.disconnect(Disconnect::ByApplication, "Session closed", "en").await;
+                                for jump in jump_handles.into_iter().rev() {
+                                    let _ = jump.disconnect(Disconnect::ByApplication, "Session closed", "en").await;
+                                }
+                            }
+                            continue;
+                        }
+                    };
+                    match result {
+                        Ok((handle, jump_handles)) => {
+                            // Swapping the handle waits for requests still opening channels on the
+                            // old one and restoring remote forwards waits on the server, so both run
+                            // on a task; the session stays `reconnecting` until `Resumed`
+                            let old_jumps = std::mem::replace(&mut session.jump_handles, jump_handles);
+                            let binds = remote_forward_binds(&port_forwards, &session_id);
+                            let shared = session.handle.clone();
+                            let worker_tx = worker_tx.clone();
+                            tokio::spawn(async move {
+                                let old = std::mem::replace(&mut *shared.write().await, handle);
+                                drop(old);
+                                for jump in old_jumps.into_iter().rev() {
+                                    let _ = jump.disconnect(Disconnect::ByApplication, "Reconnected", "en").await;
+                                }
+                                restore_remote_forwards(&shared, &binds).await;
+                                let _ = worker_tx.send(WorkerCommand::Resumed { session_id, handle: shared });
+                            });
+                        }
+                        Err(_) => {
+                            if let Some(session) = sessions.remove(&session_id) {
+                                stop_port_forwards(&mut port_forwards, &session, &session_id).await;
+                                session.disconnect("Reconnect failed").await;
+                            }
+                            close_session_terminals(&terminal_sessions, &session_id).await;
+                        }
                     }
-                    drop(terminals);
-                    let _ = response_tx.send(Ok(()));
                 }
                 
-                WorkerCommand::ResizeTerminal { terminal_id, cols, rows, response_tx } => {
-                    let terminals = terminal_sessions.lock().await;
-                    let result = if let Some(term) = terminals.get(&terminal_id) {
-                        term.channel.window_change(cols, rows, 0, 0).await
-                            .map_err(|e| format!("Failed to resize terminal: {}", e))
-                    } else {
-                        Err(format!("Terminal session not found: {}", terminal_id))
-                    };
-                    drop(terminals);
-                    let _ = response_tx.send(result);
+                WorkerCommand::Resumed { session_id, handle } => {
+                    match sessions.get_mut(&session_id) {
+                        Some(session) => {
+                            session.reconnecting = false;
+                            resume_terminals(&terminal_sessions, session, &session_id, true, &hooks);
+                        }
+                        // Disconnected while the handle was being swapped
+                        None => {
+                            let _ = handle.read().await.disconnect(Disconnect::ByApplication, "Session closed", "en").await;
+                        }
+                    }
                 }
                 
                 WorkerCommand::Shutdown => {
                     // Disconnect all sessions before shutdown
-                    for (_, mut session) in sessions.drain() {
-                        let _ = session.handle.disconnect(Disconnect::ByApplication, "Shutdown", "en").await;
+                    for (session_id, session) in sessions.drain() {
+                        stop_port_forwards(&mut port_forwards, &session, &session_id).await;
+                        session.disconnect("Shutdown").await;
                     }
                     break;
                 }
@@ -889,25 +2685,78 @@ fn run_worker(rx: mpsc::Receiver<WorkerCommand>) {
 
 // ================== Main SSHManager Struct ==================
 
+/// Shuts the worker thread down once the last manager clone is dropped
+struct WorkerThread {
+    worker_tx: mpsc::Sender<WorkerCommand>,
+    _handle: thread::JoinHandle<()>,
+}
+
+impl Drop for WorkerThread {
+    fn drop(&mut self) {
+        // Send shutdown command to worker thread
+        let _ = self.worker_tx.send(WorkerCommand::Shutdown);
+    }
+}
+
+/// Cheap to clone: clones talk to the same worker thread. Tauri commands clone it out of
+/// `AppState` so a long-running request does not hold the state lock for everyone else.
+#[derive(Clone)]
 pub struct SSHManagerRussh {
     worker_tx: mpsc::Sender<WorkerCommand>,
-    _worker_handle: thread::JoinHandle<()>,
+    _worker: Arc<WorkerThread>,
     // Track current active session for backward compatibility
     current_session: Arc<Mutex<Option<String>>>,
+    max_retries: Arc<AtomicU32>,
+    transfers: TransferQueue,
+    watches: WatchRegistry,
+    zmodem: ZmodemHub,
+    recordings: RecordingRegistry,
+    terminal_groups: TerminalGroups,
+    scrollback: ScrollbackRegistry,
+    local_terminals: LocalTerminals,
 }
 
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
 impl SSHManagerRussh {
     pub fn new() -> Self {
         let (tx, rx) = mpsc::channel();
-        let handle = thread::spawn(move || run_worker(rx));
+        let max_retries = Arc::new(AtomicU32::new(DEFAULT_MAX_RETRIES));
+        let worker_tx = tx.clone();
+        let worker_max_retries = max_retries.clone();
+        let transfers = TransferQueue::new();
+        let hooks = TerminalHooks {
+            zmodem: ZmodemHub::new(transfers.clone()),
+            recordings: RecordingRegistry::new(),
+            groups: TerminalGroups::new(),
+            scrollback: ScrollbackRegistry::new(),
+        };
+        let worker_hooks = hooks.clone();
+        let handle = thread::spawn(move || run_worker(rx, worker_tx, worker_max_retries, worker_hooks));
         
         Self {
-            worker_tx: tx,
-            _worker_handle: handle,
+            worker_tx: tx.clone(),
+            _worker: Arc::new(WorkerThread {
+                worker_tx: tx,
+                _handle: handle,
+            }),
             current_session: Arc::new(Mutex::new(None)),
+            max_retries,
+            transfers,
+            watches: WatchRegistry::new(),
+            local_terminals: LocalTerminals::new(hooks.recordings.clone(), hooks.groups.clone(), hooks.scrollback.clone()),
+            zmodem: hooks.zmodem,
+            recordings: hooks.recordings,
+            terminal_groups: hooks.groups,
+            scrollback: hooks.scrollback,
         }
     }
     
+    /// Number of reconnect attempts made after a session drops (SSHSettings.max_retries); 0 disables auto-reconnect
+    pub fn set_max_retries(&self, max_retries: u32) {
+        self.max_retries.store(max_retries, Ordering::Relaxed);
+    }
+    
     fn get_current_session(&self) -> Result<String, String> {
         self.current_session
             .lock()
@@ -932,6 +2781,50 @@ impl SSHManagerRussh {
         username: &str,
         password: Option<&str>,
         private_key: Option<&str>,
+    ) -> Result<String, String> {
+        let credentials = SSHCredentials {
+            password: password.map(|s| s.to_string()),
+            private_key: private_key.map(|s| s.to_string()),
+            ..Default::default()
+        };
+        self.connect_via(host, port, username, credentials, Vec::new())
+    }
+    
+    /// Connect to SSH server through an ordered chain of jump hosts (sets as current session).
+    /// The previous current session is disconnected once the new one is up, unless it
+    /// still has terminals, port forwards or watches; use `open_session` to keep
+    /// several sessions open side by side.
+    pub fn connect_via(
+        &self,
+        host: &str,
+        port: u16,
+        username: &str,
+        credentials: SSHCredentials,
+        jump_hosts: Vec<JumpHostConfig>,
+    ) -> Result<String, String> {
+        let result = self.open_session(host, port, username, credentials, jump_hosts)?;
+        
+        let previous = self.current_session.lock().ok().and_then(|g| g.clone());
+        // Set as current session
+        self.set_current_session(Some(result.clone()));
+        
+        if let Some(previous) = idle_previous_session(previous, &result, |id| self.is_session_in_use(id)) {
+            // The session may already be gone (closed by the server or the user)
+            let _ = self.disconnect_session(&previous);
+        }
+        
+        Ok(result)
+    }
+    
+    /// Open an independent session without touching the current one; every call gets
+    /// its own session id, even for a target that is already connected
+    pub fn open_session(
+        &self,
+        host: &str,
+        port: u16,
+        username: &str,
+        credentials: SSHCredentials,
+        jump_hosts: Vec<JumpHostConfig>,
     ) -> Result<String, String> {
         let (response_tx, response_rx) = mpsc::channel();
         
@@ -940,36 +2833,120 @@ impl SSHManagerRussh {
                 host: host.to_string(),
                 port,
                 username: username.to_string(),
-                password: password.map(|s| s.to_string()),
-                private_key: private_key.map(|s| s.to_string()),
+                credentials,
+                jump_hosts,
                 response_tx,
             })
             .map_err(|_| "Worker thread has shut down".to_string())?;
         
-        let result = response_rx
+        response_rx
             .recv()
-            .map_err(|_| "Failed to receive response from worker".to_string())??;
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// Fetch the server host key and compare it with known_hosts, without authenticating
+    pub fn scan_host_key(&self, host: &str, port: u16) -> Result<HostKeyInfo, String> {
+        let (response_tx, response_rx) = mpsc::channel();
         
-        // Set as current session
-        self.set_current_session(Some(result.clone()));
+        self.worker_tx
+            .send(WorkerCommand::ScanHostKey {
+                host: host.to_string(),
+                port,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
         
-        Ok(result)
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
     }
     
     /// Execute command on current session (backward compatible)
     pub fn execute_command(&self, command: &str) -> Result<TerminalOutput, String> {
+        self.execute_command_with_options(command, ExecOptions::unlimited())
+    }
+    
+    /// Execute command on current session with timeout/cancellation/output limits
+    pub fn execute_command_with_options(&self, command: &str, options: ExecOptions) -> Result<TerminalOutput, String> {
         let session_id = self.get_current_session()?;
-        self.execute_command_on_session(&session_id, command)
+        self.execute_command_on_session(&session_id, command, options)
     }
     
     /// Execute command on specific session
-    pub fn execute_command_on_session(&self, session_id: &str, command: &str) -> Result<TerminalOutput, String> {
+    pub fn execute_command_on_session(
+        &self,
+        session_id: &str,
+        command: &str,
+        options: ExecOptions,
+    ) -> Result<TerminalOutput, String> {
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
             .send(WorkerCommand::ExecuteCommand {
                 session_id: session_id.to_string(),
                 command: command.to_string(),
+                options,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// Start a command on the current session and stream its output to `window` as
+    /// `ssh_command_output` / `ssh_command_finished` events. Returns the job id, which is
+    /// `options.cancel_id` when set and also what `cancel_command` takes.
+    pub fn execute_command_streaming(
+        &self,
+        command: &str,
+        options: ExecOptions,
+        window: tauri::Window,
+    ) -> Result<String, String> {
+        let session_id = self.get_current_session()?;
+        self.execute_command_streaming_on_session(&session_id, command, options, window)
+    }
+    
+    /// Streaming execution on a specific session
+    pub fn execute_command_streaming_on_session(
+        &self,
+        session_id: &str,
+        command: &str,
+        options: ExecOptions,
+        window: tauri::Window,
+    ) -> Result<String, String> {
+        let job_id = options
+            .cancel_id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::StreamCommand {
+                session_id: session_id.to_string(),
+                command: command.to_string(),
+                options,
+                job_id: job_id.clone(),
+                window,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())??;
+        Ok(job_id)
+    }
+    
+    /// Cancel a running command by its ExecOptions::cancel_id; the remote process is
+    /// signalled and its channel closed
+    pub fn cancel_command(&self, cancel_id: &str) -> Result<(), String> {
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::CancelCommand {
+                cancel_id: cancel_id.to_string(),
                 response_tx,
             })
             .map_err(|_| "Worker thread has shut down".to_string())?;
@@ -1027,14 +3004,87 @@ impl SSHManagerRussh {
             .map_err(|_| "Failed to receive response from worker".to_string())?
     }
     
+    /// Read part of a remote file without fetching the rest of it
+    pub fn read_sftp_range(&self, path: &str, offset: u64, length: u64) -> Result<FileRange, String> {
+        self.request_sftp_range(path, RangeRequest::Offset { offset, length })
+    }
+    
+    /// Read the last `bytes` bytes of a remote file
+    pub fn tail_sftp_file(&self, path: &str, bytes: u64) -> Result<FileRange, String> {
+        self.request_sftp_range(path, RangeRequest::Tail { bytes })
+    }
+    
+    fn request_sftp_range(&self, path: &str, request: RangeRequest) -> Result<FileRange, String> {
+        let session_id = self.get_current_session()?;
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::ReadSftpRange {
+                session_id,
+                path: path.to_string(),
+                request,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// Byte offset of every `interval`-th line, computed on the server so the file never
+    /// leaves it; combine with `read_sftp_range` to jump to a line
+    pub fn build_line_index(&self, path: &str, interval: u64) -> Result<LineIndex, String> {
+        let command = ssh_file_range::line_index_command(path, interval);
+        // Scanning a multi-GB log takes a while and produces one short line per interval
+        let options = ExecOptions { timeout_ms: Some(30 * 60 * 1000), ..Default::default() };
+        let output = self.execute_command_with_options(&command, options)?;
+        if output.exit_code.unwrap_or(0) != 0 {
+            return Err(format!("Failed to index lines: {}", output.stderr.trim()));
+        }
+        ssh_file_range::parse_line_index(path, interval, &output.stdout)
+    }
+    
     /// Write file on current session
     pub fn write_sftp_file(&self, path: &str, content: &[u8]) -> Result<(), String> {
         let session_id = self.get_current_session()?;
-        self.write_sftp_file_on_session(&session_id, path, content)
+        self.write_sftp_file_on_session(&session_id, path, content, WriteOptions::default())
+            .map(|_| ())
+    }
+    
+    /// Write file on current session, refusing to overwrite changes made since
+    /// `options.expected_version` was read; returns the new version
+    pub fn write_sftp_file_with_options(&self, path: &str, content: &[u8], options: WriteOptions) -> Result<FileVersion, String> {
+        let session_id = self.get_current_session()?;
+        self.write_sftp_file_on_session(&session_id, path, content, options)
+    }
+    
+    /// Read a whole file together with the version token needed for a safe write
+    pub fn read_sftp_file_versioned(&self, path: &str) -> Result<VersionedFile, String> {
+        let session_id = self.get_current_session()?;
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::ReadSftpFileVersioned {
+                session_id,
+                path: path.to_string(),
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
     }
     
     /// Write file on specific session
-    pub fn write_sftp_file_on_session(&self, session_id: &str, path: &str, content: &[u8]) -> Result<(), String> {
+    pub fn write_sftp_file_on_session(
+        &self,
+        session_id: &str,
+        path: &str,
+        content: &[u8],
+        options: WriteOptions,
+    ) -> Result<FileVersion, String> {
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
@@ -1042,6 +3092,7 @@ impl SSHManagerRussh {
                 session_id: session_id.to_string(),
                 path: path.to_string(),
                 content: content.to_vec(),
+                options,
                 response_tx,
             })
             .map_err(|_| "Worker thread has shut down".to_string())?;
@@ -1092,26 +3143,136 @@ impl SSHManagerRussh {
             })
             .map_err(|_| "Worker thread has shut down".to_string())?;
         
-        response_rx
-            .recv()
-            .map_err(|_| "Failed to receive response from worker".to_string())?
-    }
-    
-    /// Rename file on current session
-    pub fn rename_sftp_file(&self, old_path: &str, new_path: &str) -> Result<(), String> {
-        let session_id = self.get_current_session()?;
-        self.rename_sftp_file_on_session(&session_id, old_path, new_path)
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// Rename file on current session
+    pub fn rename_sftp_file(&self, old_path: &str, new_path: &str) -> Result<(), String> {
+        let session_id = self.get_current_session()?;
+        self.rename_sftp_file_on_session(&session_id, old_path, new_path)
+    }
+    
+    /// Rename file on specific session
+    pub fn rename_sftp_file_on_session(&self, session_id: &str, old_path: &str, new_path: &str) -> Result<(), String> {
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::RenameSftpFile {
+                session_id: session_id.to_string(),
+                old_path: old_path.to_string(),
+                new_path: new_path.to_string(),
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// lstat a remote path, or stat it when `follow_links` is set
+    pub fn stat_sftp(&self, path: &str, follow_links: bool) -> Result<SftpFileDetails, String> {
+        let session_id = self.get_current_session()?;
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::StatSftpFile {
+                session_id,
+                path: path.to_string(),
+                follow_links,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// Create a symlink at `link_path` pointing to `target`
+    pub fn create_sftp_symlink(&self, target: &str, link_path: &str) -> Result<(), String> {
+        let session_id = self.get_current_session()?;
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::CreateSftpSymlink {
+                session_id,
+                target: target.to_string(),
+                link_path: link_path.to_string(),
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// Change mode, owner and/or timestamps; returns how many paths were changed
+    pub fn set_sftp_attributes(&self, path: &str, change: SftpAttributeChange, recursive: bool) -> Result<usize, String> {
+        let session_id = self.get_current_session()?;
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::SetSftpAttributes {
+                session_id,
+                path: path.to_string(),
+                change,
+                recursive,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    // ================== Port Forwarding ==================
+    
+    /// Start a local (-L), remote (-R) or dynamic SOCKS5 (-D) forward on a session
+    pub fn create_port_forward(&self, session_id: &str, spec: PortForwardSpec) -> Result<PortForwardInfo, String> {
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::CreatePortForward {
+                session_id: session_id.to_string(),
+                spec,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// List port forwards, optionally only those of one session
+    pub fn list_port_forwards(&self, session_id: Option<&str>) -> Vec<PortForwardInfo> {
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        if self.worker_tx
+            .send(WorkerCommand::ListPortForwards {
+                session_id: session_id.map(|s| s.to_string()),
+                response_tx,
+            })
+            .is_err()
+        {
+            return Vec::new();
+        }
+        
+        response_rx.recv().unwrap_or_default()
     }
     
-    /// Rename file on specific session
-    pub fn rename_sftp_file_on_session(&self, session_id: &str, old_path: &str, new_path: &str) -> Result<(), String> {
+    /// Stop a port forward
+    pub fn close_port_forward(&self, forward_id: &str) -> Result<(), String> {
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
-            .send(WorkerCommand::RenameSftpFile {
-                session_id: session_id.to_string(),
-                old_path: old_path.to_string(),
-                new_path: new_path.to_string(),
+            .send(WorkerCommand::ClosePortForward {
+                forward_id: forward_id.to_string(),
                 response_tx,
             })
             .map_err(|_| "Worker thread has shut down".to_string())?;
@@ -1134,6 +3295,7 @@ impl SSHManagerRussh {
     
     /// Disconnect specific session
     pub fn disconnect_session(&self, session_id: &str) -> Result<(), String> {
+        self.watches.stop_session(session_id);
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
@@ -1160,6 +3322,7 @@ impl SSHManagerRussh {
     
     /// Disconnect all sessions
     pub fn disconnect_all(&self) -> Result<(), String> {
+        self.watches.stop_all();
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
@@ -1201,6 +3364,28 @@ impl SSHManagerRussh {
         response_rx.recv().unwrap_or(false)
     }
     
+    /// Whether the session still carries terminals, port forwards or watches
+    fn is_session_in_use(&self, session_id: &str) -> bool {
+        if !self.watches.list(Some(session_id)).is_empty() {
+            return true;
+        }
+        
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        if self.worker_tx
+            .send(WorkerCommand::IsSessionInUse {
+                session_id: session_id.to_string(),
+                response_tx,
+            })
+            .is_err()
+        {
+            return false;
+        }
+        
+        // Keep the session when the worker cannot tell
+        response_rx.recv().unwrap_or(true)
+    }
+    
     /// Get connection info for current session
     pub fn get_connection_info(&self) -> Option<ConnectionInfo> {
         let session_id = self.current_session.lock().ok()?.clone()?;
@@ -1226,6 +3411,14 @@ impl SSHManagerRussh {
     
     /// List all active sessions
     pub fn list_sessions(&self) -> Vec<String> {
+        self.list_session_infos()
+            .into_iter()
+            .map(|info| info.session_id)
+            .collect()
+    }
+    
+    /// List open sessions with their target and connection details, oldest first
+    pub fn list_session_infos(&self) -> Vec<ConnectionInfo> {
         let (response_tx, response_rx) = mpsc::channel();
         
         if self.worker_tx
@@ -1264,6 +3457,16 @@ impl SSHManagerRussh {
     
     /// Execute dashboard command as specific user
     pub fn execute_dashboard_command_as_user(&self, command: &str, username: Option<&str>) -> Result<TerminalOutput, String> {
+        self.execute_command_as_user_with_options(command, username, ExecOptions::unlimited())
+    }
+    
+    /// Execute command as specific user with timeout/cancellation/output limits
+    pub fn execute_command_as_user_with_options(
+        &self,
+        command: &str,
+        username: Option<&str>,
+        options: ExecOptions,
+    ) -> Result<TerminalOutput, String> {
         let final_command = if let Some(user) = username {
             // Use sudo -u to switch user for command execution
             // Use su -c as fallback if sudo is not available
@@ -1278,7 +3481,7 @@ impl SSHManagerRussh {
             command.to_string()
         };
         
-        self.execute_command(&final_command)
+        self.execute_command_with_options(&final_command, options)
     }
     
     /// Get connection status (backward compatibility)
@@ -1296,88 +3499,29 @@ impl SSHManagerRussh {
         }
     }
     
-    /// Get file details via SFTP
-    pub fn get_file_details(&self, path: &str) -> Result<SftpFileDetails, String> {
-        // Execute stat command to get file details
+    /// Get file details via SFTP. Without `follow_links` a symlink describes itself (lstat)
+    pub fn get_file_details(&self, path: &str, follow_links: bool) -> Result<SftpFileDetails, String> {
+        let mut details = self.stat_sftp(path, follow_links)?;
+        
+        // SFTP v3 only carries numeric ids and no birth time; take them from stat(1) when available
         let stat_cmd = format!(
-            "stat -c '%n|%F|%s|%a|%U|%G|%W|%Y|%X' '{}' 2>/dev/null || ls -la '{}'",
-            path.replace("'", "'\\''"),
+            "stat {}-c '%U|%G|%W' '{}' 2>/dev/null",
+            if follow_links { "-L " } else { "" },
             path.replace("'", "'\\''")
         );
-        
-        let output = self.execute_command(&stat_cmd)?;
-        let output_str = output.output.trim();
-        
-        // Try to parse stat output
-        let parts: Vec<&str> = output_str.split('|').collect();
-        if parts.len() >= 9 {
-            let name = std::path::Path::new(parts[0])
-                .file_name()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_else(|| parts[0].to_string());
-            
-            let file_type = match parts[1] {
-                "regular file" => "file",
-                "regular empty file" => "file",
-                "directory" => "directory",
-                "symbolic link" => "symlink",
-                "block special file" => "block",
-                "character special file" => "char",
-                "socket" => "socket",
-                "FIFO" => "fifo",
-                _ => "unknown",
-            };
-            
-            let size: u64 = parts[2].parse().unwrap_or(0);
-            let permissions = parts[3].to_string();
-            let owner = Some(parts[4].to_string());
-            let group = Some(parts[5].to_string());
-            
-            let created = parts[6].parse::<i64>().ok()
-                .filter(|&t| t > 0)
-                .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
-                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
-            
-            let modified = parts[7].parse::<i64>().ok()
-                .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
-                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
-            
-            let accessed = parts[8].parse::<i64>().ok()
-                .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
-                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
-            
-            Ok(SftpFileDetails {
-                name,
-                path: path.to_string(),
-                file_type: file_type.to_string(),
-                size,
-                permissions,
-                owner,
-                group,
-                created,
-                modified,
-                accessed,
-            })
-        } else {
-            // Fallback: parse ls -la output
-            let name = std::path::Path::new(path)
-                .file_name()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_else(|| path.to_string());
-            
-            Ok(SftpFileDetails {
-                name,
-                path: path.to_string(),
-                file_type: "unknown".to_string(),
-                size: 0,
-                permissions: "unknown".to_string(),
-                owner: None,
-                group: None,
-                created: None,
-                modified: None,
-                accessed: None,
-            })
+        if let Ok(output) = self.execute_command(&stat_cmd) {
+            let parts: Vec<&str> = output.stdout.trim().split('|').collect();
+            if parts.len() == 3 {
+                details.owner = Some(parts[0].to_string());
+                details.group = Some(parts[1].to_string());
+                details.created = parts[2].parse::<i64>().ok()
+                    .filter(|&t| t > 0)
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
+            }
         }
+        
+        Ok(details)
     }
     
     // ================== Additional Methods for Backward Compatibility ==================
@@ -1389,6 +3533,7 @@ impl SSHManagerRussh {
         terminal_id: &str,
         cols: u32,
         rows: u32,
+        output: TerminalOutputOptions,
     ) -> Result<(), String> {
         let session_id = self.get_current_session()?;
         
@@ -1400,6 +3545,7 @@ impl SSHManagerRussh {
                 terminal_id: terminal_id.to_string(),
                 cols,
                 rows,
+                output,
                 window,
                 response_tx,
             })
@@ -1410,8 +3556,28 @@ impl SSHManagerRussh {
             .map_err(|_| "Timeout waiting for terminal session creation".to_string())?
     }
     
+    /// Start a shell on this machine in a PTY. It shares terminal ids, events and the
+    /// terminal commands below with SSH terminals and needs no connection.
+    pub fn create_local_terminal_session(
+        &self,
+        window: tauri::Window,
+        terminal_id: &str,
+        cols: u32,
+        rows: u32,
+        output: TerminalOutputOptions,
+        options: LocalShellOptions,
+    ) -> Result<(), String> {
+        self.local_terminals.create(window, terminal_id, cols, rows, output, options)
+    }
+    
     /// Close terminal session
     pub fn close_terminal_session(&self, terminal_id: &str) -> Result<(), String> {
+        // Watches opened from this terminal's tab go away with it
+        self.watches.stop_owner(terminal_id);
+        self.scrollback.remove(terminal_id);
+        if self.local_terminals.contains(terminal_id) {
+            return self.local_terminals.close(terminal_id);
+        }
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
@@ -1426,8 +3592,11 @@ impl SSHManagerRussh {
             .map_err(|_| "Timeout waiting for terminal close".to_string())?
     }
     
-    /// Close all terminal sessions
+    /// Close all terminal sessions, SSH terminals and local shells alike
     pub fn close_all_terminal_sessions(&self) -> Result<(), String> {
+        for terminal_id in self.local_terminals.ids() {
+            self.close_terminal_session(&terminal_id)?;
+        }
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
@@ -1441,6 +3610,9 @@ impl SSHManagerRussh {
     
     /// Send input to terminal
     pub fn send_terminal_input(&self, terminal_id: &str, data: Vec<u8>) -> Result<(), String> {
+        if self.local_terminals.contains(terminal_id) {
+            return self.local_terminals.send_input(terminal_id, &data);
+        }
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
@@ -1456,8 +3628,62 @@ impl SSHManagerRussh {
             .map_err(|_| "Timeout waiting for terminal input send".to_string())?
     }
     
+    pub fn create_terminal_group(&self, name: &str, terminal_ids: Vec<String>) -> TerminalGroupInfo {
+        self.terminal_groups.create(name, terminal_ids)
+    }
+    
+    pub fn delete_terminal_group(&self, group_id: &str) -> Result<(), String> {
+        self.terminal_groups.delete(group_id)
+    }
+    
+    pub fn list_terminal_groups(&self) -> Vec<TerminalGroupInfo> {
+        self.terminal_groups.list()
+    }
+    
+    pub fn add_terminal_group_member(&self, group_id: &str, terminal_id: &str) -> Result<TerminalGroupInfo, String> {
+        self.terminal_groups.add_member(group_id, terminal_id)
+    }
+    
+    pub fn remove_terminal_group_member(&self, group_id: &str, terminal_id: &str) -> Result<TerminalGroupInfo, String> {
+        self.terminal_groups.remove_member(group_id, terminal_id)
+    }
+    
+    pub fn set_terminal_group_member_enabled(&self, group_id: &str, terminal_id: &str, enabled: bool) -> Result<TerminalGroupInfo, String> {
+        self.terminal_groups.set_member_enabled(group_id, terminal_id, enabled)
+    }
+    
+    /// Send the same input to every enabled terminal of a group. A member that fails
+    /// (reconnecting, closed) is reported and does not stop the others.
+    pub fn broadcast_terminal_input(&self, group_id: &str, data: &[u8]) -> Result<BroadcastResult, String> {
+        let targets = self.terminal_groups.targets(group_id)?;
+        // Send to all members at once so one slow or stuck member doesn't delay the rest
+        // by its whole timeout; results are collected in member order
+        let outcomes: Vec<Result<(), String>> = thread::scope(|scope| {
+            let sends: Vec<_> = targets
+                .iter()
+                .map(|terminal_id| scope.spawn(move || self.send_terminal_input(terminal_id, data.to_vec())))
+                .collect();
+            sends
+                .into_iter()
+                .map(|send| send.join().unwrap_or_else(|_| Err("Input send panicked".to_string())))
+                .collect()
+        });
+        
+        let mut result = BroadcastResult::default();
+        for (terminal_id, outcome) in targets.into_iter().zip(outcomes) {
+            match outcome {
+                Ok(()) => result.sent.push(terminal_id),
+                Err(error) => result.failed.push(BroadcastFailure { terminal_id, error }),
+            }
+        }
+        Ok(result)
+    }
+    
     /// Resize terminal
     pub fn resize_terminal(&self, terminal_id: &str, cols: u32, rows: u32) -> Result<(), String> {
+        if self.local_terminals.contains(terminal_id) {
+            return self.local_terminals.resize(terminal_id, cols, rows);
+        }
         let (response_tx, response_rx) = mpsc::channel();
         
         self.worker_tx
@@ -1474,11 +3700,104 @@ impl SSHManagerRussh {
             .map_err(|_| "Timeout waiting for terminal resize".to_string())?
     }
     
-    /// Change file permissions
-    pub fn chmod_sftp(&self, path: &str, mode: u32) -> Result<(), String> {
-        let cmd = format!("chmod {:o} '{}'", mode, path.replace("'", "'\\''"));
-        self.execute_command(&cmd)?;
-        Ok(())
+    /// Change the remote encoding of a terminal or switch it to raw base64 output
+    pub fn set_terminal_output(&self, terminal_id: &str, output: TerminalOutputOptions) -> Result<(), String> {
+        if self.local_terminals.contains(terminal_id) {
+            return self.local_terminals.set_output(terminal_id, output);
+        }
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::SetTerminalOutput {
+                terminal_id: terminal_id.to_string(),
+                output,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .map_err(|_| "Timeout waiting for terminal update".to_string())?
+    }
+    
+    /// Start recording a terminal to an asciinema v2 file under the app data dir
+    pub fn start_terminal_recording(&self, terminal_id: &str, options: RecordingOptions) -> Result<RecordingInfo, String> {
+        if self.local_terminals.contains(terminal_id) {
+            return self.local_terminals.start_recording(terminal_id, options);
+        }
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::StartTerminalRecording {
+                terminal_id: terminal_id.to_string(),
+                options,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    pub fn stop_terminal_recording(&self, terminal_id: &str) -> Result<RecordingInfo, String> {
+        self.recordings.stop(terminal_id)
+    }
+    
+    pub fn list_terminal_recordings(&self) -> Result<Vec<RecordingInfo>, String> {
+        self.recordings.list()
+    }
+    
+    /// Replay a recording to the window as `ssh_recording_data` events, returns the stream id
+    pub fn stream_terminal_recording(&self, recording_id: &str, window: tauri::Window) -> Result<String, String> {
+        self.recordings.stream(recording_id, window)
+    }
+    
+    pub fn scrollback_info(&self, terminal_id: &str) -> Result<ScrollbackInfo, String> {
+        self.scrollback.info(terminal_id)
+    }
+    
+    /// Regex search over a terminal's scrollback with ANSI sequences stripped
+    pub fn search_scrollback(&self, terminal_id: &str, pattern: &str, options: ScrollbackSearchOptions) -> Result<ScrollbackSearchResult, String> {
+        self.scrollback.search(terminal_id, pattern, options)
+    }
+    
+    /// Lines `start..start + count` of a terminal's scrollback, e.g. to repaint after a window reload
+    pub fn scrollback_range(&self, terminal_id: &str, start: u64, count: usize, strip_ansi: bool) -> Result<ScrollbackRange, String> {
+        self.scrollback.range(terminal_id, start, count, strip_ansi)
+    }
+    
+    /// Write a terminal's scrollback to a plain text file, returns the number of lines
+    pub fn export_scrollback(&self, terminal_id: &str, path: &std::path::Path) -> Result<usize, String> {
+        self.scrollback.export(terminal_id, path)
+    }
+    
+    /// Answer the `ssh_zmodem_request` event of a terminal that started rz/sz
+    pub fn zmodem_respond(&self, terminal_id: &str, answer: ZmodemAnswer) -> Result<(), String> {
+        self.zmodem.respond(terminal_id, answer)
+    }
+    
+    /// Cancel the rz/sz transfer running in a terminal, returns false if there is none
+    pub fn zmodem_cancel(&self, terminal_id: &str) -> bool {
+        self.zmodem.cancel(terminal_id)
+    }
+    
+    /// Change file permissions, optionally for everything below a directory
+    pub fn chmod_sftp(&self, path: &str, mode: u32, recursive: bool) -> Result<usize, String> {
+        let change = SftpAttributeChange { mode: Some(mode), ..Default::default() };
+        self.set_sftp_attributes(path, change, recursive)
+    }
+    
+    /// Change numeric owner and/or group
+    pub fn chown_sftp(&self, path: &str, uid: Option<u32>, gid: Option<u32>, recursive: bool) -> Result<usize, String> {
+        let change = SftpAttributeChange { uid, gid, ..Default::default() };
+        self.set_sftp_attributes(path, change, recursive)
+    }
+    
+    /// Set access and/or modification time (unix seconds)
+    pub fn set_sftp_times(&self, path: &str, atime: Option<u32>, mtime: Option<u32>) -> Result<(), String> {
+        let change = SftpAttributeChange { atime, mtime, ..Default::default() };
+        self.set_sftp_attributes(path, change, false).map(|_| ())
     }
     
     /// Get bash environment info
@@ -1626,26 +3945,234 @@ echo "PATH=$PATH""#;
         Ok(())
     }
     
+    /// Stream remote files and directories into a local archive without running
+    /// anything on the remote host
+    pub fn archive_to_local(&self, remote_paths: &[String], archive_path: &str, format: ArchiveFormat) -> Result<ArchiveSummary, String> {
+        let session_id = self.get_current_session()?;
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::CreateLocalArchive {
+                session_id,
+                remote_paths: remote_paths.to_vec(),
+                archive_path: archive_path.to_string(),
+                format,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    /// Unpack a local archive into a remote directory over SFTP
+    pub fn extract_local_archive(&self, archive_path: &str, remote_dir: &str, format: ArchiveFormat) -> Result<ArchiveSummary, String> {
+        let session_id = self.get_current_session()?;
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.worker_tx
+            .send(WorkerCommand::ExtractLocalArchive {
+                session_id,
+                archive_path: archive_path.to_string(),
+                remote_dir: remote_dir.to_string(),
+                format,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
     /// Upload file from local to remote
     pub fn upload_file(&self, local_path: &str, remote_path: &str) -> Result<(), String> {
-        // Read local file
-        let content = std::fs::read(local_path)
-            .map_err(|e| format!("Failed to read local file: {}", e))?;
-        
-        // Write to remote via SFTP
-        self.write_sftp_file(remote_path, &content)
+        self.transfer_and_wait(TransferDirection::Upload, local_path, remote_path)
     }
     
     /// Download file from remote to local
     pub fn download_file(&self, remote_path: &str, local_path: &str) -> Result<(), String> {
-        // Read from remote via SFTP
-        let content = self.read_sftp_file(remote_path)?;
+        self.transfer_and_wait(TransferDirection::Download, remote_path, local_path)
+    }
+    
+    // ================== Transfer Queue Methods ==================
+    
+    /// Queue a chunked transfer on a session; progress is reported to `window` as
+    /// `sftp_transfer_progress` events carrying the FileTransferTask
+    pub fn enqueue_transfer(
+        &self,
+        session_id: &str,
+        direction: TransferDirection,
+        source_path: &str,
+        destination_path: &str,
+        options: TransferOptions,
+        window: Option<tauri::Window>,
+    ) -> Result<crate::types::FileTransferTask, String> {
+        let (task, job) = self.transfers.enqueue(session_id, direction, source_path, destination_path, options);
+        self.submit_transfer(job, window, None)?;
+        Ok(task)
+    }
+    
+    /// Re-queue a failed or cancelled transfer, resuming from what already reached the destination
+    pub fn retry_transfer(
+        &self,
+        transfer_id: &str,
+        window: Option<tauri::Window>,
+    ) -> Result<crate::types::FileTransferTask, String> {
+        let (task, job) = self.transfers.retry(transfer_id)?;
+        let transfer_id = job.id.clone();
+        let (response_tx, response_rx) = mpsc::channel();
+        self.worker_tx
+            .send(WorkerCommand::StartTransfer { job, window, done_tx: None, response_tx })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+            .inspect_err(|e| {
+                self.transfers.update(&transfer_id, |t| {
+                    t.status = "failed".to_string();
+                    t.error_message = Some(e.clone());
+                });
+            })?;
+        Ok(task)
+    }
+    
+    /// Recursively upload or download a directory. Every file becomes its own queued
+    /// transfer; in mirror mode unchanged files are skipped
+    pub fn transfer_directory(
+        &self,
+        session_id: &str,
+        direction: TransferDirection,
+        source_path: &str,
+        destination_path: &str,
+        options: SyncOptions,
+        window: Option<tauri::Window>,
+    ) -> Result<DirectorySyncSummary, String> {
+        let (response_tx, response_rx) = mpsc::channel();
         
-        // Write to local file
-        std::fs::write(local_path, &content)
-            .map_err(|e| format!("Failed to write local file: {}", e))?;
+        self.worker_tx
+            .send(WorkerCommand::StartDirectoryTransfer {
+                session_id: session_id.to_string(),
+                direction,
+                source_path: source_path.to_string(),
+                destination_path: destination_path.to_string(),
+                options,
+                queue: self.transfers.clone(),
+                window,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())?;
         
-        Ok(())
+        response_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    // ================== Remote Watch ==================
+    
+    /// Start following a remote file (`tail -F`) or watching it for changes (`inotifywait`).
+    /// Events go to `window`; the watch ends on `stop_watch`, when its owner tab closes or
+    /// when the session disconnects
+    pub fn start_watch(
+        &self,
+        session_id: &str,
+        spec: WatchSpec,
+        owner: Option<String>,
+        window: tauri::Window,
+    ) -> Result<WatchInfo, String> {
+        let (info, stop_rx) = self.watches.register(session_id, owner, spec);
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        let result = self
+            .worker_tx
+            .send(WorkerCommand::StartWatch {
+                info: info.clone(),
+                registry: self.watches.clone(),
+                stop_rx,
+                window,
+                response_tx,
+            })
+            .map_err(|_| "Worker thread has shut down".to_string())
+            .and_then(|_| {
+                response_rx
+                    .recv()
+                    .map_err(|_| "Failed to receive response from worker".to_string())?
+            });
+        if let Err(e) = result {
+            let _ = self.watches.stop(&info.watch_id);
+            return Err(e);
+        }
+        Ok(info)
+    }
+    
+    pub fn stop_watch(&self, watch_id: &str) -> Result<(), String> {
+        self.watches.stop(watch_id)
+    }
+    
+    /// Stop every watch opened by a tab, returns how many were stopped
+    pub fn stop_owner_watches(&self, owner: &str) -> usize {
+        self.watches.stop_owner(owner)
+    }
+    
+    pub fn list_watches(&self, session_id: Option<&str>) -> Vec<WatchInfo> {
+        self.watches.list(session_id)
+    }
+    
+    /// Transfer on the current session and block until it finishes
+    fn transfer_and_wait(&self, direction: TransferDirection, source_path: &str, destination_path: &str) -> Result<(), String> {
+        let session_id = self.get_current_session()?;
+        let (_, job) = self.transfers.enqueue(&session_id, direction, source_path, destination_path, TransferOptions::default());
+        let (done_tx, done_rx) = mpsc::channel();
+        self.submit_transfer(job, None, Some(done_tx))?;
+        done_rx
+            .recv()
+            .map_err(|_| "Failed to receive response from worker".to_string())?
+    }
+    
+    fn submit_transfer(
+        &self,
+        job: TransferJob,
+        window: Option<tauri::Window>,
+        done_tx: Option<mpsc::Sender<Result<(), String>>>,
+    ) -> Result<(), String> {
+        let transfer_id = job.id.clone();
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        let result = self
+            .worker_tx
+            .send(WorkerCommand::StartTransfer { job, window, done_tx, response_tx })
+            .map_err(|_| "Worker thread has shut down".to_string())
+            .and_then(|_| {
+                response_rx
+                    .recv()
+                    .map_err(|_| "Failed to receive response from worker".to_string())?
+            });
+        if result.is_err() {
+            self.transfers.discard(&transfer_id);
+        }
+        result
+    }
+    
+    pub fn list_transfers(&self, session_id: Option<&str>) -> Vec<crate::types::FileTransferTask> {
+        self.transfers.list(session_id)
+    }
+    
+    pub fn pause_transfer(&self, transfer_id: &str) -> Result<(), String> {
+        self.transfers.pause(transfer_id)
+    }
+    
+    pub fn resume_transfer(&self, transfer_id: &str) -> Result<(), String> {
+        self.transfers.resume(transfer_id)
+    }
+    
+    pub fn cancel_transfer(&self, transfer_id: &str) -> Result<(), String> {
+        self.transfers.cancel(transfer_id)
+    }
+    
+    /// Drop finished transfers from the queue, returns how many were removed
+    pub fn clear_finished_transfers(&self) -> usize {
+        self.transfers.clear_finished()
     }
     
     /// Create directory (alias for create_sftp_directory for backward compatibility)
@@ -1660,9 +4187,95 @@ impl Default for SSHManagerRussh {
     }
 }
 
-impl Drop for SSHManagerRussh {
-    fn drop(&mut self) {
-        // Send shutdown command to worker thread
-        let _ = self.worker_tx.send(WorkerCommand::Shutdown);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_stuck_terminal_does_not_hold_up_input_to_the_others() {
+        // Nobody reads the stuck terminal's pipe, so its write never finishes
+        let (stuck_writer, _stuck_reader) = tokio::io::duplex(4);
+        let (writer, mut reader) = tokio::io::duplex(64);
+        let stuck = spawn_terminal_writer(stuck_writer);
+        let live = spawn_terminal_writer(writer);
+
+        let (stuck_tx, stuck_rx) = mpsc::channel();
+        stuck.send((b"this does not fit".to_vec(), stuck_tx)).unwrap();
+        let (tx, rx) = mpsc::channel();
+        live.send((b"ls\r".to_vec(), tx.clone())).unwrap();
+        live.send((b"pwd\r".to_vec(), tx)).unwrap();
+
+        let mut received = [0u8; 7];
+        tokio::time::timeout(std::time::Duration::from_secs(5), reader.read_exact(&mut received))
+            .await
+            .expect("input to the live terminal was held up")
+            .unwrap();
+        assert_eq!(&received, b"ls\rpwd\r");
+        assert_eq!(rx.recv().unwrap(), Ok(()));
+        assert_eq!(rx.recv().unwrap(), Ok(()));
+        assert!(stuck_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn switching_hosts_keeps_the_previous_session_while_it_has_terminals() {
+        let terminals: HashMap<&str, Vec<&str>> = HashMap::from([("first", vec!["term-1", "term-2"]), ("second", vec![])]);
+        let in_use = |id: &str| terminals.get(id).is_some_and(|t| !t.is_empty());
+
+        assert_eq!(idle_previous_session(Some("first".into()), "second", in_use), None);
+        assert_eq!(idle_previous_session(Some("second".into()), "third", in_use), Some("second".to_string()));
+        assert_eq!(idle_previous_session(Some("third".into()), "third", |_| false), None);
+        assert_eq!(idle_previous_session(None, "third", in_use), None);
+    }
+
+    #[test]
+    fn attribute_change_keeps_the_other_half_of_each_pair() {
+        let mut existing = FileAttributes::empty();
+        existing.uid = Some(1000);
+        existing.gid = Some(33);
+        existing.atime = Some(1_600_000_000);
+        existing.mtime = Some(1_650_000_000);
+
+        let owner_only = SftpAttributeChange { uid: Some(0), ..Default::default() };
+        assert!(needs_existing_attributes(&owner_only));
+        let attrs = merge_attribute_change(&owner_only, Some(&existing));
+        assert_eq!((attrs.uid, attrs.gid), (Some(0), Some(33)));
+        assert_eq!((attrs.atime, attrs.mtime), (None, None));
+
+        let group_only = SftpAttributeChange { gid: Some(4), ..Default::default() };
+        let attrs = merge_attribute_change(&group_only, Some(&existing));
+        assert_eq!((attrs.uid, attrs.gid), (Some(1000), Some(4)));
+
+        let mtime_only = SftpAttributeChange { mtime: Some(1_700_000_000), ..Default::default() };
+        let attrs = merge_attribute_change(&mtime_only, Some(&existing));
+        assert_eq!((attrs.atime, attrs.mtime), (Some(1_600_000_000), Some(1_700_000_000)));
+        assert_eq!((attrs.uid, attrs.gid), (None, None));
+
+        let mode_only = SftpAttributeChange { mode: Some(0o100640), ..Default::default() };
+        assert!(!needs_existing_attributes(&mode_only));
+        assert_eq!(merge_attribute_change(&mode_only, None).permissions, Some(0o640));
+    }
+
+    #[test]
+    fn writes_are_refused_when_the_file_changed() {
+        let read = FileVersion::new(6, Some(1_700_000_000), b"hello\n");
+        assert_eq!(read.token, format!("6-1700000000-{}", read.sha256));
+        assert!(check_expected_version("/etc/hosts", Some(&read), &read.token).is_ok());
+        
+        // Same size and mtime (edited within the same second) but different content
+        let edited = FileVersion::new(6, Some(1_700_000_000), b"hellO\n");
+        let error = check_expected_version("/etc/hosts", Some(&edited), &read.token).unwrap_err();
+        assert!(error.starts_with(FILE_CONFLICT_ERROR));
+        
+        let error = check_expected_version("/etc/hosts", None, &read.token).unwrap_err();
+        assert!(error.starts_with(FILE_CONFLICT_ERROR) && error.contains("deleted"));
+    }
+    
+    #[test]
+    fn running_command_ids_are_unique() {
+        let running = Mutex::new(HashMap::new());
+        let _first = register_running_command(&running, "scan").unwrap();
+        assert!(register_running_command(&running, "scan").is_err());
+        running.lock().unwrap().remove("scan");
+        assert!(register_running_command(&running, "scan").is_ok());
     }
 }
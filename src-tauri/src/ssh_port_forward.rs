@@ -0,0 +1,432 @@
+// SSH 端口转发
+// 在 russh 会话上实现本地 (-L)、远程 (-R) 与动态 SOCKS5 (-D) 端口转发
+
+use russh::client::{Handle, Handler, Msg};
+use russh::Channel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, RwLock};
+
+/// accept 失败（如文件描述符耗尽 EMFILE）后的等待时间，连续失败时逐次加倍
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(50);
+const ACCEPT_RETRY_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// 转发类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortForwardKind {
+    /// -L：本地监听，经 SSH 连接到远端目标
+    Local,
+    /// -R：服务器监听，连接回本地目标
+    Remote,
+    /// -D：本地 SOCKS5 代理
+    Dynamic,
+}
+
+/// 转发参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortForwardSpec {
+    pub kind: PortForwardKind,
+    pub bind_address: String,
+    pub bind_port: u16,
+    pub target_host: Option<String>,
+    pub target_port: Option<u16>,
+}
+
+/// 转发状态（返回给前端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortForwardInfo {
+    pub id: String,
+    pub session_id: String,
+    pub kind: PortForwardKind,
+    pub bind_address: String,
+    pub bind_port: u16,
+    pub target_host: Option<String>,
+    pub target_port: Option<u16>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub active_connections: u64,
+    pub total_connections: u64,
+    pub created: chrono::DateTime<chrono::Utc>,
+}
+
+/// 转发流量统计，sent 为发往 SSH 对端方向，received 为从对端返回方向
+#[derive(Debug, Default)]
+pub struct ForwardStats {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    active_connections: AtomicU64,
+    total_connections: AtomicU64,
+}
+
+/// 远程转发的本地目标，ClientHandler 据此把服务器发起的连接接到本地
+#[derive(Clone)]
+pub struct RemoteForwardTarget {
+    pub host: String,
+    pub port: u16,
+    stats: Arc<ForwardStats>,
+    shutdown: watch::Receiver<bool>,
+}
+
+/// 远程转发注册表，按服务器端监听端口索引
+pub type RemoteForwardRegistry = Arc<Mutex<HashMap<u32, RemoteForwardTarget>>>;
+
+/// 一个正在运行的转发
+pub struct PortForward {
+    info: PortForwardInfo,
+    stats: Arc<ForwardStats>,
+    shutdown: watch::Sender<bool>,
+    accept_task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl PortForward {
+    fn new(id: &str, session_id: &str, spec: &PortForwardSpec, bind_port: u16) -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            info: PortForwardInfo {
+                id: id.to_string(),
+                session_id: session_id.to_string(),
+                kind: spec.kind,
+                bind_address: spec.bind_address.clone(),
+                bind_port,
+                target_host: spec.target_host.clone(),
+                target_port: spec.target_port,
+                bytes_sent: 0,
+                bytes_received: 0,
+                active_connections: 0,
+                total_connections: 0,
+                created: chrono::Utc::now(),
+            },
+            stats: Arc::new(ForwardStats::default()),
+            shutdown,
+            accept_task: None,
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.info.session_id
+    }
+
+    /// 当前状态快照
+    pub fn info(&self) -> PortForwardInfo {
+        let mut info = self.info.clone();
+        info.bytes_sent = self.stats.bytes_sent.load(Ordering::Relaxed);
+        info.bytes_received = self.stats.bytes_received.load(Ordering::Relaxed);
+        info.active_connections = self.stats.active_connections.load(Ordering::Relaxed);
+        info.total_connections = self.stats.total_connections.load(Ordering::Relaxed);
+        info
+    }
+
+    /// 停止转发并断开其上的全部连接
+    pub async fn stop<H: Handler>(mut self, handle: &RwLock<Handle<H>>, registry: &RemoteForwardRegistry) {
+        self.abandon();
+        if self.info.kind == PortForwardKind::Remote {
+            if let Ok(mut forwards) = registry.lock() {
+                forwards.remove(&(self.info.bind_port as u32));
+            }
+            let _ = handle
+                .read()
+                .await
+                .cancel_tcpip_forward(self.info.bind_address.clone(), self.info.bind_port as u32)
+                .await;
+        }
+    }
+
+    /// 所属会话已不存在时停止：只关闭本地监听与连接，不再通知服务器
+    pub fn abandon(&mut self) {
+        let _ = self.shutdown.send(true);
+        if let Some(task) = self.accept_task.take() {
+            task.abort();
+        }
+    }
+}
+
+fn require_target(spec: &PortForwardSpec) -> Result<(String, u16), String> {
+    match (&spec.target_host, spec.target_port) {
+        (Some(host), Some(port)) if !host.is_empty() => Ok((host.clone(), port)),
+        _ => Err("Port forward target host and port are required".to_string()),
+    }
+}
+
+/// 启动转发
+pub async fn start_forward<H: Handler + 'static>(
+    handle: Arc<RwLock<Handle<H>>>,
+    registry: &RemoteForwardRegistry,
+    id: &str,
+    session_id: &str,
+    spec: &PortForwardSpec,
+) -> Result<PortForward, String> {
+    match spec.kind {
+        PortForwardKind::Local => {
+            let target = require_target(spec)?;
+            let listener = bind_listener(spec).await?;
+            let bind_port = listener.local_addr().map(|a| a.port()).unwrap_or(spec.bind_port);
+            let mut forward = PortForward::new(id, session_id, spec, bind_port);
+            forward.accept_task = Some(tokio::spawn(accept_loop(
+                listener,
+                handle,
+                Some(target),
+                forward.stats.clone(),
+                forward.shutdown.subscribe(),
+            )));
+            Ok(forward)
+        }
+        PortForwardKind::Dynamic => {
+            let listener = bind_listener(spec).await?;
+            let bind_port = listener.local_addr().map(|a| a.port()).unwrap_or(spec.bind_port);
+            let mut forward = PortForward::new(id, session_id, spec, bind_port);
+            forward.accept_task = Some(tokio::spawn(accept_loop(
+                listener,
+                handle,
+                None,
+                forward.stats.clone(),
+                forward.shutdown.subscribe(),
+            )));
+            Ok(forward)
+        }
+        PortForwardKind::Remote => {
+            let (target_host, target_port) = require_target(spec)?;
+            let allocated = handle
+                .write()
+                .await
+                .tcpip_forward(spec.bind_address.clone(), spec.bind_port as u32)
+                .await
+                .map_err(|e| format!("Server refused remote forward: {}", e))?;
+            // 请求端口为 0 时服务器返回实际分配的端口
+            let bind_port = if spec.bind_port == 0 { allocated as u16 } else { spec.bind_port };
+
+            let forward = PortForward::new(id, session_id, spec, bind_port);
+            let target = RemoteForwardTarget {
+                host: target_host,
+                port: target_port,
+                stats: forward.stats.clone(),
+                shutdown: forward.shutdown.subscribe(),
+            };
+            if let Ok(mut forwards) = registry.lock() {
+                forwards.insert(bind_port as u32, target);
+            }
+            Ok(forward)
+        }
+    }
+}
+
+async fn bind_listener(spec: &PortForwardSpec) -> Result<TcpListener, String> {
+    TcpListener::bind((spec.bind_address.as_str(), spec.bind_port))
+        .await
+        .map_err(|e| format!("Failed to listen on {}:{}: {}", spec.bind_address, spec.bind_port, e))
+}
+
+/// 本地/动态转发的监听循环；target 为 None 时按 SOCKS5 协商目标
+async fn accept_loop<H: Handler + 'static>(
+    listener: TcpListener,
+    handle: Arc<RwLock<Handle<H>>>,
+    target: Option<(String, u16)>,
+    stats: Arc<ForwardStats>,
+    shutdown: watch::Receiver<bool>,
+) {
+    let mut retry_delay = ACCEPT_RETRY_DELAY;
+    loop {
+        let (mut socket, peer) = match listener.accept().await {
+            Ok(conn) => {
+                retry_delay = ACCEPT_RETRY_DELAY;
+                conn
+            }
+            // 错误通常会立即重复出现，不等待就会空转占满 CPU
+            Err(_) => {
+                tokio::time::sleep(retry_delay).await;
+                retry_delay = (retry_delay * 2).min(ACCEPT_RETRY_MAX_DELAY);
+                continue;
+            }
+        };
+
+        let handle = handle.clone();
+        let target = target.clone();
+        let stats = stats.clone();
+        let shutdown = shutdown.clone();
+
+        tokio::spawn(async move {
+            let socks = target.is_none();
+            let (host, port) = match target {
+                Some(target) => target,
+                None => match socks5_handshake(&mut socket).await {
+                    Ok(target) => target,
+                    Err(_) => return,
+                },
+            };
+
+            let channel = handle
+                .read()
+                .await
+                .channel_open_direct_tcpip(host, port as u32, peer.ip().to_string(), peer.port() as u32)
+                .await;
+
+            match channel {
+                Ok(channel) => {
+                    if socks && socks5_reply(&mut socket, true).await.is_err() {
+                        return;
+                    }
+                    pipe(socket, channel, stats, shutdown).await;
+                }
+                Err(_) => {
+                    if socks {
+                        let _ = socks5_reply(&mut socket, false).await;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// 处理服务器通过远程转发打开的通道
+pub fn spawn_remote_connection(channel: Channel<Msg>, target: RemoteForwardTarget) {
+    tokio::spawn(async move {
+        match TcpStream::connect((target.host.as_str(), target.port)).await {
+            Ok(socket) => pipe(socket, channel, target.stats, target.shutdown).await,
+            Err(_) => {
+                let _ = channel.close().await;
+            }
+        }
+    });
+}
+
+/// 在 TCP 连接与 SSH 通道之间双向复制数据并统计流量
+async fn pipe(
+    socket: TcpStream,
+    channel: Channel<Msg>,
+    stats: Arc<ForwardStats>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    stats.active_connections.fetch_add(1, Ordering::Relaxed);
+    stats.total_connections.fetch_add(1, Ordering::Relaxed);
+
+    let (mut socket_read, mut socket_write) = socket.into_split();
+    let (mut channel_read, mut channel_write) = tokio::io::split(channel.into_stream());
+
+    let upstream = copy_counted(&mut socket_read, &mut channel_write, &stats.bytes_sent);
+    let downstream = copy_counted(&mut channel_read, &mut socket_write, &stats.bytes_received);
+
+    tokio::select! {
+        _ = async { tokio::join!(upstream, downstream) } => {}
+        _ = shutdown.wait_for(|stop| *stop) => {}
+    }
+
+    stats.active_connections.fetch_sub(1, Ordering::Relaxed);
+}
+
+async fn copy_counted<R, W>(reader: &mut R, writer: &mut W, counter: &AtomicU64)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; 32 * 1024];
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        if writer.write_all(&buf[..n]).await.is_err() {
+            break;
+        }
+        counter.fetch_add(n as u64, Ordering::Relaxed);
+    }
+    let _ = writer.shutdown().await;
+}
+
+/// SOCKS5 协商（仅支持无认证的 CONNECT），返回目标地址
+async fn socks5_handshake<S>(stream: &mut S) -> Result<(String, u16), String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let io_err = |e: std::io::Error| format!("SOCKS5 handshake failed: {}", e);
+
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await.map_err(io_err)?;
+    if header[0] != 0x05 {
+        return Err("Unsupported SOCKS version".to_string());
+    }
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods).await.map_err(io_err)?;
+    if !methods.contains(&0x00) {
+        let _ = stream.write_all(&[0x05, 0xff]).await;
+        return Err("SOCKS5 client does not offer no-auth method".to_string());
+    }
+    stream.write_all(&[0x05, 0x00]).await.map_err(io_err)?;
+
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request).await.map_err(io_err)?;
+    if request[1] != 0x01 {
+        // 仅支持 CONNECT
+        let _ = stream.write_all(&[0x05, 0x07, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await;
+        return Err("Unsupported SOCKS5 command".to_string());
+    }
+
+    let host = match request[3] {
+        0x01 => {
+            let mut addr = [0u8; 4];
+            stream.read_exact(&mut addr).await.map_err(io_err)?;
+            Ipv4Addr::from(addr).to_string()
+        }
+        0x03 => {
+            let len = stream.read_u8().await.map_err(io_err)?;
+            let mut name = vec![0u8; len as usize];
+            stream.read_exact(&mut name).await.map_err(io_err)?;
+            String::from_utf8_lossy(&name).to_string()
+        }
+        0x04 => {
+            let mut addr = [0u8; 16];
+            stream.read_exact(&mut addr).await.map_err(io_err)?;
+            Ipv6Addr::from(addr).to_string()
+        }
+        _ => return Err("Unsupported SOCKS5 address type".to_string()),
+    };
+    let port = stream.read_u16().await.map_err(io_err)?;
+
+    Ok((host, port))
+}
+
+async fn socks5_reply<S>(stream: &mut S, success: bool) -> std::io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let status = if success { 0x00 } else { 0x05 };
+    stream.write_all(&[0x05, status, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_socks5_handshake_domain() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+
+        let client_task = tokio::spawn(async move {
+            client.write_all(&[0x05, 0x01, 0x00]).await.unwrap();
+            let mut reply = [0u8; 2];
+            client.read_exact(&mut reply).await.unwrap();
+            assert_eq!(reply, [0x05, 0x00]);
+
+            let mut request = vec![0x05, 0x01, 0x00, 0x03, 9];
+            request.extend_from_slice(b"intranet1");
+            request.extend_from_slice(&8080u16.to_be_bytes());
+            client.write_all(&request).await.unwrap();
+        });
+
+        let target = socks5_handshake(&mut server).await.unwrap();
+        client_task.await.unwrap();
+        assert_eq!(target, ("intranet1".to_string(), 8080));
+    }
+
+    #[tokio::test]
+    async fn test_socks5_handshake_rejects_auth_only_clients() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+        client.write_all(&[0x05, 0x01, 0x02]).await.unwrap();
+        assert!(socks5_handshake(&mut server).await.is_err());
+    }
+}
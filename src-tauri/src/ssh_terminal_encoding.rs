@@ -0,0 +1,127 @@
+// 终端输出解码
+// 每个终端保存增量解码器，跨数据包的多字节字符（中文文件名、制表符）不再被替换为 U+FFFD；
+// 支持 GBK 等非 UTF-8 的远程编码，也可以把原始字节以 base64 交给前端自行解码
+
+use base64::{engine::general_purpose, Engine as _};
+use encoding_rs::{Decoder, Encoding};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// 远程终端使用的字符编码
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalEncoding {
+    #[default]
+    #[serde(alias = "utf-8")]
+    Utf8,
+    /// 较旧的中文 Linux 发行版（zh_CN.GBK）
+    Gbk,
+    Gb18030,
+    Big5,
+}
+
+impl TerminalEncoding {
+    fn encoding(self) -> &'static Encoding {
+        match self {
+            TerminalEncoding::Utf8 => encoding_rs::UTF_8,
+            TerminalEncoding::Gbk => encoding_rs::GBK,
+            TerminalEncoding::Gb18030 => encoding_rs::GB18030,
+            TerminalEncoding::Big5 => encoding_rs::BIG5,
+        }
+    }
+}
+
+/// 终端输出选项
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalOutputOptions {
+    pub encoding: TerminalEncoding,
+    /// ssh_terminal_data 的 data 改为原始字节的 base64（并带 "encoding": "base64"），由前端解码
+    pub raw: bool,
+}
+
+/// 一个终端的解码状态；stdout 与 stderr 是独立的字节流，各用一个解码器
+pub struct TerminalDecoder {
+    options: TerminalOutputOptions,
+    stdout: Decoder,
+    stderr: Decoder,
+}
+
+impl TerminalDecoder {
+    pub fn new(options: TerminalOutputOptions) -> Self {
+        let encoding = options.encoding.encoding();
+        Self {
+            options,
+            stdout: encoding.new_decoder_without_bom_handling(),
+            stderr: encoding.new_decoder_without_bom_handling(),
+        }
+    }
+
+    pub fn options(&self) -> TerminalOutputOptions {
+        self.options
+    }
+
+    /// 换了新通道（重连）后，上一个通道留下的半个字符不再有意义
+    pub fn reset(&mut self) {
+        *self = Self::new(self.options);
+    }
+
+    /// 解码一段输出；末尾不完整的多字节序列留到下一段
+    pub fn decode(&mut self, data: &[u8], stderr: bool) -> String {
+        let decoder = if stderr { &mut self.stderr } else { &mut self.stdout };
+        let capacity = decoder.max_utf8_buffer_length(data.len()).unwrap_or(data.len() * 3 + 4);
+        let mut text = String::with_capacity(capacity);
+        let _ = decoder.decode_to_string(data, &mut text, false);
+        text
+    }
+
+    /// ssh_terminal_data 事件的载荷；text 为 decode 的结果
+    pub fn payload(&self, terminal_id: &str, data: &[u8], text: &str) -> serde_json::Value {
+        if self.options.raw {
+            serde_json::json!({
+                "terminalId": terminal_id,
+                "data": general_purpose::STANDARD.encode(data),
+                "encoding": "base64",
+            })
+        } else {
+            serde_json::json!({"terminalId": terminal_id, "data": text})
+        }
+    }
+
+    /// 前端发来的 UTF-8 输入转换为远程编码
+    pub fn encode_input<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.options.encoding == TerminalEncoding::Utf8 {
+            return Cow::Borrowed(data);
+        }
+        let text = String::from_utf8_lossy(data);
+        let (bytes, _, _) = self.options.encoding.encoding().encode(&text);
+        Cow::Owned(bytes.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_characters_split_across_packets() {
+        let mut decoder = TerminalDecoder::new(TerminalOutputOptions::default());
+        let bytes = "日志─┐".as_bytes();
+        assert_eq!(decoder.decode(&bytes[..4], false), "日");
+        assert_eq!(decoder.decode(&bytes[4..8], false), "志");
+        // stderr 不受 stdout 中未完成序列的影响
+        assert_eq!(decoder.decode(b"err", true), "err");
+        assert_eq!(decoder.decode(&bytes[8..], false), "─┐");
+    }
+
+    #[test]
+    fn decodes_and_encodes_gbk() {
+        let options = TerminalOutputOptions { encoding: TerminalEncoding::Gbk, raw: false };
+        let mut decoder = TerminalDecoder::new(options);
+        let gbk = [0xc8, 0xd5, 0xd6, 0xbe]; // "日志"
+        assert_eq!(decoder.decode(&gbk[..3], false), "日");
+        assert_eq!(decoder.decode(&gbk[3..], false), "志");
+        assert_eq!(decoder.encode_input("日志".as_bytes()).as_ref(), &gbk);
+        assert_eq!(decoder.encode_input(b"ls\r").as_ref(), b"ls\r");
+    }
+}
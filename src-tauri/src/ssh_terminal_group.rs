@@ -0,0 +1,217 @@
+// 终端组
+// 把多个终端编为一组，同一段输入广播给组内所有启用的成员（同时处置多台服务器时使用）；
+// 成员的通道断开或退出时发送事件
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+/// 组成员的通道断开或退出
+pub const MEMBER_LOST_EVENT: &str = "ssh_terminal_group_member_lost";
+
+/// 成员通道的结束方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberLoss {
+    /// shell 退出或终端被关闭，成员移出组
+    Closed,
+    /// 连接断开，会话重连后终端恢复，成员保留在组内
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalGroupMember {
+    pub terminal_id: String,
+    /// 关闭后不再接收广播
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalGroupInfo {
+    pub group_id: String,
+    pub name: String,
+    pub members: Vec<TerminalGroupMember>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// 广播到单个成员失败
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastFailure {
+    pub terminal_id: String,
+    pub error: String,
+}
+
+/// 一次广播的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BroadcastResult {
+    pub sent: Vec<String>,
+    pub failed: Vec<BroadcastFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MemberLostPayload<'a> {
+    group_id: &'a str,
+    terminal_id: &'a str,
+    reason: MemberLoss,
+}
+
+/// 终端组只记录成员的终端 id；关闭的终端由 member_lost 移出，断线的保留等待重连
+#[derive(Clone, Default)]
+pub struct TerminalGroups {
+    groups: Arc<Mutex<HashMap<String, TerminalGroupInfo>>>,
+}
+
+impl TerminalGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create(&self, name: &str, terminal_ids: Vec<String>) -> TerminalGroupInfo {
+        let mut members: Vec<TerminalGroupMember> = Vec::with_capacity(terminal_ids.len());
+        for terminal_id in terminal_ids {
+            if !members.iter().any(|m| m.terminal_id == terminal_id) {
+                members.push(TerminalGroupMember { terminal_id, enabled: true });
+            }
+        }
+        let info = TerminalGroupInfo {
+            group_id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            members,
+            created_at: chrono::Utc::now(),
+        };
+        self.groups.lock().unwrap().insert(info.group_id.clone(), info.clone());
+        info
+    }
+
+    pub fn delete(&self, group_id: &str) -> Result<(), String> {
+        self.groups
+            .lock()
+            .unwrap()
+            .remove(group_id)
+            .map(|_| ())
+            .ok_or_else(|| format!("Terminal group not found: {}", group_id))
+    }
+
+    pub fn get(&self, group_id: &str) -> Result<TerminalGroupInfo, String> {
+        self.groups
+            .lock()
+            .unwrap()
+            .get(group_id)
+            .cloned()
+            .ok_or_else(|| format!("Terminal group not found: {}", group_id))
+    }
+
+    pub fn list(&self) -> Vec<TerminalGroupInfo> {
+        let mut groups: Vec<TerminalGroupInfo> = self.groups.lock().unwrap().values().cloned().collect();
+        groups.sort_by_key(|g| g.created_at);
+        groups
+    }
+
+    pub fn add_member(&self, group_id: &str, terminal_id: &str) -> Result<TerminalGroupInfo, String> {
+        self.update(group_id, |group| {
+            if !group.members.iter().any(|m| m.terminal_id == terminal_id) {
+                group.members.push(TerminalGroupMember { terminal_id: terminal_id.to_string(), enabled: true });
+            }
+            Ok(())
+        })
+    }
+
+    pub fn remove_member(&self, group_id: &str, terminal_id: &str) -> Result<TerminalGroupInfo, String> {
+        self.update(group_id, |group| {
+            let before = group.members.len();
+            group.members.retain(|m| m.terminal_id != terminal_id);
+            if group.members.len() == before {
+                return Err(format!("Terminal is not in the group: {}", terminal_id));
+            }
+            Ok(())
+        })
+    }
+
+    /// 临时把某个成员排除在广播之外，或重新加入
+    pub fn set_member_enabled(&self, group_id: &str, terminal_id: &str, enabled: bool) -> Result<TerminalGroupInfo, String> {
+        self.update(group_id, |group| {
+            let member = group
+                .members
+                .iter_mut()
+                .find(|m| m.terminal_id == terminal_id)
+                .ok_or_else(|| format!("Terminal is not in the group: {}", terminal_id))?;
+            member.enabled = enabled;
+            Ok(())
+        })
+    }
+
+    /// 应接收广播的成员
+    pub fn targets(&self, group_id: &str) -> Result<Vec<String>, String> {
+        Ok(self
+            .get(group_id)?
+            .members
+            .into_iter()
+            .filter(|m| m.enabled)
+            .map(|m| m.terminal_id)
+            .collect())
+    }
+
+    /// 终端的通道断开或退出：通知其所在的每个组，退出的终端移出组
+    pub fn member_lost(&self, terminal_id: &str, reason: MemberLoss, window: &tauri::Window) {
+        for group_id in self.mark_lost(terminal_id, reason) {
+            let _ = window.emit(
+                MEMBER_LOST_EVENT,
+                MemberLostPayload { group_id: &group_id, terminal_id, reason },
+            );
+        }
+    }
+
+    /// 返回包含该终端的组
+    fn mark_lost(&self, terminal_id: &str, reason: MemberLoss) -> Vec<String> {
+        let mut groups = self.groups.lock().unwrap();
+        let mut affected = Vec::new();
+        for group in groups.values_mut() {
+            if group.members.iter().any(|m| m.terminal_id == terminal_id) {
+                if reason == MemberLoss::Closed {
+                    group.members.retain(|m| m.terminal_id != terminal_id);
+                }
+                affected.push(group.group_id.clone());
+            }
+        }
+        affected
+    }
+
+    fn update(
+        &self,
+        group_id: &str,
+        f: impl FnOnce(&mut TerminalGroupInfo) -> Result<(), String>,
+    ) -> Result<TerminalGroupInfo, String> {
+        let mut groups = self.groups.lock().unwrap();
+        let group = groups
+            .get_mut(group_id)
+            .ok_or_else(|| format!("Terminal group not found: {}", group_id))?;
+        f(group)?;
+        Ok(group.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broadcasts_to_enabled_members_only() {
+        let groups = TerminalGroups::new();
+        let ids = ["web-01", "web-02", "web-03", "web-01"].map(String::from).to_vec();
+        let group = groups.create("web", ids);
+        assert_eq!(group.members.len(), 3);
+
+        groups.set_member_enabled(&group.group_id, "web-02", false).unwrap();
+        assert_eq!(groups.targets(&group.group_id).unwrap(), vec!["web-01", "web-03"]);
+        assert!(groups.set_member_enabled(&group.group_id, "db-01", false).is_err());
+
+        // 断线的成员保留，退出的成员移出
+        assert_eq!(groups.mark_lost("web-03", MemberLoss::Disconnected), vec![group.group_id.clone()]);
+        assert_eq!(groups.targets(&group.group_id).unwrap(), vec!["web-01", "web-03"]);
+        groups.mark_lost("web-03", MemberLoss::Closed);
+        assert_eq!(groups.targets(&group.group_id).unwrap(), vec!["web-01"]);
+        assert!(groups.mark_lost("db-01", MemberLoss::Closed).is_empty());
+    }
+}
@@ -0,0 +1,395 @@
+// 终端会话录制（asciinema v2 .cast）
+// 把终端的输出、输入与尺寸变化按时间写入应用数据目录下的 recordings/，用于事件报告与应用内回放
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::Emitter;
+
+/// 回放数据事件
+pub const STREAM_EVENT: &str = "ssh_recording_data";
+/// 每个回放事件携带的记录条数
+const STREAM_BATCH: usize = 500;
+const RECORDINGS_DIR: &str = "recordings";
+const CAST_EXTENSION: &str = "cast";
+
+/// 录制选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingOptions {
+    pub title: Option<String>,
+    /// 同时记录键盘输入（"i" 事件）
+    pub record_input: bool,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            record_input: true,
+        }
+    }
+}
+
+/// 录制文件信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    /// 文件名，回放时用它指定录制
+    pub recording_id: String,
+    pub path: String,
+    pub title: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub started_at: DateTime<Utc>,
+    /// 最后一条记录的时间（秒）
+    pub duration: f64,
+    pub size: u64,
+    /// 正在录制时为所属终端
+    pub terminal_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CastHeader<'a> {
+    version: u32,
+    width: u32,
+    height: u32,
+    timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    env: HashMap<&'static str, &'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamPayload<'a> {
+    stream_id: &'a str,
+    /// 仅第一批携带 .cast 文件头
+    header: Option<serde_json::Value>,
+    events: Vec<serde_json::Value>,
+    done: bool,
+    error: Option<String>,
+}
+
+struct Recorder {
+    file: File,
+    started: Instant,
+    record_input: bool,
+    info: RecordingInfo,
+}
+
+impl Recorder {
+    /// 每条记录单独写入，应用异常退出时已录制的内容不会丢失
+    fn event(&mut self, kind: &str, data: &str) -> std::io::Result<()> {
+        let elapsed = (self.started.elapsed().as_secs_f64() * 1e6).round() / 1e6;
+        let mut line = serde_json::to_string(&(elapsed, kind, data))?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.info.duration = elapsed;
+        self.info.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// 进行中的录制，每个终端同时只有一个；终端关闭时由读取任务停止，停止时文件同步到磁盘
+#[derive(Clone, Default)]
+pub struct RecordingRegistry {
+    /// 为空时使用应用数据目录下的 recordings/
+    dir: Option<PathBuf>,
+    active: Arc<Mutex<HashMap<String, Recorder>>>,
+}
+
+impl RecordingRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self {
+            dir: Some(dir),
+            ..Self::default()
+        }
+    }
+
+    fn dir(&self) -> Result<PathBuf, String> {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => crate::settings::get_app_data_dir()?.join(RECORDINGS_DIR),
+        };
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create recordings directory: {}", e))?;
+        Ok(dir)
+    }
+
+    /// 开始录制一个终端，cols/rows 为当前尺寸
+    pub fn start(&self, terminal_id: &str, cols: u32, rows: u32, options: RecordingOptions) -> Result<RecordingInfo, String> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(terminal_id) {
+            return Err(format!("Terminal is already being recorded: {}", terminal_id));
+        }
+
+        let now = Utc::now();
+        let safe_id: String = terminal_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let recording_id = format!("{}-{}.{}", now.format("%Y%m%d-%H%M%S"), safe_id, CAST_EXTENSION);
+        let path = self.dir()?.join(&recording_id);
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to create recording: {}", e))?;
+
+        let header = CastHeader {
+            version: 2,
+            width: cols,
+            height: rows,
+            timestamp: now.timestamp(),
+            title: options.title.as_deref(),
+            env: HashMap::from([("TERM", "xterm-256color")]),
+        };
+        let mut line = serde_json::to_string(&header).map_err(|e| e.to_string())?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write recording: {}", e))?;
+
+        let info = RecordingInfo {
+            recording_id,
+            path: path.to_string_lossy().into_owned(),
+            title: options.title,
+            width: cols,
+            height: rows,
+            started_at: now,
+            duration: 0.0,
+            size: line.len() as u64,
+            terminal_id: Some(terminal_id.to_string()),
+        };
+        active.insert(
+            terminal_id.to_string(),
+            Recorder {
+                file,
+                started: Instant::now(),
+                record_input: options.record_input,
+                info: info.clone(),
+            },
+        );
+        Ok(info)
+    }
+
+    pub fn stop(&self, terminal_id: &str) -> Result<RecordingInfo, String> {
+        let recorder = self
+            .active
+            .lock()
+            .unwrap()
+            .remove(terminal_id)
+            .ok_or_else(|| format!("Terminal is not being recorded: {}", terminal_id))?;
+        let _ = recorder.file.sync_all();
+        Ok(RecordingInfo {
+            terminal_id: None,
+            ..recorder.info
+        })
+    }
+
+    /// 终端输出（"o" 事件）
+    pub fn output(&self, terminal_id: &str, data: &str) {
+        self.record(terminal_id, "o", data, false);
+    }
+
+    /// 键盘输入（"i" 事件）
+    pub fn input(&self, terminal_id: &str, data: &[u8]) {
+        self.record(terminal_id, "i", &String::from_utf8_lossy(data), true);
+    }
+
+    /// 尺寸变化（"r" 事件）
+    pub fn resize(&self, terminal_id: &str, cols: u32, rows: u32) {
+        self.record(terminal_id, "r", &format!("{}x{}", cols, rows), false);
+    }
+
+    fn record(&self, terminal_id: &str, kind: &str, data: &str, is_input: bool) {
+        let mut active = self.active.lock().unwrap();
+        let Some(recorder) = active.get_mut(terminal_id) else {
+            return;
+        };
+        if is_input && !recorder.record_input {
+            return;
+        }
+        // 写入失败（磁盘已满等）时结束录制，已写入的部分仍可回放
+        if recorder.event(kind, data).is_err() {
+            active.remove(terminal_id);
+        }
+    }
+
+    /// 所有录制文件，按开始时间倒序
+    pub fn list(&self) -> Result<Vec<RecordingInfo>, String> {
+        let dir = self.dir()?;
+        let active: HashMap<String, RecordingInfo> = self
+            .active
+            .lock()
+            .unwrap()
+            .values()
+            .map(|r| (r.info.recording_id.clone(), r.info.clone()))
+            .collect();
+
+        let mut recordings: Vec<RecordingInfo> = std::fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read recordings directory: {}", e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == CAST_EXTENSION))
+            .filter_map(|path| {
+                let id = path.file_name()?.to_string_lossy().into_owned();
+                active.get(&id).cloned().or_else(|| read_info(&path))
+            })
+            .collect();
+        recordings.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+        Ok(recordings)
+    }
+
+    /// 录制文件的路径；只接受 recordings/ 下的文件名
+    pub fn path(&self, recording_id: &str) -> Result<PathBuf, String> {
+        let valid = !recording_id.is_empty()
+            && !recording_id.contains(['/', '\\'])
+            && !recording_id.starts_with('.')
+            && recording_id.ends_with(&format!(".{}", CAST_EXTENSION));
+        if !valid {
+            return Err(format!("Invalid recording: {}", recording_id));
+        }
+        let path = self.dir()?.join(recording_id);
+        if !path.is_file() {
+            return Err(format!("Recording not found: {}", recording_id));
+        }
+        Ok(path)
+    }
+
+    /// 分批把录制内容以 ssh_recording_data 事件发给窗口，返回本次回放的 stream_id
+    pub fn stream(&self, recording_id: &str, window: tauri::Window) -> Result<String, String> {
+        let path = self.path(recording_id)?;
+        let stream_id = uuid::Uuid::new_v4().to_string();
+        let id = stream_id.clone();
+        std::thread::spawn(move || {
+            let result = stream_file(&path, &id, &window);
+            if let Err(e) = result {
+                let _ = window.emit(
+                    STREAM_EVENT,
+                    StreamPayload { stream_id: &id, header: None, events: Vec::new(), done: true, error: Some(e) },
+                );
+            }
+        });
+        Ok(stream_id)
+    }
+}
+
+fn stream_file(path: &Path, stream_id: &str, window: &tauri::Window) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open recording: {}", e))?;
+    let mut lines = BufReader::new(file).lines();
+    let header_line = lines
+        .next()
+        .ok_or("Recording is empty")?
+        .map_err(|e| format!("Failed to read recording: {}", e))?;
+    let mut header = Some(serde_json::from_str(&header_line).map_err(|e| format!("Invalid recording header: {}", e))?);
+
+    let mut events = Vec::with_capacity(STREAM_BATCH);
+    let mut next = lines.next();
+    loop {
+        let done = next.is_none();
+        if let Some(line) = next {
+            let line = line.map_err(|e| format!("Failed to read recording: {}", e))?;
+            // 录制中断时最后一行可能不完整，跳过
+            if let Ok(event) = serde_json::from_str(&line) {
+                events.push(event);
+            }
+        }
+        if events.len() >= STREAM_BATCH || done {
+            let payload = StreamPayload {
+                stream_id,
+                header: header.take(),
+                events: std::mem::take(&mut events),
+                done,
+                error: None,
+            };
+            window
+                .emit(STREAM_EVENT, payload)
+                .map_err(|e| format!("Failed to send recording: {}", e))?;
+        }
+        if done {
+            return Ok(());
+        }
+        next = lines.next();
+    }
+}
+
+/// 从 .cast 文件头与最后一条记录得到录制信息
+fn read_info(path: &Path) -> Option<RecordingInfo> {
+    let mut file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mut header_line = String::new();
+    BufReader::new(&mut file).read_line(&mut header_line).ok()?;
+    let header: serde_json::Value = serde_json::from_str(&header_line).ok()?;
+
+    // 最后一条记录在文件末尾附近
+    let tail_start = size.saturating_sub(64 * 1024);
+    file.seek(SeekFrom::Start(tail_start)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    let duration = String::from_utf8_lossy(&tail)
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<(f64, String, String)>(line).ok())
+        .map(|(time, _, _)| time)
+        .next()
+        .unwrap_or(0.0);
+
+    Some(RecordingInfo {
+        recording_id: path.file_name()?.to_string_lossy().into_owned(),
+        path: path.to_string_lossy().into_owned(),
+        title: header["title"].as_str().map(str::to_string),
+        width: header["width"].as_u64().unwrap_or(0) as u32,
+        height: header["height"].as_u64().unwrap_or(0) as u32,
+        started_at: Utc.timestamp_opt(header["timestamp"].as_i64().unwrap_or(0), 0).single()?,
+        duration,
+        size,
+        terminal_id: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_lists_cast_file() {
+        let dir = std::env::temp_dir().join(format!("lovelyres-recordings-{}", uuid::Uuid::new_v4()));
+        let registry = RecordingRegistry::with_dir(dir.clone());
+        let options = RecordingOptions { title: Some("web-01".to_string()), record_input: false };
+        let info = registry.start("tab/1", 80, 24, options).unwrap();
+        assert!(info.recording_id.ends_with("-tab_1.cast"));
+        assert!(registry.start("tab/1", 80, 24, RecordingOptions::default()).is_err());
+
+        registry.output("tab/1", "$ ");
+        registry.input("tab/1", b"id\r");
+        registry.output("tab/1", "uid=0(root)\r\n");
+        registry.resize("tab/1", 120, 40);
+        registry.output("other", "ignored");
+        let stopped = registry.stop("tab/1").unwrap();
+        assert_eq!(stopped.terminal_id, None);
+
+        let content = std::fs::read_to_string(&stopped.path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("{\"version\":2,\"width\":80,\"height\":24,"));
+        assert!(lines[2].ends_with(",\"o\",\"uid=0(root)\\r\\n\"]"));
+        assert!(lines[3].ends_with(",\"r\",\"120x40\"]"));
+
+        let listed = registry.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].title.as_deref(), Some("web-01"));
+        assert_eq!(listed[0].size, content.len() as u64);
+        assert!(registry.path(&listed[0].recording_id).is_ok());
+        assert!(registry.path("../settings.json").is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,373 @@
+// 终端回滚缓冲区
+// 每个终端在后端保留最近的输出行（环形缓冲），窗口重新加载或终端断线后前端可以取回历史；
+// 支持按正则搜索、按行号取区间，以及导出去除 ANSI 控制序列的文本记录
+
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// 每个终端保留的输出字节数；按字节而不是行数计，长行较多时内存同样有上限
+const CAPACITY_BYTES: usize = 16 * 1024 * 1024;
+/// 不换行的输出（进度条等）超过该长度时强制断行，避免单行无限增长
+const MAX_LINE_BYTES: usize = 64 * 1024;
+const DEFAULT_MAX_MATCHES: usize = 1000;
+
+/// 缓冲区概况；行号从终端的第一行输出开始计，被挤出缓冲区的行不再可取
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollbackInfo {
+    pub terminal_id: String,
+    /// 缓冲区中最早一行的行号
+    pub first_line: u64,
+    /// 缓冲区中的行数（含尚未换行的最后一行）
+    pub line_count: usize,
+    /// 缓冲区占用的字节数
+    pub bytes: usize,
+    pub capacity_bytes: usize,
+}
+
+/// 搜索选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollbackSearchOptions {
+    pub case_sensitive: bool,
+    pub max_matches: usize,
+}
+
+impl Default for ScrollbackSearchOptions {
+    fn default() -> Self {
+        Self {
+            case_sensitive: false,
+            max_matches: DEFAULT_MAX_MATCHES,
+        }
+    }
+}
+
+/// 一处匹配；start/end 为 text 中的字符位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollbackMatch {
+    pub line: u64,
+    /// 去除 ANSI 控制序列后的整行
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollbackSearchResult {
+    pub matches: Vec<ScrollbackMatch>,
+    /// 匹配数达到 max_matches 后停止
+    pub truncated: bool,
+}
+
+/// 一段连续的行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollbackRange {
+    /// 第一行的行号（请求的起点早于缓冲区时会后移）
+    pub start: u64,
+    pub lines: Vec<String>,
+    pub first_line: u64,
+    pub line_count: usize,
+}
+
+struct Scrollback {
+    lines: VecDeque<String>,
+    /// 尚未遇到换行的最后一行
+    partial: String,
+    first_line: u64,
+    /// lines 中各行的字节数之和
+    bytes: usize,
+}
+
+impl Scrollback {
+    fn new() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            partial: String::new(),
+            first_line: 0,
+            bytes: 0,
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(pos) = rest.find('\n') {
+            self.partial.push_str(&rest[..pos]);
+            self.finish_line();
+            rest = &rest[pos + 1..];
+        }
+        self.partial.push_str(rest);
+        if self.partial.len() > MAX_LINE_BYTES {
+            self.finish_line();
+        }
+    }
+
+    fn finish_line(&mut self) {
+        let mut line = std::mem::take(&mut self.partial);
+        if line.ends_with('\r') {
+            line.pop();
+        }
+        self.bytes += line.len();
+        self.lines.push_back(line);
+        while self.bytes + self.partial.len() > CAPACITY_BYTES {
+            let Some(dropped) = self.lines.pop_front() else { break };
+            self.bytes -= dropped.len();
+            self.first_line += 1;
+        }
+    }
+
+    fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(!self.partial.is_empty())
+    }
+
+    /// 行号与内容，含尚未换行的最后一行
+    fn iter(&self) -> impl Iterator<Item = (u64, &str)> {
+        let partial = (!self.partial.is_empty()).then_some(self.partial.as_str());
+        self.lines
+            .iter()
+            .map(String::as_str)
+            .chain(partial)
+            .enumerate()
+            .map(move |(i, line)| (self.first_line + i as u64, line))
+    }
+}
+
+/// 回滚缓冲区，只记录登记过的终端，每个终端最多保留 CAPACITY_BYTES 字节
+#[derive(Clone, Default)]
+pub struct ScrollbackRegistry {
+    // 每个终端单独加锁，搜索大缓冲区时不阻塞其他终端的输出
+    buffers: Arc<Mutex<HashMap<String, Arc<Mutex<Scrollback>>>>>,
+}
+
+impl ScrollbackRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 终端创建时登记；只有登记过的终端才记录输出
+    pub fn register(&self, terminal_id: &str) {
+        self.buffers
+            .lock()
+            .unwrap()
+            .insert(terminal_id.to_string(), Arc::new(Mutex::new(Scrollback::new())));
+    }
+
+    /// 追加终端输出（已解码的文本）；关闭后仍在途的输出不会重新建立缓冲区
+    pub fn output(&self, terminal_id: &str, text: &str) {
+        let buffer = self.buffers.lock().unwrap().get(terminal_id).cloned();
+        if let Some(buffer) = buffer {
+            buffer.lock().unwrap().push(text);
+        }
+    }
+
+    /// 终端标签页关闭时丢弃；shell 退出后缓冲区保留，仍可搜索和导出
+    pub fn remove(&self, terminal_id: &str) {
+        self.buffers.lock().unwrap().remove(terminal_id);
+    }
+
+    pub fn info(&self, terminal_id: &str) -> Result<ScrollbackInfo, String> {
+        let buffer = self.buffer(terminal_id)?;
+        let buffer = buffer.lock().unwrap();
+        Ok(ScrollbackInfo {
+            terminal_id: terminal_id.to_string(),
+            first_line: buffer.first_line,
+            line_count: buffer.line_count(),
+            bytes: buffer.bytes + buffer.partial.len(),
+            capacity_bytes: CAPACITY_BYTES,
+        })
+    }
+
+    /// 在去除 ANSI 控制序列后的文本中按正则搜索
+    pub fn search(
+        &self,
+        terminal_id: &str,
+        pattern: &str,
+        options: ScrollbackSearchOptions,
+    ) -> Result<ScrollbackSearchResult, String> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid search pattern: {}", e))?;
+        let buffer = self.buffer(terminal_id)?;
+        let buffer = buffer.lock().unwrap();
+
+        let mut result = ScrollbackSearchResult { matches: Vec::new(), truncated: false };
+        for (line, raw) in buffer.iter() {
+            let text = strip_ansi(raw);
+            for m in regex.find_iter(&text) {
+                if result.matches.len() >= options.max_matches {
+                    result.truncated = true;
+                    return Ok(result);
+                }
+                let start = text[..m.start()].chars().count();
+                result.matches.push(ScrollbackMatch {
+                    line,
+                    text: text.clone(),
+                    start,
+                    end: start + m.as_str().chars().count(),
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// 取 start 起的 count 行；strip_ansi 为 false 时保留控制序列，供前端重新绘制
+    pub fn range(&self, terminal_id: &str, start: u64, count: usize, strip: bool) -> Result<ScrollbackRange, String> {
+        let buffer = self.buffer(terminal_id)?;
+        let buffer = buffer.lock().unwrap();
+        let start = start.max(buffer.first_line);
+        let lines = buffer
+            .iter()
+            .skip((start - buffer.first_line) as usize)
+            .take(count)
+            .map(|(_, line)| if strip { strip_ansi(line) } else { line.to_string() })
+            .collect();
+        Ok(ScrollbackRange {
+            start,
+            lines,
+            first_line: buffer.first_line,
+            line_count: buffer.line_count(),
+        })
+    }
+
+    /// 把整个缓冲区去除控制序列后写入文本文件，返回写入的行数
+    pub fn export(&self, terminal_id: &str, path: &Path) -> Result<usize, String> {
+        let buffer = self.buffer(terminal_id)?;
+        let (transcript, lines) = {
+            let buffer = buffer.lock().unwrap();
+            let mut transcript = String::new();
+            for (_, line) in buffer.iter() {
+                transcript.push_str(&strip_ansi(line));
+                transcript.push('\n');
+            }
+            (transcript, buffer.line_count())
+        };
+        std::fs::write(path, transcript).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(lines)
+    }
+
+    fn buffer(&self, terminal_id: &str) -> Result<Arc<Mutex<Scrollback>>, String> {
+        self.buffers
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .cloned()
+            .ok_or_else(|| format!("No scrollback for terminal: {}", terminal_id))
+    }
+}
+
+/// 去除一行中的 ANSI 转义序列与控制字符；回车、退格与行尾擦除按终端的覆盖效果处理
+pub fn strip_ansi(line: &str) -> String {
+    let mut out: Vec<char> = Vec::with_capacity(line.len());
+    let mut cursor = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI：参数与中间字节之后以 0x40-0x7E 结束
+                Some('[') => {
+                    let mut params = String::new();
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            // 擦除到行尾（ESC[K / ESC[0K）
+                            if c == 'K' && (params.is_empty() || params == "0") {
+                                out.truncate(cursor);
+                            }
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                // OSC（窗口标题等）以及 DCS/SOS/PM/APC：到 BEL 或 ST 为止
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                // 其余 ESC 序列：中间字节之后跟一个结束字符，如 ESC ( B
+                Some(c) if (' '..='/').contains(&c) => {
+                    while chars.next_if(|c| (' '..='/').contains(c)).is_some() {}
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\r' => cursor = 0,
+            '\x08' => cursor = cursor.saturating_sub(1),
+            '\t' => put(&mut out, &mut cursor, c),
+            c if c.is_control() => {}
+            c => put(&mut out, &mut cursor, c),
+        }
+    }
+    out.into_iter().collect()
+}
+
+fn put(out: &mut Vec<char>, cursor: &mut usize, c: char) {
+    if *cursor < out.len() {
+        out[*cursor] = c;
+    } else {
+        out.push(c);
+    }
+    *cursor += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_escape_sequences() {
+        assert_eq!(strip_ansi("\x1b[01;34mlogs\x1b[0m  \x1b]0;root@web: ~\x07auth.log"), "logs  auth.log");
+        assert_eq!(strip_ansi("\x1b(Bok\x1b="), "ok");
+        // 进度条用回车覆盖同一行
+        assert_eq!(strip_ansi("  10%\r 100%"), " 100%");
+        assert_eq!(strip_ansi("downloading...\r\x1b[Kdone"), "done");
+        assert_eq!(strip_ansi("lss\x08 \x08"), "ls ");
+    }
+
+    #[test]
+    fn keeps_lines_across_chunks_and_searches() {
+        let registry = ScrollbackRegistry::new();
+        registry.register("t1");
+        registry.output("t1", "$ grep -i fail\r\nFailed password for root\r\n");
+        registry.output("t1", "\x1b[31mFAILED\x1b[0m login 日志\r\n$ ");
+
+        let range = registry.range("t1", 0, 10, true).unwrap();
+        assert_eq!(range.lines, vec!["$ grep -i fail", "Failed password for root", "FAILED login 日志", "$ "]);
+        assert_eq!(registry.range("t1", 2, 1, false).unwrap().lines, vec!["\x1b[31mFAILED\x1b[0m login 日志"]);
+
+        let found = registry.search("t1", "fail(ed)?", ScrollbackSearchOptions::default()).unwrap();
+        let lines: Vec<u64> = found.matches.iter().map(|m| m.line).collect();
+        assert_eq!(lines, vec![0, 1, 2]);
+        let case_sensitive = ScrollbackSearchOptions { case_sensitive: true, max_matches: 1 };
+        let found = registry.search("t1", "[A-Z]+ login", case_sensitive).unwrap();
+        assert_eq!((found.matches[0].start, found.matches[0].end), (0, 12));
+        assert!(registry.search("t1", "(", ScrollbackSearchOptions::default()).is_err());
+        assert!(registry.info("t2").is_err());
+
+        // 关闭后迟到的输出不会重新建立缓冲区
+        registry.remove("t1");
+        registry.output("t1", "late output\r\n");
+        assert!(registry.info("t1").is_err());
+    }
+
+    #[test]
+    fn caps_the_buffer_by_bytes() {
+        let mut buffer = Scrollback::new();
+        let line = "x".repeat(MAX_LINE_BYTES - 1);
+        let lines = CAPACITY_BYTES / line.len() + 10;
+        for _ in 0..lines {
+            buffer.push(&line);
+            buffer.push("\n");
+        }
+        assert!(buffer.bytes <= CAPACITY_BYTES);
+        assert_eq!(buffer.first_line as usize + buffer.lines.len(), lines);
+        assert_eq!(buffer.bytes, buffer.lines.len() * line.len());
+    }
+}
@@ -0,0 +1,1210 @@
+// 终端内的 ZMODEM 文件传输（rz/sz）
+// 在终端输出中识别 ZMODEM 起始帧，询问前端后接管该终端的数据流完成收发；
+// 每个文件作为一条传输记录登记在传输队列中，进度通过与 SFTP 传输相同的事件上报
+
+use crate::ssh_file_transfer::{ProgressReporter, TransferDirection, TransferJob, TransferOptions, TransferQueue};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::Emitter;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+
+/// 检测到 rz/sz 时请求前端选择文件或目录
+pub const REQUEST_EVENT: &str = "ssh_zmodem_request";
+/// 一次 ZMODEM 会话结束
+pub const FINISHED_EVENT: &str = "ssh_zmodem_finished";
+/// 传输记录中远程一侧的路径前缀（ZMODEM 不知道远程的完整路径）
+pub const REMOTE_PATH_PREFIX: &str = "zmodem:";
+
+/// 等待前端应答的时间
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(120);
+/// 等待对端数据的时间，超时后重发最近的请求
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// 连续出错（超时、校验失败）的次数上限
+const MAX_ERRORS: u32 = 10;
+/// 发送时每个数据子包的长度
+const SUBPACKET_SIZE: usize = 1024;
+/// 发送时每隔多少字节等待一次接收方确认
+const WINDOW_SIZE: u64 = 64 * 1024;
+/// 接收时单个数据子包的长度上限
+const MAX_SUBPACKET_SIZE: usize = 32 * 1024;
+/// 寻找帧头时允许跳过的字节数
+const MAX_GARBAGE: usize = 64 * 1024;
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const CAN: u8 = 0x18;
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+
+// 帧类型
+const ZRQINIT: u8 = 0;
+const ZRINIT: u8 = 1;
+const ZSINIT: u8 = 2;
+const ZACK: u8 = 3;
+const ZFILE: u8 = 4;
+const ZSKIP: u8 = 5;
+const ZNAK: u8 = 6;
+const ZABORT: u8 = 7;
+const ZFIN: u8 = 8;
+const ZRPOS: u8 = 9;
+const ZDATA: u8 = 10;
+const ZEOF: u8 = 11;
+const ZCRC: u8 = 13;
+const ZCAN: u8 = 16;
+const ZFREECNT: u8 = 17;
+const ZCOMMAND: u8 = 18;
+
+// 数据子包结束方式
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+const ZRUB0: u8 = b'l';
+const ZRUB1: u8 = b'm';
+
+// ZRINIT 能力标志
+const CANFDX: u8 = 0x01;
+const CANOVIO: u8 = 0x02;
+const CANFC32: u8 = 0x20;
+// ZFILE 转换选项：二进制
+const ZCBIN: u8 = 1;
+
+/// 远程 sz 发出的 ZRQINIT 帧头开头
+const SEND_START: &[u8] = b"**\x18B00";
+/// 远程 rz 发出的 ZRINIT 帧头开头
+const RECEIVE_START: &[u8] = b"**\x18B01";
+
+/// 在终端输出中查找 ZMODEM 起始帧，返回其位置与本地的传输方向
+pub fn detect(data: &[u8]) -> Option<(usize, TransferDirection)> {
+    data.windows(SEND_START.len()).enumerate().find_map(|(i, w)| {
+        if w == SEND_START {
+            Some((i, TransferDirection::Download))
+        } else if w == RECEIVE_START {
+            Some((i, TransferDirection::Upload))
+        } else {
+            None
+        }
+    })
+}
+
+/// 跨输出块查找起始帧：每块末尾保留不足一个帧头长度的字节，与下一块拼接后再查找
+#[derive(Debug, Default)]
+pub struct StartDetector {
+    tail: Vec<u8>,
+}
+
+/// 检测到的起始帧
+#[derive(Debug, PartialEq)]
+pub struct DetectedStart {
+    /// 本块中在起始帧之前、仍应作为终端输出显示的字节数
+    pub output_end: usize,
+    pub direction: TransferDirection,
+    /// 从起始帧开始的数据（含上一块末尾属于帧头的部分）
+    pub frame: Vec<u8>,
+}
+
+impl StartDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn scan(&mut self, data: &[u8]) -> Option<DetectedStart> {
+        let mut combined = std::mem::take(&mut self.tail);
+        let carried = combined.len();
+        combined.extend_from_slice(data);
+        match detect(&combined) {
+            Some((start, direction)) => Some(DetectedStart {
+                output_end: start.saturating_sub(carried),
+                direction,
+                frame: combined.split_off(start),
+            }),
+            None => {
+                let keep = combined.len().min(SEND_START.len() - 1);
+                self.tail = combined.split_off(combined.len() - keep);
+                None
+            }
+        }
+    }
+
+    /// 通道重新建立后之前的输出不再与新输出相连
+    pub fn reset(&mut self) {
+        self.tail.clear();
+    }
+}
+
+/// 前端对 ZMODEM 请求的应答
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ZmodemAnswer {
+    /// 远程 sz：保存到本地目录
+    Receive { directory: String },
+    /// 远程 rz：发送这些本地文件
+    Send { paths: Vec<String> },
+    Cancel,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestPayload<'a> {
+    terminal_id: &'a str,
+    direction: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FinishedPayload<'a> {
+    terminal_id: &'a str,
+    direction: &'static str,
+    files: usize,
+    error: Option<String>,
+}
+
+enum ZmodemInput {
+    Data(Vec<u8>),
+    Cancel,
+}
+
+/// 每个终端至多一个 ZMODEM 会话：先在 pending 中等待前端选择，确认后进入 active 接管终端数据流
+#[derive(Clone)]
+pub struct ZmodemHub {
+    transfers: TransferQueue,
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<ZmodemAnswer>>>>,
+    active: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<ZmodemInput>>>>,
+}
+
+impl ZmodemHub {
+    pub fn new(transfers: TransferQueue) -> Self {
+        Self {
+            transfers,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_active(&self, terminal_id: &str) -> bool {
+        self.active.lock().unwrap().contains_key(terminal_id)
+    }
+
+    /// 把终端输出交给进行中的会话；没有进行中的会话时返回 false
+    pub fn forward(&self, terminal_id: &str, data: &[u8]) -> bool {
+        let mut active = self.active.lock().unwrap();
+        match active.get(terminal_id) {
+            Some(tx) if tx.send(ZmodemInput::Data(data.to_vec())).is_ok() => true,
+            Some(_) => {
+                active.remove(terminal_id);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// 前端的选择结果
+    pub fn respond(&self, terminal_id: &str, answer: ZmodemAnswer) -> Result<(), String> {
+        let tx = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(terminal_id)
+            .ok_or_else(|| format!("No pending ZMODEM request on terminal: {}", terminal_id))?;
+        tx.send(answer).map_err(|_| "ZMODEM request has expired".to_string())
+    }
+
+    /// 取消终端上进行中的会话（用户在终端中按下 Ctrl+C 等）
+    pub fn cancel(&self, terminal_id: &str) -> bool {
+        if let Some(tx) = self.pending.lock().unwrap().remove(terminal_id) {
+            let _ = tx.send(ZmodemAnswer::Cancel);
+            return true;
+        }
+        self.active
+            .lock()
+            .unwrap()
+            .get(terminal_id)
+            .is_some_and(|tx| tx.send(ZmodemInput::Cancel).is_ok())
+    }
+
+    /// 终端关闭：结束其上的会话
+    pub fn detach(&self, terminal_id: &str) {
+        self.pending.lock().unwrap().remove(terminal_id);
+        self.active.lock().unwrap().remove(terminal_id);
+    }
+
+    /// 检测到起始帧后接管终端数据流；initial 为从起始帧开始的输出
+    pub fn start<W>(
+        &self,
+        terminal_id: &str,
+        session_id: &str,
+        direction: TransferDirection,
+        initial: &[u8],
+        writer: W,
+        window: tauri::Window,
+    ) where
+        W: AsyncWrite + Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let _ = tx.send(ZmodemInput::Data(initial.to_vec()));
+        self.active.lock().unwrap().insert(terminal_id.to_string(), tx);
+
+        let session = Session {
+            reader: FrameReader::new(rx),
+            writer: Box::pin(writer),
+            transfers: self.transfers.clone(),
+            session_id: session_id.to_string(),
+            window,
+        };
+        tokio::spawn(run_session(self.clone(), terminal_id.to_string(), direction, session));
+    }
+}
+
+async fn run_session(hub: ZmodemHub, terminal_id: String, direction: TransferDirection, mut session: Session) {
+    let (answer_tx, answer_rx) = oneshot::channel();
+    hub.pending.lock().unwrap().insert(terminal_id.clone(), answer_tx);
+    let _ = session.window.emit(
+        REQUEST_EVENT,
+        RequestPayload { terminal_id: &terminal_id, direction: direction.as_str() },
+    );
+    let answer = match tokio::time::timeout(ANSWER_TIMEOUT, answer_rx).await {
+        Ok(Ok(answer)) => answer,
+        _ => ZmodemAnswer::Cancel,
+    };
+    hub.pending.lock().unwrap().remove(&terminal_id);
+
+    let result = match (direction, answer) {
+        (_, ZmodemAnswer::Cancel) => Err(ZError::Cancelled),
+        (TransferDirection::Download, ZmodemAnswer::Receive { directory }) => session.receive(Path::new(&directory)).await,
+        (TransferDirection::Upload, ZmodemAnswer::Send { paths }) => session.send_files(&paths).await,
+        _ => Err(ZError::Failed("ZMODEM answer does not match the transfer direction".to_string())),
+    };
+    if let Err(e) = &result {
+        if !matches!(e, ZError::Aborted | ZError::Closed) {
+            session.abort().await;
+        }
+    }
+
+    hub.active.lock().unwrap().remove(&terminal_id);
+    // 会话结束后到达的输出（如 shell 提示符）交还给终端
+    if result.is_ok() {
+        let rest = session.reader.drain();
+        if !rest.is_empty() {
+            let _ = session.window.emit(
+                "ssh_terminal_data",
+                serde_json::json!({"terminalId": terminal_id, "data": String::from_utf8_lossy(&rest)}),
+            );
+        }
+    }
+
+    let (files, error) = match result {
+        Ok(files) => (files, None),
+        Err(e) => (0, Some(e.to_string())),
+    };
+    let _ = session.window.emit(
+        FINISHED_EVENT,
+        FinishedPayload { terminal_id: &terminal_id, direction: direction.as_str(), files, error },
+    );
+}
+
+#[derive(Debug)]
+enum ZError {
+    Timeout,
+    Garbled,
+    /// 对端发送了取消序列
+    Aborted,
+    /// 终端已关闭
+    Closed,
+    /// 本地取消
+    Cancelled,
+    Failed(String),
+}
+
+impl ZError {
+    /// 超时与校验失败可以通过重发请求恢复
+    fn is_retryable(&self) -> bool {
+        matches!(self, ZError::Timeout | ZError::Garbled)
+    }
+}
+
+impl std::fmt::Display for ZError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZError::Timeout => write!(f, "Timed out waiting for the remote side"),
+            ZError::Garbled => write!(f, "Too many corrupted ZMODEM frames"),
+            ZError::Aborted => write!(f, "Transfer cancelled by the remote side"),
+            ZError::Closed => write!(f, "Terminal closed"),
+            ZError::Cancelled => write!(f, "Transfer cancelled"),
+            ZError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// ================== 编码 ==================
+
+/// CRC-16/XMODEM
+fn crc16_update(mut crc: u16, data: &[u8]) -> u16 {
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    for part in parts {
+        crc.update(part);
+    }
+    crc.sum()
+}
+
+/// ZDLE 转义：ZDLE 本身与流控字符
+fn escape_into(out: &mut Vec<u8>, data: &[u8]) {
+    for &b in data {
+        match b {
+            ZDLE | 0x10 | 0x90 | XON | 0x91 | XOFF | 0x93 => out.extend([ZDLE, b ^ 0x40]),
+            _ => out.push(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+    kind: u8,
+    /// ZP0..ZP3（位置，小端）或 ZF3..ZF0（标志）
+    data: [u8; 4],
+}
+
+impl Header {
+    fn new(kind: u8, data: [u8; 4]) -> Self {
+        Self { kind, data }
+    }
+
+    fn with_position(kind: u8, position: u64) -> Self {
+        Self::new(kind, (position as u32).to_le_bytes())
+    }
+
+    fn position(&self) -> u64 {
+        u32::from_le_bytes(self.data) as u64
+    }
+
+    fn bytes(&self) -> [u8; 5] {
+        [self.kind, self.data[0], self.data[1], self.data[2], self.data[3]]
+    }
+
+    fn to_hex(self) -> Vec<u8> {
+        let bytes = self.bytes();
+        let mut out = b"**\x18B".to_vec();
+        out.extend(hex::encode(bytes).into_bytes());
+        out.extend(hex::encode(crc16_update(0, &bytes).to_be_bytes()).into_bytes());
+        out.extend(b"\r\x8a");
+        if self.kind != ZFIN && self.kind != ZACK {
+            out.push(XON);
+        }
+        out
+    }
+
+    fn to_binary(self) -> Vec<u8> {
+        let bytes = self.bytes();
+        let mut out = vec![ZPAD, ZDLE, ZBIN];
+        escape_into(&mut out, &bytes);
+        escape_into(&mut out, &crc16_update(0, &bytes).to_be_bytes());
+        out
+    }
+}
+
+/// 带 CRC-16 的数据子包
+fn encode_subpacket(data: &[u8], end: u8) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 8);
+    escape_into(&mut out, data);
+    out.extend([ZDLE, end]);
+    let crc = crc16_update(crc16_update(0, data), &[end]);
+    escape_into(&mut out, &crc.to_be_bytes());
+    if end == ZCRCW {
+        out.push(XON);
+    }
+    out
+}
+
+// ================== 解码 ==================
+
+enum Escaped {
+    Byte(u8),
+    End(u8),
+}
+
+struct FrameReader {
+    input: mpsc::UnboundedReceiver<ZmodemInput>,
+    buffer: VecDeque<u8>,
+}
+
+impl FrameReader {
+    fn new(input: mpsc::UnboundedReceiver<ZmodemInput>) -> Self {
+        Self { input, buffer: VecDeque::new() }
+    }
+
+    async fn byte(&mut self) -> Result<u8, ZError> {
+        loop {
+            if let Some(b) = self.buffer.pop_front() {
+                return Ok(b);
+            }
+            match tokio::time::timeout(READ_TIMEOUT, self.input.recv()).await {
+                Err(_) => return Err(ZError::Timeout),
+                Ok(None) => return Err(ZError::Closed),
+                Ok(Some(ZmodemInput::Cancel)) => return Err(ZError::Cancelled),
+                Ok(Some(ZmodemInput::Data(data))) => self.buffer.extend(data),
+            }
+        }
+    }
+
+    /// 已缓冲但尚未读取的数据
+    fn drain(&mut self) -> Vec<u8> {
+        let mut rest: Vec<u8> = self.buffer.drain(..).collect();
+        while let Ok(input) = self.input.try_recv() {
+            if let ZmodemInput::Data(data) = input {
+                rest.extend(data);
+            }
+        }
+        rest
+    }
+
+    /// 读取一个去转义的字节，跳过流控字符；连续 5 个 CAN 表示对端取消
+    async fn escaped(&mut self) -> Result<Escaped, ZError> {
+        loop {
+            match self.byte().await? {
+                XON | XOFF | 0x91 | 0x93 => continue,
+                ZDLE => break,
+                b => return Ok(Escaped::Byte(b)),
+            }
+        }
+        let mut cans = 1;
+        loop {
+            let c = self.byte().await?;
+            return match c {
+                CAN => {
+                    cans += 1;
+                    if cans >= 5 {
+                        return Err(ZError::Aborted);
+                    }
+                    continue;
+                }
+                XON | XOFF | 0x91 | 0x93 => continue,
+                _ if cans > 1 => Err(ZError::Garbled),
+                ZCRCE | ZCRCG | ZCRCQ | ZCRCW => Ok(Escaped::End(c)),
+                ZRUB0 => Ok(Escaped::Byte(0x7f)),
+                ZRUB1 => Ok(Escaped::Byte(0xff)),
+                c if c & 0x60 == 0x40 => Ok(Escaped::Byte(c ^ 0x40)),
+                _ => Err(ZError::Garbled),
+            };
+        }
+    }
+
+    async fn escaped_byte(&mut self) -> Result<u8, ZError> {
+        match self.escaped().await? {
+            Escaped::Byte(b) => Ok(b),
+            Escaped::End(_) => Err(ZError::Garbled),
+        }
+    }
+
+    /// 读取下一个帧头，返回帧头及其后数据子包是否使用 CRC-32
+    async fn header(&mut self) -> Result<(Header, bool), ZError> {
+        let mut skipped = 0;
+        let mut cans = 0;
+        loop {
+            skipped += 1;
+            if skipped > MAX_GARBAGE {
+                return Err(ZError::Garbled);
+            }
+            match self.byte().await? {
+                ZPAD => cans = 0,
+                CAN => {
+                    cans += 1;
+                    if cans >= 5 {
+                        return Err(ZError::Aborted);
+                    }
+                    continue;
+                }
+                _ => {
+                    cans = 0;
+                    continue;
+                }
+            }
+
+            let mut b = self.byte().await?;
+            while b == ZPAD {
+                b = self.byte().await?;
+            }
+            if b != ZDLE {
+                continue;
+            }
+            return match self.byte().await? {
+                ZHEX => self.hex_header().await.map(|h| (h, false)),
+                ZBIN => self.binary_header(false).await.map(|h| (h, false)),
+                ZBIN32 => self.binary_header(true).await.map(|h| (h, true)),
+                _ => continue,
+            };
+        }
+    }
+
+    async fn hex_header(&mut self) -> Result<Header, ZError> {
+        let mut digits = [0u8; 14];
+        for digit in digits.iter_mut() {
+            *digit = self.byte().await? & 0x7f;
+        }
+        let mut bytes = [0u8; 7];
+        hex::decode_to_slice(digits, &mut bytes).map_err(|_| ZError::Garbled)?;
+        if crc16_update(0, &bytes[..5]).to_be_bytes() != bytes[5..] {
+            return Err(ZError::Garbled);
+        }
+        Ok(Header::new(bytes[0], [bytes[1], bytes[2], bytes[3], bytes[4]]))
+    }
+
+    async fn binary_header(&mut self, use_crc32: bool) -> Result<Header, ZError> {
+        let mut bytes = [0u8; 9];
+        let len = if use_crc32 { 9 } else { 7 };
+        for byte in bytes[..len].iter_mut() {
+            *byte = self.escaped_byte().await?;
+        }
+        let valid = if use_crc32 {
+            crc32(&[&bytes[..5]]).to_le_bytes() == bytes[5..9]
+        } else {
+            crc16_update(0, &bytes[..5]).to_be_bytes() == bytes[5..7]
+        };
+        if !valid {
+            return Err(ZError::Garbled);
+        }
+        Ok(Header::new(bytes[0], [bytes[1], bytes[2], bytes[3], bytes[4]]))
+    }
+
+    /// 读取一个数据子包到 out，返回其结束方式
+    async fn subpacket(&mut self, use_crc32: bool, out: &mut Vec<u8>) -> Result<u8, ZError> {
+        out.clear();
+        let end = loop {
+            match self.escaped().await? {
+                Escaped::Byte(_) if out.len() >= MAX_SUBPACKET_SIZE => return Err(ZError::Garbled),
+                Escaped::Byte(b) => out.push(b),
+                Escaped::End(end) => break end,
+            }
+        };
+        let valid = if use_crc32 {
+            let mut crc = [0u8; 4];
+            for byte in crc.iter_mut() {
+                *byte = self.escaped_byte().await?;
+            }
+            crc32(&[out, &[end]]).to_le_bytes() == crc
+        } else {
+            let crc = [self.escaped_byte().await?, self.escaped_byte().await?];
+            crc16_update(crc16_update(0, out), &[end]).to_be_bytes() == crc
+        };
+        if valid {
+            Ok(end)
+        } else {
+            Err(ZError::Garbled)
+        }
+    }
+}
+
+// ================== 会话 ==================
+
+struct Session {
+    reader: FrameReader,
+    writer: Pin<Box<dyn AsyncWrite + Send>>,
+    transfers: TransferQueue,
+    session_id: String,
+    window: tauri::Window,
+}
+
+/// 正在接收的文件
+struct IncomingFile {
+    file: tokio::fs::File,
+    name: String,
+    path: PathBuf,
+    offset: u64,
+    size: Option<u64>,
+    mtime: Option<u64>,
+    job: TransferJob,
+    reporter: ProgressReporter,
+}
+
+impl Session {
+    async fn send(&mut self, bytes: &[u8]) -> Result<(), ZError> {
+        self.writer
+            .write_all(bytes)
+            .await
+            .map_err(|e| ZError::Failed(format!("Failed to write to terminal: {}", e)))?;
+        self.writer
+            .flush()
+            .await
+            .map_err(|e| ZError::Failed(format!("Failed to write to terminal: {}", e)))
+    }
+
+    /// 发送取消序列，使远程的 rz/sz 退出
+    async fn abort(&mut self) {
+        let mut sequence = vec![CAN; 8];
+        sequence.extend([0x08; 8]);
+        let _ = self.send(&sequence).await;
+    }
+
+    fn enqueue(&self, direction: TransferDirection, source: &str, destination: &str) -> (TransferJob, ProgressReporter) {
+        let options = TransferOptions { resume: false, verify: false, preserve_mtime: true };
+        let (_, job) = self.transfers.enqueue(&self.session_id, direction, source, destination, options);
+        let mut reporter = job.reporter(Some(self.window.clone()));
+        reporter.set_status("in_progress");
+        (job, reporter)
+    }
+
+    // ---------- 接收（远程 sz） ----------
+
+    async fn receive(&mut self, directory: &Path) -> Result<usize, ZError> {
+        if !directory.is_dir() {
+            return Err(ZError::Failed(format!("Not a directory: {}", directory.display())));
+        }
+        let mut file = None;
+        let result = self.receive_files(directory, &mut file).await;
+        if let (Some(incoming), Err(e)) = (file, &result) {
+            finish_incoming(incoming, Err(e)).await;
+        }
+        result
+    }
+
+    async fn receive_files(&mut self, directory: &Path, file: &mut Option<IncomingFile>) -> Result<usize, ZError> {
+        // 缓冲中的 ZRQINIT 会触发第一个 ZRINIT
+        let zrinit = Header::new(ZRINIT, [0, 0, 0, CANFDX | CANOVIO | CANFC32]);
+        let mut received = 0;
+        let mut errors = 0;
+        let mut buf = Vec::new();
+        loop {
+            let (header, use_crc32) = match self.reader.header().await {
+                Ok(header) => header,
+                Err(e) if e.is_retryable() && errors < MAX_ERRORS => {
+                    errors += 1;
+                    let retry = match file {
+                        Some(f) => Header::with_position(ZRPOS, f.offset),
+                        None => zrinit,
+                    };
+                    self.send(&retry.to_hex()).await?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            match header.kind {
+                ZRQINIT => self.send(&zrinit.to_hex()).await?,
+                ZSINIT => match self.reader.subpacket(use_crc32, &mut buf).await {
+                    Ok(_) => self.send(&Header::new(ZACK, [0; 4]).to_hex()).await?,
+                    Err(e) if e.is_retryable() => self.send(&Header::new(ZNAK, [0; 4]).to_hex()).await?,
+                    Err(e) => return Err(e),
+                },
+                ZFILE => {
+                    match self.reader.subpacket(use_crc32, &mut buf).await {
+                        Ok(_) => {}
+                        Err(e) if e.is_retryable() && errors < MAX_ERRORS => {
+                            errors += 1;
+                            self.send(&Header::new(ZNAK, [0; 4]).to_hex()).await?;
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    }
+                    let (name, size, mtime) = parse_file_info(&buf);
+                    // 发送方没收到 ZRPOS 而重发的 ZFILE
+                    if file.as_ref().is_some_and(|f| f.name == name && f.offset == 0) {
+                        self.send(&Header::with_position(ZRPOS, 0).to_hex()).await?;
+                        continue;
+                    }
+                    if let Some(previous) = file.take() {
+                        finish_incoming(previous, Err(&ZError::Failed("Sender skipped to the next file".to_string()))).await;
+                    }
+                    let path = unique_destination(directory, &name);
+                    let created = tokio::fs::File::create(&path)
+                        .await
+                        .map_err(|e| ZError::Failed(format!("Failed to create {}: {}", path.display(), e)))?;
+                    let (job, mut reporter) = self.enqueue(
+                        TransferDirection::Download,
+                        &format!("{}{}", REMOTE_PATH_PREFIX, name),
+                        &path.to_string_lossy(),
+                    );
+                    reporter.report(|t| t.file_size = size.unwrap_or(0), true);
+                    *file = Some(IncomingFile { file: created, name, path, offset: 0, size, mtime, job, reporter });
+                    self.send(&Header::with_position(ZRPOS, 0).to_hex()).await?;
+                }
+                ZDATA => {
+                    let Some(incoming) = file.as_mut() else {
+                        self.send(&zrinit.to_hex()).await?;
+                        continue;
+                    };
+                    if header.position() != incoming.offset {
+                        self.send(&Header::with_position(ZRPOS, incoming.offset).to_hex()).await?;
+                        continue;
+                    }
+                    loop {
+                        if incoming.job.is_cancelled() {
+                            return Err(ZError::Cancelled);
+                        }
+                        let end = match self.reader.subpacket(use_crc32, &mut buf).await {
+                            Ok(end) => end,
+                            Err(e) if e.is_retryable() && errors < MAX_ERRORS => {
+                                errors += 1;
+                                self.send(&Header::with_position(ZRPOS, incoming.offset).to_hex()).await?;
+                                break;
+                            }
+                            Err(e) => return Err(e),
+                        };
+                        errors = 0;
+                        incoming
+                            .file
+                            .write_all(&buf)
+                            .await
+                            .map_err(|e| ZError::Failed(format!("Failed to write {}: {}", incoming.path.display(), e)))?;
+                        incoming.offset += buf.len() as u64;
+                        let (offset, size) = (incoming.offset, incoming.size);
+                        incoming.reporter.report(|t| set_progress(t, offset, size), false);
+
+                        match end {
+                            ZCRCW => {
+                                self.send(&Header::with_position(ZACK, offset).to_hex()).await?;
+                                break;
+                            }
+                            ZCRCQ => self.send(&Header::with_position(ZACK, offset).to_hex()).await?,
+                            ZCRCE => break,
+                            _ => {}
+                        }
+                    }
+                }
+                // 位置不符的 ZEOF 属于过期帧，忽略
+                ZEOF if file.as_ref().is_some_and(|f| f.offset == header.position()) => {
+                    if let Some(incoming) = file.take() {
+                        finish_incoming(incoming, Ok(())).await;
+                        received += 1;
+                    }
+                    self.send(&zrinit.to_hex()).await?;
+                }
+                ZFIN => {
+                    self.send(&Header::new(ZFIN, [0; 4]).to_hex()).await?;
+                    self.consume_over_and_out().await;
+                    return Ok(received);
+                }
+                ZFREECNT => self.send(&Header::new(ZACK, [0; 4]).to_hex()).await?,
+                ZCOMMAND => return Err(ZError::Failed("Remote commands over ZMODEM are not supported".to_string())),
+                ZCAN | ZABORT => return Err(ZError::Aborted),
+                _ => {}
+            }
+        }
+    }
+
+    /// 读掉发送方最后的 "OO"，避免显示在终端上
+    async fn consume_over_and_out(&mut self) {
+        for _ in 0..2 {
+            let next = tokio::time::timeout(Duration::from_millis(500), self.reader.byte()).await;
+            match next {
+                Ok(Ok(b'O')) => {}
+                Ok(Ok(other)) => {
+                    self.reader.buffer.push_front(other);
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    // ---------- 发送（远程 rz） ----------
+
+    async fn send_files(&mut self, paths: &[String]) -> Result<usize, ZError> {
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let metadata = tokio::fs::metadata(path)
+                .await
+                .map_err(|e| ZError::Failed(format!("Failed to read {}: {}", path, e)))?;
+            if !metadata.is_file() {
+                return Err(ZError::Failed(format!("Not a file: {}", path)));
+            }
+            if metadata.len() > u32::MAX as u64 {
+                return Err(ZError::Failed(format!("File is too large for ZMODEM (4 GiB limit): {}", path)));
+            }
+            files.push((path.as_str(), metadata));
+        }
+
+        let rinit = self.wait_for_zrinit().await?;
+        // 接收方缓冲区大小（ZP0、ZP1），0 表示可连续接收
+        let buffer_size = u16::from_le_bytes([rinit.data[0], rinit.data[1]]) as u64;
+        let window = if buffer_size > 0 { buffer_size.min(WINDOW_SIZE) } else { WINDOW_SIZE };
+
+        let mut sent = 0;
+        let mut bytes_left: u64 = files.iter().map(|(_, m)| m.len()).sum();
+        for (index, (path, metadata)) in files.iter().enumerate() {
+            let (job, mut reporter) = self.enqueue(
+                TransferDirection::Upload,
+                path,
+                &format!("{}{}", REMOTE_PATH_PREFIX, file_name(path)),
+            );
+            let result = self
+                .send_file(path, metadata, files.len() - index, bytes_left, window, &job, &mut reporter)
+                .await;
+            bytes_left -= metadata.len();
+            match result {
+                Ok(true) => {
+                    finish_report(&mut reporter, Ok(()));
+                    sent += 1;
+                }
+                Ok(false) => finish_report(&mut reporter, Err(&ZError::Failed("Skipped by the receiver".to_string()))),
+                Err(e) => {
+                    finish_report(&mut reporter, Err(&e));
+                    return Err(e);
+                }
+            }
+        }
+
+        let zfin = Header::new(ZFIN, [0; 4]).to_hex();
+        for _ in 0..3 {
+            self.send(&zfin).await?;
+            match self.reader.header().await {
+                Ok((header, _)) if header.kind == ZFIN => break,
+                Ok(_) => continue,
+                Err(e) if e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        self.send(b"OO").await?;
+        Ok(sent)
+    }
+
+    async fn wait_for_zrinit(&mut self) -> Result<Header, ZError> {
+        let mut errors = 0;
+        loop {
+            match self.reader.header().await {
+                Ok((header, _)) if header.kind == ZRINIT => return Ok(header),
+                Ok((header, _)) if header.kind == ZCAN || header.kind == ZABORT => return Err(ZError::Aborted),
+                Ok(_) => continue,
+                Err(e) if e.is_retryable() && errors < MAX_ERRORS => {
+                    errors += 1;
+                    self.send(&Header::new(ZRQINIT, [0; 4]).to_hex()).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 发送一个文件；接收方跳过时返回 false
+    #[allow(clippy::too_many_arguments)]
+    async fn send_file(
+        &mut self,
+        path: &str,
+        metadata: &std::fs::Metadata,
+        files_left: usize,
+        bytes_left: u64,
+        window: u64,
+        job: &TransferJob,
+        reporter: &mut ProgressReporter,
+    ) -> Result<bool, ZError> {
+        let size = metadata.len();
+        reporter.report(|t| t.file_size = size, true);
+
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777;
+        #[cfg(not(unix))]
+        let mode = 0o644;
+
+        let mut info = file_name(path).into_bytes();
+        info.push(0);
+        info.extend(format!("{} {:o} {:o} 0 {} {}", size, mtime, mode, files_left, bytes_left).into_bytes());
+        info.push(0);
+
+        // ZFILE，直到接收方给出起始位置
+        let mut errors = 0;
+        let mut position = loop {
+            let mut frame = Header::new(ZFILE, [0, 0, 0, ZCBIN]).to_binary();
+            frame.extend(encode_subpacket(&info, ZCRCW));
+            self.send(&frame).await?;
+            match self.wait_reply(&mut errors).await? {
+                Some(header) if header.kind == ZRPOS => break header.position().min(size),
+                Some(header) if header.kind == ZSKIP => return Ok(false),
+                Some(header) if header.kind == ZCRC => {
+                    let crc = file_crc32(path).await?;
+                    self.send(&Header::new(ZCRC, crc.to_le_bytes()).to_hex()).await?;
+                }
+                _ => {}
+            }
+        };
+
+        let mut file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| ZError::Failed(format!("Failed to open {}: {}", path, e)))?;
+        let mut buf = vec![0u8; SUBPACKET_SIZE];
+        reporter.report(|t| t.resumed_from = position, true);
+
+        'frame: loop {
+            if job.is_cancelled() {
+                return Err(ZError::Cancelled);
+            }
+            file.seek(std::io::SeekFrom::Start(position))
+                .await
+                .map_err(|e| ZError::Failed(format!("Failed to seek {}: {}", path, e)))?;
+            self.send(&Header::with_position(ZDATA, position).to_binary()).await?;
+            let window_end = position + window;
+
+            loop {
+                let n = read_full(&mut file, &mut buf)
+                    .await
+                    .map_err(|e| ZError::Failed(format!("Failed to read {}: {}", path, e)))?;
+                let at_eof = position + n as u64 >= size || n < buf.len();
+                let end = if at_eof {
+                    ZCRCE
+                } else if position + n as u64 >= window_end {
+                    ZCRCW
+                } else {
+                    ZCRCG
+                };
+                self.send(&encode_subpacket(&buf[..n], end)).await?;
+                position += n as u64;
+                reporter.report(|t| set_progress(t, position, Some(size)), false);
+
+                if end == ZCRCG {
+                    continue;
+                }
+                if end == ZCRCW {
+                    // 等待确认后以新的 ZDATA 继续；ZRPOS 表示从该位置重发
+                    match self.wait_reply(&mut errors).await? {
+                        Some(header) if header.kind == ZRPOS => position = header.position().min(size),
+                        Some(header) if header.kind == ZSKIP => return Ok(false),
+                        _ => {}
+                    }
+                    continue 'frame;
+                }
+
+                loop {
+                    self.send(&Header::with_position(ZEOF, position).to_binary()).await?;
+                    match self.wait_reply(&mut errors).await? {
+                        Some(header) if header.kind == ZRINIT => return Ok(true),
+                        Some(header) if header.kind == ZSKIP => return Ok(false),
+                        Some(header) if header.kind == ZRPOS => {
+                            position = header.position().min(size);
+                            continue 'frame;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// 等待接收方的应答帧；超时或帧损坏时返回 None 以便重发，连续出错过多则失败
+    async fn wait_reply(&mut self, errors: &mut u32) -> Result<Option<Header>, ZError> {
+        match self.reader.header().await {
+            Ok((header, _)) if header.kind == ZCAN || header.kind == ZABORT || header.kind == ZFIN => {
+                Err(ZError::Aborted)
+            }
+            Ok((header, _)) if header.kind == ZNAK => self.count_error(errors),
+            Ok((header, _)) => {
+                *errors = 0;
+                Ok(Some(header))
+            }
+            Err(e) if e.is_retryable() => self.count_error(errors),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn count_error(&self, errors: &mut u32) -> Result<Option<Header>, ZError> {
+        *errors += 1;
+        if *errors > MAX_ERRORS {
+            Err(ZError::Timeout)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn set_progress(task: &mut crate::types::FileTransferTask, transferred: u64, size: Option<u64>) {
+    task.transferred_size = transferred;
+    task.progress = match size {
+        Some(size) if size > 0 => (transferred as f64 / size as f64 * 100.0).min(100.0),
+        _ => 0.0,
+    };
+}
+
+fn finish_report(reporter: &mut ProgressReporter, result: Result<(), &ZError>) {
+    reporter.report(
+        |t| {
+            match result {
+                Ok(()) => {
+                    t.status = "completed".to_string();
+                    t.progress = 100.0;
+                }
+                Err(ZError::Cancelled | ZError::Aborted) => t.status = "cancelled".to_string(),
+                Err(e) => {
+                    t.status = "failed".to_string();
+                    t.error_message = Some(e.to_string());
+                }
+            }
+            t.completed = Some(chrono::Utc::now());
+        },
+        true,
+    );
+}
+
+async fn finish_incoming(mut incoming: IncomingFile, result: Result<(), &ZError>) {
+    let result = match result {
+        Ok(()) => incoming
+            .file
+            .flush()
+            .await
+            .map_err(|e| ZError::Failed(format!("Failed to write {}: {}", incoming.path.display(), e))),
+        Err(ZError::Cancelled) => Err(ZError::Cancelled),
+        Err(ZError::Aborted) => Err(ZError::Aborted),
+        Err(e) => Err(ZError::Failed(e.to_string())),
+    };
+    let file = incoming.file.into_std().await;
+    match &result {
+        Ok(()) => {
+            if let Some(mtime) = incoming.mtime.filter(|&m| m > 0) {
+                let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime));
+            }
+        }
+        // 取消的传输不保留不完整的文件
+        Err(ZError::Cancelled | ZError::Aborted) => {
+            drop(file);
+            let _ = tokio::fs::remove_file(&incoming.path).await;
+        }
+        Err(_) => {}
+    }
+    finish_report(&mut incoming.reporter, result.as_ref().map(|_| ()));
+}
+
+/// 解析 ZFILE 数据子包："文件名\0大小 修改时间(八进制) 权限(八进制) ...\0"
+fn parse_file_info(data: &[u8]) -> (String, Option<u64>, Option<u64>) {
+    let mut parts = data.splitn(3, |&b| b == 0);
+    let name = sanitize_file_name(&String::from_utf8_lossy(parts.next().unwrap_or_default()));
+    let info = String::from_utf8_lossy(parts.next().unwrap_or_default()).into_owned();
+    let mut fields = info.split_whitespace();
+    let size = fields.next().and_then(|s| s.parse().ok());
+    let mtime = fields.next().and_then(|s| u64::from_str_radix(s, 8).ok());
+    (name, size, mtime)
+}
+
+/// 只保留文件名部分，远程不能借此写到目标目录之外
+fn sanitize_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+    match name {
+        "" | "." | ".." => "zmodem-file".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// 目标已存在时追加序号，不覆盖本地文件
+fn unique_destination(directory: &Path, name: &str) -> PathBuf {
+    let path = directory.join(name);
+    if !path.exists() {
+        return path;
+    }
+    (1..)
+        .map(|i| directory.join(format!("{}.{}", name, i)))
+        .find(|p| !p.exists())
+        .unwrap_or(path)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+async fn read_full(file: &mut tokio::fs::File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = file.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+async fn file_crc32(path: &str) -> Result<u32, ZError> {
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|e| ZError::Failed(format!("Failed to read {}: {}", path, e)))?;
+    Ok(crc32(&[&data]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader_with(data: Vec<u8>) -> FrameReader {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(ZmodemInput::Data(data)).unwrap();
+        FrameReader::new(rx)
+    }
+
+    #[test]
+    fn detects_start_frames() {
+        assert_eq!(crc16_update(0, b"123456789"), 0x31c3);
+        assert_eq!(detect(b"rz\r**\x18B00000000000000\r\x8a"), Some((3, TransferDirection::Download)));
+        assert_eq!(detect(b"**\x18B0100000023be50\r\x8a\x11"), Some((0, TransferDirection::Upload)));
+        assert_eq!(detect(b"ls -la\r\n"), None);
+    }
+
+    #[tokio::test]
+    async fn decodes_encoded_frames() {
+        let payload: Vec<u8> = (0..=255u8).chain([ZDLE, XON, 0x91, ZDLE]).collect();
+        let mut data = b"noise*".to_vec();
+        data.extend(Header::with_position(ZRPOS, 70000).to_hex());
+        data.extend(Header::with_position(ZDATA, 70000).to_binary());
+        data.extend(encode_subpacket(&payload, ZCRCG));
+        data.extend(encode_subpacket(b"", ZCRCE));
+
+        let mut reader = reader_with(data);
+        let (header, _) = reader.header().await.unwrap();
+        assert_eq!((header.kind, header.position()), (ZRPOS, 70000));
+        let (header, _) = reader.header().await.unwrap();
+        assert_eq!((header.kind, header.position()), (ZDATA, 70000));
+        let mut buf = Vec::new();
+        assert_eq!(reader.subpacket(false, &mut buf).await.unwrap(), ZCRCG);
+        assert_eq!(buf, payload);
+        assert_eq!(reader.subpacket(false, &mut buf).await.unwrap(), ZCRCE);
+        assert!(buf.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_remote_cancel() {
+        let mut reader = reader_with([CAN; 8].into_iter().chain([0x08; 8]).collect());
+        assert!(matches!(reader.header().await, Err(ZError::Aborted)));
+    }
+
+    #[test]
+    fn detects_start_frames_split_across_chunks() {
+        let mut detector = StartDetector::new();
+        assert_eq!(detector.scan(b"$ sz evidence.tar\r\n*"), None);
+        assert_eq!(detector.scan(b"*\x18"), None);
+        let found = detector.scan(b"B00000000000000\r\x8a").unwrap();
+        assert_eq!((found.output_end, found.direction), (0, TransferDirection::Download));
+        assert_eq!(found.frame, b"**\x18B00000000000000\r\x8a".to_vec());
+
+        // 帧头完整出现在一块中时，之前的输出照常显示
+        let mut detector = StartDetector::new();
+        assert_eq!(detector.scan(b"ls\r\n"), None);
+        let found = detector.scan(b"rz\r**\x18B0100000023be50\r\x8a\x11").unwrap();
+        assert_eq!((found.output_end, found.direction), (3, TransferDirection::Upload));
+        assert!(found.frame.starts_with(RECEIVE_START));
+    }
+
+    #[test]
+    fn parses_file_info_without_leaving_directory() {
+        let (name, size, mtime) = parse_file_info(b"../../etc/passwd\x00123 14540755043 100644 0 1 123\x00");
+        assert_eq!(name, "passwd");
+        assert_eq!(size, Some(123));
+        assert_eq!(mtime, Some(0o14540755043));
+        assert_eq!(sanitize_file_name(".."), "zmodem-file");
+    }
+}
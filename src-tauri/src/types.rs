@@ -11,6 +11,7 @@ pub struct AppDataPaths {
     pub ssh_connections_file: PathBuf,
     pub ssh_commands_file: PathBuf,
     pub docker_configs_file: PathBuf,
+    pub known_hosts_file: PathBuf,
     pub logs_dir: PathBuf,
     pub temp_dir: PathBuf,
     pub backups_dir: PathBuf,
@@ -32,6 +33,7 @@ impl AppDataPaths {
             ssh_connections_file: app_data_dir.join("ssh_connections.json"),
             ssh_commands_file: app_data_dir.join("ssh_commands.json"),
             docker_configs_file: app_data_dir.join("docker_configs.json"),
+            known_hosts_file: app_data_dir.join("known_hosts"),
             logs_dir: app_data_dir.join("logs"),
             temp_dir: app_data_dir.join("temp"),
             backups_dir: app_data_dir.join("backups"),
@@ -44,7 +46,7 @@ impl AppDataPaths {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SSHAccountCredential {
     pub username: String,
-    pub auth_type: String,                  // "password", "key", "certificate"
+    pub auth_type: String,                  // "password", "key", "certificate", "agent"
     pub encrypted_password: Option<String>, // AES加密的密码
     pub key_path: Option<String>,
     pub key_passphrase: Option<String>,     // SSH密钥的密码短语
@@ -64,7 +66,7 @@ pub struct SSHConnection {
     #[serde(default)]
     pub username: String,
     #[serde(default)]
-    pub auth_type: String,                  // "password", "key", "certificate"
+    pub auth_type: String,                  // "password", "key", "certificate", "agent"
     pub encrypted_password: Option<String>, // AES加密的密码
     pub key_path: Option<String>,
     pub key_passphrase: Option<String>,     // SSH密钥的密码短语
@@ -77,6 +79,8 @@ pub struct SSHConnection {
     pub is_connected: bool,
     pub last_connected: Option<chrono::DateTime<chrono::Utc>>,
     pub tags: Option<Vec<String>>,          // 连接标签
+    #[serde(default)]
+    pub jump_hosts: Vec<String>,            // 跳板机链（按顺序引用其他已保存连接的ID）
 }
 
 /// SSH命令配置
@@ -262,13 +266,21 @@ pub struct FileTransferTask {
     pub source_path: String,
     pub destination_path: String,
     pub transfer_type: String, // "upload", "download"
-    pub status: String,        // "pending", "in_progress", "completed", "failed"
+    pub status: String,        // "pending", "in_progress", "paused", "completed", "failed", "cancelled"
     pub progress: f64,         // 0.0 to 100.0
     pub file_size: u64,
     pub transferred_size: u64,
     pub created: chrono::DateTime<chrono::Utc>,
     pub completed: Option<chrono::DateTime<chrono::Utc>>,
     pub error_message: Option<String>,
+    #[serde(default)]
+    pub resumed_from: u64,                   // 断点续传的起始偏移
+    #[serde(default)]
+    pub local_sha256: Option<String>,
+    #[serde(default)]
+    pub remote_sha256: Option<String>,
+    #[serde(default)]
+    pub checksum_verified: Option<bool>,     // None 表示未校验（如远程没有 sha256sum）
 }
 
 /// 系统监控数据
@@ -404,6 +416,12 @@ pub enum LovelyResError {
     #[error("身份验证错误: {0}")]
     AuthenticationError(String),
 
+    #[error("主机密钥不匹配: {0}")]
+    HostKeyMismatch(String),
+
+    #[error("主机密钥已吊销: {0}")]
+    HostKeyRevoked(String),
+
     #[error("权限错误: {0}")]
     PermissionError(String),
 
@@ -444,6 +462,7 @@ impl Default for SSHConnection {
             is_connected: false,
             last_connected: None,
             tags: None,
+            jump_hosts: Vec::new(),
         }
     }
 }